@@ -0,0 +1,125 @@
+/*!
+ * Mock Pyth-style price oracle for local testing of the permission contract
+ *
+ * Implements `get_price_no_older_than` like pyth-oracle.near, with prices
+ * set directly by the owner instead of pushed from Pyth.
+ */
+
+use near_sdk::collections::LookupMap;
+use near_sdk::json_types::{I64, U64};
+use near_sdk::{env, near, AccountId, PanicOnDefault};
+
+/// Price of one whole asset in USD: price * 10^expo
+#[derive(Clone, Debug, PartialEq)]
+#[near(serializers = [borsh, json])]
+pub struct Price {
+    pub price: I64,
+    pub conf: U64,
+    pub expo: i32,
+    /// Unix seconds
+    pub publish_time: i64,
+}
+
+#[derive(PanicOnDefault)]
+#[near(contract_state)]
+pub struct MockOracle {
+    pub owner: AccountId,
+    /// price_id -> latest price
+    pub prices: LookupMap<String, Price>,
+}
+
+#[near]
+impl MockOracle {
+    #[init]
+    pub fn new(owner: AccountId) -> Self {
+        Self {
+            owner,
+            prices: LookupMap::new(b"p"),
+        }
+    }
+
+    /// Publish a price for a feed at the current block time (owner only)
+    pub fn set_price(&mut self, price_id: String, price: I64, expo: i32) {
+        self.assert_owner();
+        let publish_time = (env::block_timestamp() / 1_000_000_000) as i64;
+        self.set_price_at(price_id, price, expo, publish_time);
+    }
+
+    /// Publish a price with an explicit publish time, e.g. to simulate staleness (owner only)
+    pub fn set_price_at(&mut self, price_id: String, price: I64, expo: i32, publish_time: i64) {
+        self.assert_owner();
+        self.prices.insert(
+            &price_id,
+            &Price {
+                price,
+                conf: U64(0),
+                expo,
+                publish_time,
+            },
+        );
+    }
+
+    /// Latest price for a feed, whatever its age
+    pub fn get_price_unsafe(&self, price_id: String) -> Option<Price> {
+        self.prices.get(&price_id)
+    }
+
+    /// Latest price for a feed if published within `age` seconds
+    pub fn get_price_no_older_than(&self, price_id: String, age: u64) -> Option<Price> {
+        let price = self.prices.get(&price_id)?;
+        let now = (env::block_timestamp() / 1_000_000_000) as i64;
+        if now.saturating_sub(price.publish_time) > age as i64 {
+            return None;
+        }
+        Some(price)
+    }
+
+    fn assert_owner(&self) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner,
+            "Only owner can call this method"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+
+    fn context(predecessor: &str, timestamp_secs: u64) {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .predecessor_account_id(predecessor.parse().unwrap())
+            .block_timestamp(timestamp_secs * 1_000_000_000);
+        testing_env!(builder.build());
+    }
+
+    #[test]
+    fn test_price_expires_after_age() {
+        context("owner.near", 1_000);
+        let mut oracle = MockOracle::new("owner.near".parse().unwrap());
+        oracle.set_price("sol".to_string(), I64(15_000_000_000), -8);
+
+        let price = oracle.get_price_no_older_than("sol".to_string(), 60).unwrap();
+        assert_eq!(price.price, I64(15_000_000_000));
+        assert_eq!(price.publish_time, 1_000);
+
+        context("anyone.near", 1_061);
+        assert_eq!(oracle.get_price_no_older_than("sol".to_string(), 60), None);
+        assert!(oracle.get_price_unsafe("sol".to_string()).is_some());
+        assert_eq!(oracle.get_price_no_older_than("eth".to_string(), 60), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only owner can call this method")]
+    fn test_set_price_owner_only() {
+        context("owner.near", 1_000);
+        let mut oracle = MockOracle::new("owner.near".parse().unwrap());
+
+        context("attacker.near", 1_000);
+        oracle.set_price("sol".to_string(), I64(1), 0);
+    }
+}
@@ -0,0 +1,394 @@
+use near_sdk::env;
+
+use crate::message::SignedAction;
+use crate::types::{AllowedOperationInput, AllowedOperationType, PriceCondition};
+
+/// EIP-712 domain name
+pub const DOMAIN_NAME: &str = "ShadeLink Permissions";
+/// EIP-712 domain version
+pub const DOMAIN_VERSION: &str = "1";
+
+/// No chainId: wallets refuse to sign when it differs from their active chain,
+/// and a NEAR contract has no EVM chain to name
+const DOMAIN_TYPE: &str = "EIP712Domain(string name,string version,address verifyingContract)";
+const REGISTER_WALLET_TYPE: &str = "RegisterWallet(string derivationPath,uint64 nonce)";
+const ADD_OPERATION_TYPE: &str =
+    "AddOperation(string derivationPath,Operation operation,uint64 nonce)";
+const REMOVE_OPERATION_TYPE: &str =
+    "RemoveOperation(string derivationPath,string operationId,uint64 nonce)";
+const FREEZE_PATH_TYPE: &str = "FreezePath(string derivationPath,uint64 nonce)";
+const UNFREEZE_PATH_TYPE: &str = "UnfreezePath(string derivationPath,uint64 nonce)";
+const REMOVE_WALLET_TYPE: &str =
+    "RemoveWallet(string derivationPath,string walletAddress,bool closePath,uint64 nonce)";
+const ROTATE_WALLET_TYPE: &str = "RotateWallet(string derivationPath,string oldAddress,string newWalletType,bytes newPublicKey,string newChainAddress,uint64 nonce)";
+const LINK_WALLET_TYPE: &str = "LinkWallet(string derivationPath,string walletType,bytes publicKey,string chainAddress,string signerAddress,uint64 nonce)";
+const SET_THRESHOLD_TYPE: &str = "SetThreshold(string derivationPath,uint8 threshold,uint64 nonce)";
+const APPROVE_PROPOSAL_TYPE: &str =
+    "ApproveProposal(string derivationPath,string proposalId,uint64 nonce)";
+const SET_SPENDING_LIMIT_TYPE: &str =
+    "SetSpendingLimit(string derivationPath,string asset,uint256 limit,uint64 window,uint64 nonce)";
+const REMOVE_SPENDING_LIMIT_TYPE: &str =
+    "RemoveSpendingLimit(string derivationPath,string asset,uint64 nonce)";
+const ADD_DESTINATION_TYPE: &str =
+    "AddDestination(string derivationPath,string chain,string address,uint64 nonce)";
+const REMOVE_DESTINATION_TYPE: &str =
+    "RemoveDestination(string derivationPath,string chain,string address,uint64 nonce)";
+/// Flattened AllowedOperationInput; fields a variant lacks are "" / 0
+const OPERATION_TYPE: &str = "Operation(string operationType,string priceAsset,string quoteAsset,uint256 triggerPrice,string condition,string sourceAsset,string targetAsset,uint256 maxAmount,string sourceChain,string destinationAddress,string destinationChain,uint16 slippageBps,uint64 expiresAt,uint64 interval,uint32 maxExecutions,uint256 amountPerExecution,uint64 startAt,uint64 endAt)";
+
+/// Stand-in `verifyingContract` for a NEAR account: last 20 bytes of keccak256(account_id)
+pub fn verifying_contract(account_id: &str) -> [u8; 20] {
+    let hash = env::keccak256_array(account_id.as_bytes());
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    address
+}
+
+/// EIP-712 digest a wallet signs for `action` on the contract `account_id`
+pub fn action_digest(action: &SignedAction, account_id: &str) -> [u8; 32] {
+    let mut data = vec![0x19, 0x01];
+    data.extend_from_slice(&domain_separator(account_id));
+    data.extend_from_slice(&hash_action(action));
+    env::keccak256_array(&data)
+}
+
+fn domain_separator(account_id: &str) -> [u8; 32] {
+    hash_struct(
+        DOMAIN_TYPE,
+        &[
+            encode_string(DOMAIN_NAME),
+            encode_string(DOMAIN_VERSION),
+            encode_address(&verifying_contract(account_id)),
+        ],
+    )
+}
+
+fn hash_action(action: &SignedAction) -> [u8; 32] {
+    match action {
+        SignedAction::RegisterWallet {
+            derivation_path,
+            nonce,
+        } => hash_struct(
+            REGISTER_WALLET_TYPE,
+            &[encode_string(derivation_path), encode_uint(*nonce as u128)],
+        ),
+        SignedAction::AddOperation {
+            derivation_path,
+            operation,
+            nonce,
+        } => hash_struct(
+            &format!("{}{}", ADD_OPERATION_TYPE, OPERATION_TYPE),
+            &[
+                encode_string(derivation_path),
+                hash_operation(operation),
+                encode_uint(*nonce as u128),
+            ],
+        ),
+        SignedAction::RemoveOperation {
+            derivation_path,
+            operation_id,
+            nonce,
+        } => hash_struct(
+            REMOVE_OPERATION_TYPE,
+            &[
+                encode_string(derivation_path),
+                encode_string(operation_id),
+                encode_uint(*nonce as u128),
+            ],
+        ),
+        SignedAction::FreezePath {
+            derivation_path,
+            nonce,
+        } => hash_struct(
+            FREEZE_PATH_TYPE,
+            &[encode_string(derivation_path), encode_uint(*nonce as u128)],
+        ),
+        SignedAction::UnfreezePath {
+            derivation_path,
+            nonce,
+        } => hash_struct(
+            UNFREEZE_PATH_TYPE,
+            &[encode_string(derivation_path), encode_uint(*nonce as u128)],
+        ),
+        SignedAction::RemoveWallet {
+            derivation_path,
+            wallet_address,
+            close_path,
+            nonce,
+        } => hash_struct(
+            REMOVE_WALLET_TYPE,
+            &[
+                encode_string(derivation_path),
+                encode_string(wallet_address),
+                encode_uint(*close_path as u128),
+                encode_uint(*nonce as u128),
+            ],
+        ),
+        SignedAction::RotateWallet {
+            derivation_path,
+            old_address,
+            new_wallet,
+            nonce,
+        } => hash_struct(
+            ROTATE_WALLET_TYPE,
+            &[
+                encode_string(derivation_path),
+                encode_string(old_address),
+                encode_string(new_wallet.wallet_type.as_str()),
+                env::keccak256_array(&new_wallet.public_key),
+                encode_string(&new_wallet.chain_address),
+                encode_uint(*nonce as u128),
+            ],
+        ),
+        SignedAction::LinkWallet {
+            derivation_path,
+            new_wallet,
+            signer_address,
+            nonce,
+        } => hash_struct(
+            LINK_WALLET_TYPE,
+            &[
+                encode_string(derivation_path),
+                encode_string(new_wallet.wallet_type.as_str()),
+                env::keccak256_array(&new_wallet.public_key),
+                encode_string(&new_wallet.chain_address),
+                encode_string(signer_address),
+                encode_uint(*nonce as u128),
+            ],
+        ),
+        SignedAction::SetThreshold {
+            derivation_path,
+            threshold,
+            nonce,
+        } => hash_struct(
+            SET_THRESHOLD_TYPE,
+            &[
+                encode_string(derivation_path),
+                encode_uint(*threshold as u128),
+                encode_uint(*nonce as u128),
+            ],
+        ),
+        SignedAction::ApproveProposal {
+            derivation_path,
+            proposal_id,
+            nonce,
+        } => hash_struct(
+            APPROVE_PROPOSAL_TYPE,
+            &[
+                encode_string(derivation_path),
+                encode_string(proposal_id),
+                encode_uint(*nonce as u128),
+            ],
+        ),
+        SignedAction::SetSpendingLimit {
+            derivation_path,
+            asset,
+            limit,
+            window,
+            nonce,
+        } => hash_struct(
+            SET_SPENDING_LIMIT_TYPE,
+            &[
+                encode_string(derivation_path),
+                encode_string(asset),
+                encode_uint(*limit),
+                encode_uint(*window as u128),
+                encode_uint(*nonce as u128),
+            ],
+        ),
+        SignedAction::RemoveSpendingLimit {
+            derivation_path,
+            asset,
+            nonce,
+        } => hash_struct(
+            REMOVE_SPENDING_LIMIT_TYPE,
+            &[
+                encode_string(derivation_path),
+                encode_string(asset),
+                encode_uint(*nonce as u128),
+            ],
+        ),
+        SignedAction::AddDestination {
+            derivation_path,
+            chain,
+            address,
+            nonce,
+        } => hash_struct(
+            ADD_DESTINATION_TYPE,
+            &[
+                encode_string(derivation_path),
+                encode_string(chain),
+                encode_string(address),
+                encode_uint(*nonce as u128),
+            ],
+        ),
+        SignedAction::RemoveDestination {
+            derivation_path,
+            chain,
+            address,
+            nonce,
+        } => hash_struct(
+            REMOVE_DESTINATION_TYPE,
+            &[
+                encode_string(derivation_path),
+                encode_string(chain),
+                encode_string(address),
+                encode_uint(*nonce as u128),
+            ],
+        ),
+    }
+}
+
+fn hash_operation(operation: &AllowedOperationInput) -> [u8; 32] {
+    let (operation_type, price_asset, quote_asset, trigger_price, condition) =
+        match &operation.operation_type {
+            AllowedOperationType::Swap { .. } => ("Swap", "", "", 0, ""),
+            AllowedOperationType::LimitOrder {
+                price_asset,
+                quote_asset,
+                trigger_price,
+                condition,
+                ..
+            } => (
+                "LimitOrder",
+                price_asset.as_str(),
+                quote_asset.as_str(),
+                trigger_price.0,
+                match condition {
+                    PriceCondition::Above => "Above",
+                    PriceCondition::Below => "Below",
+                },
+            ),
+            AllowedOperationType::StopLoss {
+                price_asset,
+                quote_asset,
+                trigger_price,
+                ..
+            } => (
+                "StopLoss",
+                price_asset.as_str(),
+                quote_asset.as_str(),
+                trigger_price.0,
+                "",
+            ),
+            AllowedOperationType::TakeProfit {
+                price_asset,
+                quote_asset,
+                trigger_price,
+                ..
+            } => (
+                "TakeProfit",
+                price_asset.as_str(),
+                quote_asset.as_str(),
+                trigger_price.0,
+                "",
+            ),
+        };
+
+    let schedule = operation.recurring.as_ref();
+    hash_struct(
+        OPERATION_TYPE,
+        &[
+            encode_string(operation_type),
+            encode_string(price_asset),
+            encode_string(quote_asset),
+            encode_uint(trigger_price),
+            encode_string(condition),
+            encode_string(operation.operation_type.source_asset()),
+            encode_string(operation.operation_type.target_asset()),
+            encode_uint(operation.operation_type.max_amount()),
+            encode_string(&operation.source_chain),
+            encode_string(&operation.destination_address),
+            encode_string(&operation.destination_chain),
+            encode_uint(operation.slippage_bps as u128),
+            encode_uint(operation.expires_at.unwrap_or(0) as u128),
+            encode_uint(schedule.map_or(0, |s| s.interval) as u128),
+            encode_uint(schedule.map_or(0, |s| s.max_executions) as u128),
+            encode_uint(schedule.map_or(0, |s| s.amount_per_execution.0)),
+            encode_uint(schedule.map_or(0, |s| s.start_at) as u128),
+            encode_uint(schedule.and_then(|s| s.end_at).unwrap_or(0) as u128),
+        ],
+    )
+}
+
+fn hash_struct(encoded_type: &str, fields: &[[u8; 32]]) -> [u8; 32] {
+    let mut data = env::keccak256_array(encoded_type.as_bytes()).to_vec();
+    for field in fields {
+        data.extend_from_slice(field);
+    }
+    env::keccak256_array(&data)
+}
+
+fn encode_string(value: &str) -> [u8; 32] {
+    env::keccak256_array(value.as_bytes())
+}
+
+fn encode_uint(value: u128) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[16..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+fn encode_address(address: &[u8; 20]) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[12..].copy_from_slice(address);
+    word
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eip712_mail_example() {
+        // "Mail" example from the EIP-712 specification
+        let person = "Person(string name,address wallet)";
+        let mail_type = format!("Mail(Person from,Person to,string contents){}", person);
+        let cow = hash_struct(
+            person,
+            &[
+                encode_string("Cow"),
+                encode_address(&[
+                    0xcd, 0x2a, 0x3d, 0x9f, 0x93, 0x8e, 0x13, 0xcd, 0x94, 0x7e, 0xc0, 0x5a, 0xbc,
+                    0x7f, 0xe7, 0x34, 0xdf, 0x8d, 0xd8, 0x26,
+                ]),
+            ],
+        );
+        let bob = hash_struct(person, &[encode_string("Bob"), encode_address(&[0xbb; 20])]);
+        let mail = hash_struct(&mail_type, &[cow, bob, encode_string("Hello, Bob!")]);
+        assert_eq!(
+            hex::encode(mail),
+            "c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e"
+        );
+
+        let domain = hash_struct(
+            "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)",
+            &[
+                encode_string("Ether Mail"),
+                encode_string("1"),
+                encode_uint(1),
+                encode_address(&[0xcc; 20]),
+            ],
+        );
+        assert_eq!(
+            hex::encode(domain),
+            "f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f"
+        );
+    }
+
+    #[test]
+    fn test_action_digest_binds_contents() {
+        let register = SignedAction::RegisterWallet {
+            derivation_path: "evm-1,test",
+            nonce: 1,
+        };
+        let other_nonce = SignedAction::RegisterWallet {
+            derivation_path: "evm-1,test",
+            nonce: 2,
+        };
+
+        let digest = action_digest(&register, "permission.near");
+        assert_ne!(digest, action_digest(&other_nonce, "permission.near"));
+        assert_ne!(digest, action_digest(&register, "permission.testnet"));
+    }
+}
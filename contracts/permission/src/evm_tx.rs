@@ -0,0 +1,518 @@
+use near_sdk::env;
+
+use crate::signature::parse_evm_address;
+use crate::types::AllowedOperation;
+
+/// EIP-2930 typed transaction prefix
+const TX_TYPE_ACCESS_LIST: u8 = 0x01;
+/// EIP-1559 typed transaction prefix
+const TX_TYPE_DYNAMIC_FEE: u8 = 0x02;
+
+/// ERC-20 transfer(address,uint256)
+const ERC20_TRANSFER: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
+/// ERC-20 approve(address,uint256)
+const ERC20_APPROVE: [u8; 4] = [0x09, 0x5e, 0xa7, 0xb3];
+
+/// Placeholder address commonly used for the native asset
+const NATIVE_ASSET_ADDRESS: &str = "0xeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee";
+
+/// RLP item borrowed from the input
+enum Rlp<'a> {
+    Bytes(&'a [u8]),
+    List(Vec<Rlp<'a>>),
+}
+
+impl<'a> Rlp<'a> {
+    fn bytes(&self) -> Result<&'a [u8], &'static str> {
+        match self {
+            Rlp::Bytes(b) => Ok(b),
+            Rlp::List(_) => Err("Malformed EVM transaction"),
+        }
+    }
+
+    /// Canonical big-endian integer, at most 32 bytes
+    fn uint(&self) -> Result<U256, &'static str> {
+        let b = self.bytes()?;
+        if b.len() > 32 || b.first() == Some(&0) {
+            return Err("Malformed EVM transaction");
+        }
+        let mut out = [0u8; 32];
+        out[32 - b.len()..].copy_from_slice(b);
+        Ok(U256(out))
+    }
+
+    fn u64(&self) -> Result<u64, &'static str> {
+        self.uint()?.to_u64().ok_or("Malformed EVM transaction")
+    }
+}
+
+/// Minimal big-endian 256-bit integer
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct U256(pub [u8; 32]);
+
+impl U256 {
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|&b| b == 0)
+    }
+
+    fn to_u64(self) -> Option<u64> {
+        if self.0[..24].iter().any(|&b| b != 0) {
+            return None;
+        }
+        Some(u64::from_be_bytes(self.0[24..].try_into().ok()?))
+    }
+
+    pub fn to_u128(self) -> Option<u128> {
+        if self.0[..16].iter().any(|&b| b != 0) {
+            return None;
+        }
+        Some(u128::from_be_bytes(self.0[16..].try_into().ok()?))
+    }
+}
+
+/// Decode one RLP item, returning it and the remaining input
+fn decode_item(input: &[u8]) -> Result<(Rlp<'_>, &[u8]), &'static str> {
+    let err = "Malformed EVM transaction";
+    let prefix = *input.first().ok_or(err)?;
+
+    let (is_list, offset, len) = match prefix {
+        0x00..=0x7f => return Ok((Rlp::Bytes(&input[..1]), &input[1..])),
+        0x80..=0xb7 => (false, 1, (prefix - 0x80) as usize),
+        0xb8..=0xbf => {
+            let len_of_len = (prefix - 0xb7) as usize;
+            (false, 1 + len_of_len, read_length(input, len_of_len)?)
+        }
+        0xc0..=0xf7 => (true, 1, (prefix - 0xc0) as usize),
+        0xf8..=0xff => {
+            let len_of_len = (prefix - 0xf7) as usize;
+            (true, 1 + len_of_len, read_length(input, len_of_len)?)
+        }
+    };
+
+    let end = offset.checked_add(len).ok_or(err)?;
+    let payload = input.get(offset..end).ok_or(err)?;
+    let rest = &input[end..];
+
+    if !is_list {
+        // Single bytes below 0x80 must use the short form
+        if prefix == 0x81 && payload[0] < 0x80 {
+            return Err(err);
+        }
+        return Ok((Rlp::Bytes(payload), rest));
+    }
+
+    let mut items = Vec::new();
+    let mut remaining = payload;
+    while !remaining.is_empty() {
+        let (item, next) = decode_item(remaining)?;
+        items.push(item);
+        remaining = next;
+    }
+    Ok((Rlp::List(items), rest))
+}
+
+/// Read a long-form RLP length; must be canonical (no leading zeros, > 55)
+fn read_length(input: &[u8], len_of_len: usize) -> Result<usize, &'static str> {
+    let err = "Malformed EVM transaction";
+    let bytes = input.get(1..1 + len_of_len).ok_or(err)?;
+    if bytes[0] == 0 || len_of_len > 4 {
+        return Err(err);
+    }
+    let len = bytes.iter().fold(0usize, |acc, &b| (acc << 8) | b as usize);
+    if len <= 55 {
+        return Err(err);
+    }
+    Ok(len)
+}
+
+/// Fields of an unsigned transaction relevant to the allowlist
+pub struct EvmTransaction {
+    pub chain_id: u64,
+    pub to: [u8; 20],
+    pub value: U256,
+    pub data: Vec<u8>,
+}
+
+/// Decode an unsigned legacy (EIP-155), EIP-2930 or EIP-1559 transaction
+pub fn parse_transaction(payload: &[u8]) -> Result<EvmTransaction, &'static str> {
+    let first = *payload.first().ok_or("Malformed EVM transaction")?;
+
+    // Index of (chain_id, to, value, data) within the field list
+    let (body, field_count, chain_idx, to_idx) = match first {
+        TX_TYPE_ACCESS_LIST => (&payload[1..], 8, 0, 4),
+        TX_TYPE_DYNAMIC_FEE => (&payload[1..], 9, 0, 5),
+        0xc0..=0xff => (payload, 9, 6, 3),
+        _ => return Err("Unsupported EVM transaction type"),
+    };
+
+    let (item, rest) = decode_item(body)?;
+    if !rest.is_empty() {
+        return Err("Trailing bytes after EVM transaction");
+    }
+    let fields = match item {
+        Rlp::List(fields) => fields,
+        Rlp::Bytes(_) => return Err("Malformed EVM transaction"),
+    };
+    if fields.len() != field_count {
+        return Err(if first >= 0xc0 && fields.len() == 6 {
+            "EVM transaction missing chain ID"
+        } else {
+            "Malformed EVM transaction"
+        });
+    }
+
+    // Unsigned EIP-155 legacy transactions end with (chain_id, 0, 0)
+    if first >= 0xc0 && (!fields[7].bytes()?.is_empty() || !fields[8].bytes()?.is_empty()) {
+        return Err("Malformed EVM transaction");
+    }
+
+    let to: [u8; 20] = fields[to_idx]
+        .bytes()?
+        .try_into()
+        .map_err(|_| "Contract creation not allowed")?;
+
+    Ok(EvmTransaction {
+        chain_id: fields[chain_idx].u64()?,
+        to,
+        value: fields[to_idx + 1].uint()?,
+        data: fields[to_idx + 2].bytes()?.to_vec(),
+    })
+}
+
+/// Hash the MPC signs for an unsigned transaction
+pub fn signing_hash(payload: &[u8]) -> [u8; 32] {
+    env::keccak256_array(payload)
+}
+
+/// Check an unsigned EVM transaction against an allowed operation:
+/// the chain must match `source_chain`, native value and ERC-20
+/// transfers must go to the destination in the source or target asset,
+/// approvals must grant the destination at most max_amount of the source
+/// asset, and the source asset total must not exceed `max_amount`, the
+/// amount being executed
+pub fn validate_evm_payload(
+    operation: &AllowedOperation,
+    payload: &[u8],
+    max_amount: u128,
+) -> Result<(), &'static str> {
+    let tx = parse_transaction(payload)?;
+
+    let expected_chain =
+        evm_chain_id(&operation.source_chain).ok_or("Operation is not on an EVM chain")?;
+    if tx.chain_id != expected_chain {
+        return Err("Transaction chain ID does not match operation");
+    }
+
+    let destination = parse_evm_address(&operation.destination_address)
+        .ok_or("Operation destination is not an EVM address")?;
+    let source_asset = operation.operation_type.source_asset();
+    let target_asset = operation.operation_type.target_asset();
+
+    if tx.data.is_empty() {
+        // Native transfer
+        let is_source = is_native_asset(source_asset);
+        if !is_source && !is_native_asset(target_asset) {
+            return Err("Transfer asset not allowed by operation");
+        }
+        if tx.to != destination {
+            return Err("Transfer recipient does not match destination");
+        }
+        if is_source && amount_exceeds(tx.value, max_amount) {
+            return Err("Transfer amount exceeds max_amount");
+        }
+        return Ok(());
+    }
+
+    if !tx.value.is_zero() {
+        return Err("Native value not allowed with calldata");
+    }
+    if tx.data.len() != 68 || tx.data[4..16].iter().any(|&b| b != 0) {
+        return Err("Unsupported EVM calldata");
+    }
+    let selector: [u8; 4] = tx.data[..4].try_into().unwrap_or_default();
+    let account: [u8; 20] = tx.data[16..36].try_into().unwrap_or_default();
+    let mut amount = [0u8; 32];
+    amount.copy_from_slice(&tx.data[36..68]);
+    let amount = U256(amount);
+
+    let is_source = token_matches(source_asset, &tx.to);
+    match selector {
+        ERC20_TRANSFER => {
+            if !is_source && !token_matches(target_asset, &tx.to) {
+                return Err("Transfer asset not allowed by operation");
+            }
+            if account != destination {
+                return Err("Transfer recipient does not match destination");
+            }
+        }
+        ERC20_APPROVE => {
+            if !is_source {
+                return Err("Approval asset not allowed by operation");
+            }
+            if account != destination {
+                return Err("Approval spender does not match destination");
+            }
+        }
+        _ => return Err("Unsupported EVM calldata"),
+    }
+
+    if is_source && amount_exceeds(amount, max_amount) {
+        return Err("Transfer amount exceeds max_amount");
+    }
+
+    Ok(())
+}
+
+fn amount_exceeds(amount: U256, max_amount: u128) -> bool {
+    amount.to_u128().is_none_or(|a| a > max_amount)
+}
+
+/// Asset strings hold ERC-20 contract addresses; "ETH" or the 0xEeee…
+/// placeholder denote the chain's native asset
+fn is_native_asset(asset: &str) -> bool {
+    asset.eq_ignore_ascii_case("ETH") || asset.eq_ignore_ascii_case(NATIVE_ASSET_ADDRESS)
+}
+
+fn token_matches(asset: &str, token: &[u8; 20]) -> bool {
+    parse_evm_address(asset).is_some_and(|a| &a == token)
+}
+
+/// Chain ID for a destination chain given as a name, number or "eip155:<id>"
+pub fn evm_chain_id(chain: &str) -> Option<u64> {
+    let lower = chain.to_ascii_lowercase();
+    let id = lower.strip_prefix("eip155:").unwrap_or(&lower);
+    if let Ok(id) = id.parse() {
+        return Some(id);
+    }
+    match id {
+        "ethereum" | "eth" => Some(1),
+        "optimism" => Some(10),
+        "bsc" => Some(56),
+        "polygon" => Some(137),
+        "base" => Some(8453),
+        "arbitrum" => Some(42161),
+        "avalanche" => Some(43114),
+        "sepolia" => Some(11155111),
+        "base-sepolia" => Some(84532),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::AllowedOperationType;
+    use near_sdk::json_types::U128;
+
+    const USDC: &str = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48";
+    const USER: &str = "0x3535353535353535353535353535353535353535";
+
+    fn rlp_bytes(b: &[u8]) -> Vec<u8> {
+        if b.len() == 1 && b[0] < 0x80 {
+            return b.to_vec();
+        }
+        let mut out = rlp_len(0x80, b.len());
+        out.extend_from_slice(b);
+        out
+    }
+
+    fn rlp_list(items: &[Vec<u8>]) -> Vec<u8> {
+        let body: Vec<u8> = items.concat();
+        let mut out = rlp_len(0xc0, body.len());
+        out.extend(body);
+        out
+    }
+
+    fn rlp_len(offset: u8, len: usize) -> Vec<u8> {
+        if len <= 55 {
+            return vec![offset + len as u8];
+        }
+        let len_bytes: Vec<u8> = len
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|&b| b == 0)
+            .collect();
+        let mut out = vec![offset + 55 + len_bytes.len() as u8];
+        out.extend(len_bytes);
+        out
+    }
+
+    fn rlp_uint(v: u128) -> Vec<u8> {
+        let bytes: Vec<u8> = v
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|&b| b == 0)
+            .collect();
+        rlp_bytes(&bytes)
+    }
+
+    fn eip1559(chain_id: u128, to: &str, value: u128, data: &[u8]) -> Vec<u8> {
+        let mut out = vec![TX_TYPE_DYNAMIC_FEE];
+        out.extend(rlp_list(&[
+            rlp_uint(chain_id),
+            rlp_uint(0),
+            rlp_uint(1_000_000_000),
+            rlp_uint(30_000_000_000),
+            rlp_uint(100_000),
+            rlp_bytes(&parse_evm_address(to).unwrap()),
+            rlp_uint(value),
+            rlp_bytes(data),
+            rlp_list(&[]),
+        ]));
+        out
+    }
+
+    fn erc20_call(selector: [u8; 4], account: &str, amount: u128) -> Vec<u8> {
+        let mut data = selector.to_vec();
+        data.extend_from_slice(&[0u8; 12]);
+        data.extend_from_slice(&parse_evm_address(account).unwrap());
+        data.extend_from_slice(&[0u8; 16]);
+        data.extend_from_slice(&amount.to_be_bytes());
+        data
+    }
+
+    fn operation(source: &str, target: &str, max_amount: u128) -> AllowedOperation {
+        AllowedOperation {
+            operation_id: "op".to_string(),
+            derivation_path: "evm-1,test".to_string(),
+            operation_type: AllowedOperationType::Swap {
+                source_asset: source.to_string(),
+                target_asset: target.to_string(),
+                max_amount: U128(max_amount),
+            },
+            source_chain: "base".to_string(),
+            destination_address: USER.to_string(),
+            destination_chain: "base".to_string(),
+            slippage_bps: 50,
+            expires_at: None,
+            executed: false,
+            nonce: 1,
+            created_at: 0,
+            recurring: None,
+            execution_count: 0,
+            last_executed_at: None,
+            consumed_amount: U128(0),
+        }
+    }
+
+    #[test]
+    fn test_eip155_legacy_vector() {
+        // Example transaction from the EIP-155 specification
+        let payload = hex::decode(
+            "ec098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a764000080018080",
+        )
+        .unwrap();
+
+        let tx = parse_transaction(&payload).unwrap();
+        assert_eq!(tx.chain_id, 1);
+        assert_eq!(tx.to, [0x35u8; 20]);
+        assert_eq!(tx.value.to_u128(), Some(1_000_000_000_000_000_000));
+        assert!(tx.data.is_empty());
+        assert_eq!(
+            hex::encode(signing_hash(&payload)),
+            "daf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53"
+        );
+    }
+
+    #[test]
+    fn test_legacy_without_chain_id_rejected() {
+        let payload = rlp_list(&[
+            rlp_uint(9),
+            rlp_uint(20_000_000_000),
+            rlp_uint(21_000),
+            rlp_bytes(&[0x35u8; 20]),
+            rlp_uint(1),
+            rlp_bytes(&[]),
+        ]);
+        assert_eq!(
+            parse_transaction(&payload).err(),
+            Some("EVM transaction missing chain ID")
+        );
+    }
+
+    #[test]
+    fn test_eip2930_transaction() {
+        let mut payload = vec![TX_TYPE_ACCESS_LIST];
+        payload.extend(rlp_list(&[
+            rlp_uint(8453),
+            rlp_uint(3),
+            rlp_uint(1_000_000_000),
+            rlp_uint(21_000),
+            rlp_bytes(&parse_evm_address(USER).unwrap()),
+            rlp_uint(500),
+            rlp_bytes(&[]),
+            rlp_list(&[]),
+        ]));
+
+        let op = operation("ETH", USDC, 500);
+        assert!(validate_evm_payload(&op, &payload, 500).is_ok());
+        assert_eq!(
+            validate_evm_payload(&operation("ETH", USDC, 499), &payload, 499),
+            Err("Transfer amount exceeds max_amount")
+        );
+    }
+
+    #[test]
+    fn test_erc20_transfer() {
+        let payload = eip1559(8453, USDC, 0, &erc20_call(ERC20_TRANSFER, USER, 1_000));
+        assert!(validate_evm_payload(&operation(USDC, "ETH", 1_000), &payload, 1_000).is_ok());
+
+        let to_attacker = eip1559(
+            8453,
+            USDC,
+            0,
+            &erc20_call(
+                ERC20_TRANSFER,
+                "0x000000000000000000000000000000000000dead",
+                1_000,
+            ),
+        );
+        assert_eq!(
+            validate_evm_payload(&operation(USDC, "ETH", 1_000), &to_attacker, 1_000),
+            Err("Transfer recipient does not match destination")
+        );
+    }
+
+    #[test]
+    fn test_erc20_approve_capped() {
+        let payload = eip1559(8453, USDC, 0, &erc20_call(ERC20_APPROVE, USER, u128::MAX));
+        assert_eq!(
+            validate_evm_payload(&operation(USDC, "ETH", 1_000), &payload, 1_000),
+            Err("Transfer amount exceeds max_amount")
+        );
+    }
+
+    #[test]
+    fn test_wrong_chain_and_unknown_calldata_rejected() {
+        let payload = eip1559(1, USDC, 0, &erc20_call(ERC20_TRANSFER, USER, 1));
+        assert_eq!(
+            validate_evm_payload(&operation(USDC, "ETH", 1_000), &payload, 1_000),
+            Err("Transaction chain ID does not match operation")
+        );
+
+        let payload = eip1559(8453, USDC, 0, &[0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(
+            validate_evm_payload(&operation(USDC, "ETH", 1_000), &payload, 1_000),
+            Err("Unsupported EVM calldata")
+        );
+    }
+
+    #[test]
+    fn test_non_canonical_rlp_rejected() {
+        // 0x05 encoded with a length prefix
+        assert!(decode_item(&[0x81, 0x05]).is_err());
+        // Long-form length used for a short string
+        assert!(decode_item(&[0xb8, 0x01, 0xff]).is_err());
+        // Integer with a leading zero
+        let (item, _) = decode_item(&[0x82, 0x00, 0x01]).unwrap();
+        assert!(item.uint().is_err());
+    }
+
+    #[test]
+    fn test_evm_chain_id() {
+        assert_eq!(evm_chain_id("ethereum"), Some(1));
+        assert_eq!(evm_chain_id("Base"), Some(8453));
+        assert_eq!(evm_chain_id("eip155:42161"), Some(42161));
+        assert_eq!(evm_chain_id("solana"), None);
+    }
+}
@@ -1,20 +1,77 @@
+// Signed user methods carry path, wallet, signature, message and nonce
+#![allow(clippy::too_many_arguments)]
+
 use near_sdk::collections::{LookupMap, UnorderedMap, UnorderedSet};
+use near_sdk::json_types::U128;
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{
-    env, ext_contract, near, AccountId, Gas, NearToken, PanicOnDefault, Promise,
-    PromiseError,
+    env, ext_contract, near, AccountId, Gas, NearToken, PanicOnDefault, Promise, PromiseError,
 };
 
+mod eip712;
+mod evm_tx;
+mod message;
+mod migrate;
+mod mpc;
+mod near_tx;
+mod oracle;
+mod registration;
 mod signature;
+mod solana_tx;
+mod storage;
 mod types;
 
-use signature::{verify_evm_signature, verify_near_signature, verify_solana_signature};
+use evm_tx::{signing_hash as evm_signing_hash, validate_evm_payload};
+use message::{verify_action_message, SignedAction};
+use mpc::{derived_near_account, SignatureResponse};
+use near_sdk::borsh::BorshDeserialize;
+use near_tx::{signing_hash as near_signing_hash, validate_near_payload};
+use oracle::{
+    ext_price_oracle, oracle_price_result, quote_price, OraclePrice, GAS_FOR_PRICE_QUERY,
+    MAX_PRICE_AGE_SECS,
+};
+use registration::{
+    measurement_key, normalize_measurement, parse_registration, relayer_key_id,
+    relayer_report_data, verify_registration, ATTESTATION_TTL_NS, MAX_REGISTRATION_AGE_NS,
+};
+use signature::{
+    solana_message_content, verify_evm_digest, verify_evm_signature, verify_near_signature,
+    verify_solana_signature, Nep413Payload,
+};
+use solana_tx::validate_solana_payload;
 use types::*;
 
 /// Gas for cross-contract call to MPC signer
 const GAS_FOR_MPC_SIGN: Gas = Gas::from_tgas(100);
 /// Gas for callback after MPC sign
 const GAS_FOR_CALLBACK: Gas = Gas::from_tgas(20);
+/// Gas for migrate after deploying new code
+const GAS_FOR_MIGRATE: Gas = Gas::from_tgas(50);
+/// Gas for callback after oracle prices; covers the MPC sign request and its callback
+const GAS_FOR_ORACLE_CALLBACK: Gas = Gas::from_tgas(140);
+
+/// How long a proposal collects approvals (7 days, nanoseconds)
+const PROPOSAL_TTL_NS: u64 = 7 * 24 * 60 * 60 * 1_000_000_000;
+/// Pending proposals kept per derivation path
+const MAX_PENDING_PROPOSALS: usize = 16;
+/// Delay before a new address-book destination can receive funds (24 hours, nanoseconds)
+const DESTINATION_ACTIVATION_DELAY_NS: u64 = 24 * 60 * 60 * 1_000_000_000;
+/// Methods gated by `assert_tee_relayer`, which a relayer scope can allow
+const RELAYER_METHODS: [&str; 13] = [
+    "register_wallet",
+    "link_wallet",
+    "add_allowed_operation",
+    "remove_allowed_operation",
+    "set_spending_limit",
+    "remove_spending_limit",
+    "add_destination",
+    "remove_destination",
+    "propose_change",
+    "approve_proposal",
+    "remove_wallet",
+    "rotate_wallet",
+    "sign_allowed",
+];
 
 /// External interface for ChainSignatureContract
 #[ext_contract(ext_chain_sig)]
@@ -22,13 +79,6 @@ pub trait ChainSignatureContract {
     fn sign(&mut self, request: SignRequest) -> Promise;
 }
 
-/// Ed25519 signature response from MPC contract
-#[derive(Deserialize)]
-#[serde(crate = "near_sdk::serde")]
-pub struct Ed25519SignatureResponse {
-    pub signature: Vec<u8>,
-}
-
 /// Sign request format for ChainSignatureContract
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
@@ -54,8 +104,8 @@ pub struct PermissionContract {
     /// Contract owner (admin)
     pub owner: AccountId,
     /// User permissions: derivation_path -> UserPermissions
-    pub permissions: LookupMap<DerivationPath, UserPermissions>,
-    /// Wallet address to derivation path mapping for lookup
+    pub permissions: LookupMap<DerivationPath, VersionedUserPermissions>,
+    /// Primary (first linked) derivation path of each wallet address
     pub wallet_to_path: LookupMap<String, DerivationPath>,
     /// Authorized TEE relayers that can request signatures
     pub tee_relayers: UnorderedSet<AccountId>,
@@ -65,6 +115,48 @@ pub struct PermissionContract {
     pub active_operations: UnorderedSet<String>,
     /// Nonce tracking for replay protection
     pub used_nonces: LookupMap<String, bool>,
+    /// Next expected mutation nonce per wallet: chain_address -> nonce
+    pub wallet_nonces: LookupMap<String, u64>,
+    /// Solana programs (base58) allowed in signed payloads beyond the built-in set
+    pub allowed_solana_programs: UnorderedSet<String>,
+    /// NEAR "receiver:method" pairs allowed in signed payloads beyond token transfers
+    pub allowed_near_methods: UnorderedSet<String>,
+    /// Price oracle checked before signing conditional operations
+    pub price_oracle: Option<AccountId>,
+    /// Oracle price feed IDs: asset -> price_id
+    pub price_feeds: UnorderedMap<String, String>,
+    /// Roles granted to accounts other than the owner
+    pub roles: UnorderedMap<AccountId, Vec<Role>>,
+    /// Account proposed as the next owner, until it accepts
+    pub pending_owner: Option<AccountId>,
+    /// Blocks signing and allowlist changes while set
+    pub paused: bool,
+    /// NEP-145 storage deposits of sponsoring accounts
+    pub storage_accounts: LookupMap<AccountId, StorageAccount>,
+    /// Account whose storage deposit pays for each derivation path
+    pub path_sponsors: LookupMap<DerivationPath, AccountId>,
+    /// All derivation paths each wallet address owns
+    pub wallet_paths: LookupMap<String, UnorderedSet<DerivationPath>>,
+    /// Hex Ed25519 root keys of verifiers trusted to certify registration
+    /// signing keys
+    pub attestation_roots: UnorderedSet<String>,
+    /// Approved TEE code: "measurement:compose_hash" (hex)
+    pub approved_measurements: UnorderedSet<String>,
+    /// Relayers registered with a verified signed registration
+    pub attested_relayers: UnorderedMap<AccountId, AttestedRelayer>,
+    /// "account:public_key" of removed attested relayers, whose
+    /// registrations are no longer accepted
+    pub revoked_relayer_keys: UnorderedSet<String>,
+    /// Only relayers with a valid attestation may call relayer methods
+    pub require_attestation: bool,
+    /// Limits on scoped relayers; relayers without an entry are unrestricted
+    pub relayer_scopes: UnorderedMap<AccountId, RelayerScope>,
+    /// Domain 1 (Ed25519) root public key of the MPC contract, from which
+    /// each path's NEAR account is derived
+    pub mpc_ed25519_key: Option<[u8; 32]>,
+    /// Untagged permissions stored by the first release, moved to
+    /// `permissions` when next saved
+    pub legacy_permissions: LookupMap<DerivationPath, LegacyUserPermissions>,
 }
 
 #[near]
@@ -72,14 +164,34 @@ impl PermissionContract {
     /// Initialize the contract
     #[init]
     pub fn new(owner: AccountId, mpc_contract: AccountId) -> Self {
+        migrate::write_state_version();
         Self {
             owner,
-            permissions: LookupMap::new(b"p"),
+            permissions: LookupMap::new(b"v"),
             wallet_to_path: LookupMap::new(b"w"),
             tee_relayers: UnorderedSet::new(b"t"),
             mpc_contract,
             active_operations: UnorderedSet::new(b"a"),
             used_nonces: LookupMap::new(b"n"),
+            wallet_nonces: LookupMap::new(b"o"),
+            allowed_solana_programs: UnorderedSet::new(b"y"),
+            allowed_near_methods: UnorderedSet::new(b"z"),
+            price_oracle: None,
+            price_feeds: UnorderedMap::new(b"f"),
+            roles: UnorderedMap::new(b"r"),
+            pending_owner: None,
+            paused: false,
+            storage_accounts: LookupMap::new(b"s"),
+            path_sponsors: LookupMap::new(b"d"),
+            wallet_paths: LookupMap::new(b"x"),
+            attestation_roots: UnorderedSet::new(b"k"),
+            approved_measurements: UnorderedSet::new(b"m"),
+            attested_relayers: UnorderedMap::new(b"q"),
+            revoked_relayer_keys: UnorderedSet::new(b"g"),
+            require_attestation: false,
+            relayer_scopes: UnorderedMap::new(b"c"),
+            mpc_ed25519_key: None,
+            legacy_permissions: LookupMap::new(b"p"),
         }
     }
 
@@ -87,32 +199,397 @@ impl PermissionContract {
     // Admin Methods
     // ═══════════════════════════════════════════════════════════════════════════
 
-    /// Register an authorized TEE relayer (owner only)
-    pub fn register_tee_relayer(&mut self, relayer_account: AccountId) {
-        self.assert_owner();
+    /// Register an authorized TEE relayer (RelayerManager)
+    /// A scope limits the methods, operation types, key types and paths the
+    /// relayer may use; without one it may use all of them. Registering
+    /// again replaces the scope
+    pub fn register_tee_relayer(
+        &mut self,
+        relayer_account: AccountId,
+        scope: Option<RelayerScope>,
+    ) {
+        self.assert_role(Role::RelayerManager);
+        if let Some(Err(e)) = scope.as_ref().map(|s| s.validate(&RELAYER_METHODS)) {
+            env::panic_str(e);
+        }
         self.tee_relayers.insert(&relayer_account);
-        env::log_str(&format!("Registered TEE relayer: {}", relayer_account));
+        PermissionEvent::TeeRelayerRegistered {
+            relayer_account: relayer_account.clone(),
+        }
+        .emit();
+
+        match scope {
+            Some(scope) => {
+                self.relayer_scopes.insert(&relayer_account, &scope);
+                PermissionEvent::RelayerScopeSet {
+                    relayer_account,
+                    scope,
+                }
+                .emit();
+            }
+            None => {
+                self.relayer_scopes.remove(&relayer_account);
+            }
+        }
     }
 
-    /// Remove a TEE relayer (owner only)
+    /// Remove a TEE relayer and its attestation (RelayerManager)
+    /// The attested key is revoked, so its registration cannot be replayed
     pub fn remove_tee_relayer(&mut self, relayer_account: AccountId) {
-        self.assert_owner();
+        self.assert_role(Role::RelayerManager);
         self.tee_relayers.remove(&relayer_account);
-        env::log_str(&format!("Removed TEE relayer: {}", relayer_account));
+        if let Some(attested) = self.attested_relayers.remove(&relayer_account) {
+            self.revoked_relayer_keys
+                .insert(&relayer_key_id(&relayer_account, &attested.public_key));
+        }
+        self.relayer_scopes.remove(&relayer_account);
+        PermissionEvent::TeeRelayerRemoved { relayer_account }.emit();
+    }
+
+    /// Replace the scope of a registered or attested relayer (RelayerManager)
+    /// Unlike `register_tee_relayer` this does not add it to `tee_relayers`,
+    /// so an attested relayer still needs a valid attestation
+    pub fn set_relayer_scope(&mut self, relayer_account: AccountId, scope: RelayerScope) {
+        self.assert_role(Role::RelayerManager);
+        if let Err(e) = scope.validate(&RELAYER_METHODS) {
+            env::panic_str(e);
+        }
+        self.relayer_scopes.insert(&relayer_account, &scope);
+        PermissionEvent::RelayerScopeSet {
+            relayer_account,
+            scope,
+        }
+        .emit();
+    }
+
+    /// Approve TEE code for relayer attestation (Admin)
+    /// `measurement` is the 48-byte code measurement, `compose_hash` the
+    /// 32-byte app compose hash, both hex
+    pub fn approve_measurement(&mut self, measurement: String, compose_hash: String) {
+        self.assert_role(Role::Admin);
+        let (measurement, compose_hash) = normalize_measurement(&measurement, &compose_hash)
+            .unwrap_or_else(|e| env::panic_str(e));
+        self.approved_measurements
+            .insert(&measurement_key(&measurement, &compose_hash));
+        PermissionEvent::MeasurementApproved {
+            measurement,
+            compose_hash,
+        }
+        .emit();
+    }
+
+    /// Revoke approved TEE code; relayers attested with it stop working (Admin)
+    pub fn revoke_measurement(&mut self, measurement: String, compose_hash: String) {
+        self.assert_role(Role::Admin);
+        let (measurement, compose_hash) = normalize_measurement(&measurement, &compose_hash)
+            .unwrap_or_else(|e| env::panic_str(e));
+        self.approved_measurements
+            .remove(&measurement_key(&measurement, &compose_hash));
+        PermissionEvent::MeasurementRevoked {
+            measurement,
+            compose_hash,
+        }
+        .emit();
+    }
+
+    /// Trust an Ed25519 root key (hex) to certify attestation keys (Admin)
+    pub fn add_attestation_root(&mut self, public_key: String) {
+        self.assert_role(Role::Admin);
+        let key = hex::decode(&public_key).unwrap_or_default();
+        assert!(
+            key.len() == 32,
+            "Attestation root must be a 32-byte hex key"
+        );
+        let public_key = hex::encode(key);
+        self.attestation_roots.insert(&public_key);
+        PermissionEvent::AttestationRootAdded { public_key }.emit();
+    }
+
+    /// Stop trusting an attestation root key (Admin)
+    pub fn remove_attestation_root(&mut self, public_key: String) {
+        self.assert_role(Role::Admin);
+        let public_key = public_key.to_lowercase();
+        self.attestation_roots.remove(&public_key);
+        PermissionEvent::AttestationRootRemoved { public_key }.emit();
+    }
+
+    /// Require relayers to hold a valid attestation (Admin)
+    /// While unset, relayers registered with `register_tee_relayer` also pass
+    pub fn set_require_attestation(&mut self, required: bool) {
+        self.assert_role(Role::Admin);
+        self.require_attestation = required;
+        PermissionEvent::AttestationRequirementSet { required }.emit();
+    }
+
+    /// Register the calling relayer with a signed registration
+    /// The registration must chain to a trusted root, carry an approved
+    /// measurement and bind the caller's account and signing key in its
+    /// report data (see `registration::relayer_report_data`). The attestation
+    /// expires ATTESTATION_TTL_NS after the registration was issued
+    /// This trusts the verifier behind the root key; no TEE quote is checked
+    /// on chain
+    /// Relayers no RelayerManager registered start with an empty scope and
+    /// can do nothing until `set_relayer_scope` grants them one
+    pub fn register_attested_relayer(&mut self, registration: Vec<u8>) {
+        let relayer_account = env::predecessor_account_id();
+        assert_eq!(
+            env::signer_account_id(),
+            relayer_account,
+            "Relayer must submit its own attestation"
+        );
+        let public_key = env::signer_account_pk();
+        assert!(
+            !self
+                .revoked_relayer_keys
+                .contains(&relayer_key_id(&relayer_account, &public_key)),
+            "Relayer key revoked"
+        );
+
+        let registration = parse_registration(&registration).unwrap_or_else(|e| env::panic_str(e));
+        if let Err(e) = verify_registration(&registration, |key| {
+            self.attestation_roots.contains(&hex::encode(key))
+        }) {
+            env::panic_str(e);
+        }
+        let report_data = relayer_report_data(relayer_account.as_str(), public_key.as_bytes());
+        assert!(
+            registration.report_data == report_data,
+            "Registration does not bind the relayer key"
+        );
+
+        let measurement = hex::encode(registration.measurement);
+        let compose_hash = hex::encode(registration.compose_hash);
+        assert!(
+            self.approved_measurements
+                .contains(&measurement_key(&measurement, &compose_hash)),
+            "Measurement not approved"
+        );
+
+        let now = env::block_timestamp();
+        assert!(
+            registration.issued_at <= now
+                && now - registration.issued_at <= MAX_REGISTRATION_AGE_NS,
+            "Relayer registration is stale"
+        );
+        let expires_at = registration.issued_at + ATTESTATION_TTL_NS;
+
+        if !self.tee_relayers.contains(&relayer_account)
+            && self.relayer_scopes.get(&relayer_account).is_none()
+        {
+            let scope = RelayerScope::deny_all();
+            self.relayer_scopes.insert(&relayer_account, &scope);
+            PermissionEvent::RelayerScopeSet {
+                relayer_account: relayer_account.clone(),
+                scope,
+            }
+            .emit();
+        }
+
+        self.attested_relayers.insert(
+            &relayer_account,
+            &AttestedRelayer {
+                public_key: public_key.clone(),
+                measurement: measurement.clone(),
+                compose_hash: compose_hash.clone(),
+                expires_at,
+            },
+        );
+        PermissionEvent::RelayerAttested {
+            relayer_account,
+            public_key,
+            measurement,
+            compose_hash,
+            expires_at,
+        }
+        .emit();
     }
 
-    /// Update MPC contract address (owner only)
+    /// Update MPC contract address (ConfigManager)
+    /// Clears the MPC Ed25519 key, which belongs to the previous contract
     pub fn update_mpc_contract(&mut self, mpc_contract: AccountId) {
+        self.assert_role(Role::ConfigManager);
+        self.mpc_contract = mpc_contract.clone();
+        self.mpc_ed25519_key = None;
+        PermissionEvent::MpcContractUpdated { mpc_contract }.emit();
+    }
+
+    /// Set the MPC contract's domain 1 root key, "ed25519:<base58>" as
+    /// returned by its `public_key` view (ConfigManager)
+    /// NEAR transactions can only be signed once it is set
+    pub fn set_mpc_ed25519_key(&mut self, public_key: String) {
+        self.assert_role(Role::ConfigManager);
+        let key: [u8; 32] = public_key
+            .strip_prefix("ed25519:")
+            .and_then(|key| bs58::decode(key).into_vec().ok())
+            .and_then(|key| key.try_into().ok())
+            .unwrap_or_else(|| env::panic_str("Invalid MPC Ed25519 key"));
+        self.mpc_ed25519_key = Some(key);
+        PermissionEvent::MpcEd25519KeySet { public_key }.emit();
+    }
+
+    /// Deploy new contract code, passed as the raw call input, then run
+    /// `migrate` (owner only)
+    pub fn update_contract(&self) -> Promise {
+        self.assert_owner();
+        let code = env::input().unwrap_or_else(|| env::panic_str("Missing contract code"));
+        PermissionEvent::ContractUpdated {
+            code_hash: hex::encode(env::sha256(&code)),
+        }
+        .emit();
+
+        Promise::new(env::current_account_id())
+            .deploy_contract(code)
+            .function_call(
+                "migrate".to_string(),
+                Vec::new(),
+                NearToken::from_yoctonear(0),
+                GAS_FOR_MIGRATE,
+            )
+    }
+
+    /// Allow a Solana program in signed payloads (ConfigManager)
+    /// Instructions of allowed programs are not decoded, so only add audited programs
+    pub fn add_allowed_solana_program(&mut self, program_id: String) {
+        self.assert_role(Role::ConfigManager);
+        self.allowed_solana_programs.insert(&program_id);
+        PermissionEvent::SolanaProgramAllowed { program_id }.emit();
+    }
+
+    /// Disallow a Solana program in signed payloads (ConfigManager)
+    pub fn remove_allowed_solana_program(&mut self, program_id: String) {
+        self.assert_role(Role::ConfigManager);
+        self.allowed_solana_programs.remove(&program_id);
+        PermissionEvent::SolanaProgramRemoved { program_id }.emit();
+    }
+
+    /// Allow a NEAR contract method in signed payloads (ConfigManager)
+    pub fn add_allowed_near_method(&mut self, receiver_id: AccountId, method_name: String) {
+        self.assert_role(Role::ConfigManager);
+        let key = format!("{}:{}", receiver_id, method_name);
+        self.allowed_near_methods.insert(&key);
+        PermissionEvent::NearMethodAllowed {
+            receiver_id,
+            method_name,
+        }
+        .emit();
+    }
+
+    /// Disallow a NEAR contract method in signed payloads (ConfigManager)
+    pub fn remove_allowed_near_method(&mut self, receiver_id: AccountId, method_name: String) {
+        self.assert_role(Role::ConfigManager);
+        let key = format!("{}:{}", receiver_id, method_name);
+        self.allowed_near_methods.remove(&key);
+        PermissionEvent::NearMethodRemoved {
+            receiver_id,
+            method_name,
+        }
+        .emit();
+    }
+
+    /// Set the price oracle for conditional operations (ConfigManager)
+    pub fn set_price_oracle(&mut self, price_oracle: AccountId) {
+        self.assert_role(Role::ConfigManager);
+        self.price_oracle = Some(price_oracle.clone());
+        PermissionEvent::PriceOracleSet { price_oracle }.emit();
+    }
+
+    /// Set the oracle price feed ID for an asset (ConfigManager)
+    pub fn set_price_feed(&mut self, asset: String, price_id: String) {
+        self.assert_role(Role::ConfigManager);
+        self.price_feeds.insert(&asset, &price_id);
+        PermissionEvent::PriceFeedSet { asset, price_id }.emit();
+    }
+
+    /// Remove the oracle price feed for an asset (ConfigManager)
+    pub fn remove_price_feed(&mut self, asset: String) {
+        self.assert_role(Role::ConfigManager);
+        self.price_feeds.remove(&asset);
+        PermissionEvent::PriceFeedRemoved { asset }.emit();
+    }
+
+    /// Pause signing and allowlist changes contract-wide (Pauser)
+    pub fn pause(&mut self) {
+        self.assert_role(Role::Pauser);
+        self.paused = true;
+        PermissionEvent::Paused {
+            account_id: env::predecessor_account_id(),
+        }
+        .emit();
+    }
+
+    /// Resume after a pause (Admin)
+    pub fn unpause(&mut self) {
+        self.assert_role(Role::Admin);
+        self.paused = false;
+        PermissionEvent::Unpaused {
+            account_id: env::predecessor_account_id(),
+        }
+        .emit();
+    }
+
+    // ═══════════════════════════════════════════════════════════════════════════
+    // Roles & Ownership
+    // ═══════════════════════════════════════════════════════════════════════════
+
+    /// Grant a role to an account (Admin)
+    pub fn grant_role(&mut self, account_id: AccountId, role: Role) {
+        self.assert_role(Role::Admin);
+        let mut roles = self.roles.get(&account_id).unwrap_or_default();
+        if !roles.contains(&role) {
+            roles.push(role);
+            self.roles.insert(&account_id, &roles);
+        }
+        PermissionEvent::RoleGranted { account_id, role }.emit();
+    }
+
+    /// Revoke a role from an account (Admin)
+    pub fn revoke_role(&mut self, account_id: AccountId, role: Role) {
+        self.assert_role(Role::Admin);
+        let mut roles = self.roles.get(&account_id).unwrap_or_default();
+        roles.retain(|r| *r != role);
+        if roles.is_empty() {
+            self.roles.remove(&account_id);
+        } else {
+            self.roles.insert(&account_id, &roles);
+        }
+        PermissionEvent::RoleRevoked { account_id, role }.emit();
+    }
+
+    /// Propose a new owner; takes effect once they accept (owner only)
+    pub fn propose_owner(&mut self, pending_owner: AccountId) {
         self.assert_owner();
-        self.mpc_contract = mpc_contract;
+        self.pending_owner = Some(pending_owner.clone());
+        PermissionEvent::OwnershipProposed {
+            owner: self.owner.clone(),
+            pending_owner,
+        }
+        .emit();
+    }
+
+    /// Accept a proposed ownership transfer (pending owner only)
+    pub fn accept_ownership(&mut self) {
+        let caller = env::predecessor_account_id();
+        assert_eq!(
+            self.pending_owner.as_ref(),
+            Some(&caller),
+            "Only the pending owner can accept ownership"
+        );
+        self.pending_owner = None;
+        let previous_owner = std::mem::replace(&mut self.owner, caller.clone());
+        PermissionEvent::OwnershipTransferred {
+            previous_owner,
+            owner: caller,
+        }
+        .emit();
     }
 
     // ═══════════════════════════════════════════════════════════════════════════
     // User Management (called by TEE with user signature)
     // ═══════════════════════════════════════════════════════════════════════════
 
-    /// Register a wallet and create permission set for a derivation path
-    /// Called by TEE relayer with user's signature
+    /// Create the permission set for a new derivation path, owned by this wallet
+    /// Called by TEE relayer with user's signature; further wallets are
+    /// added with `link_wallet`
     pub fn register_wallet(
         &mut self,
         derivation_path: DerivationPath,
@@ -123,7 +600,10 @@ impl PermissionContract {
         message: Vec<u8>,
         nonce: u64,
     ) {
-        self.assert_tee_relayer();
+        self.assert_not_paused();
+        self.assert_tee_relayer("register_wallet", &derivation_path);
+        self.ensure_storage_sponsor(&derivation_path);
+        let initial_storage = env::storage_usage();
 
         // Check nonce not used
         let nonce_key = format!("{}:{}", chain_address, nonce);
@@ -133,63 +613,93 @@ impl PermissionContract {
         );
         self.used_nonces.insert(&nonce_key, &true);
 
-        // Verify signature
-        let is_valid = self.verify_user_signature(
+        // Verify signature over the expected derivation path and nonce
+        self.verify_user_signature(
             &wallet_type,
             &public_key,
             &chain_address,
             &message,
             &signature,
+            &SignedAction::RegisterWallet {
+                derivation_path: &derivation_path,
+                nonce,
+            },
         );
-        assert!(is_valid, "Invalid signature");
 
-        // Verify message contains expected derivation path
-        let expected_msg = format!(
-            "Register wallet for derivation path: {} with nonce: {}",
-            derivation_path, nonce
-        );
+        // The first wallet locks the path; others need an owner's consent
         assert!(
-            message == expected_msg.as_bytes(),
-            "Message does not match expected format"
+            self.load_permissions(&derivation_path).is_none(),
+            "Derivation path already registered"
         );
-
-        // Create or update user permissions
-        let registered_wallet = RegisteredWallet {
-            wallet_type,
-            public_key,
-            chain_address: chain_address.clone(),
+        let perms = UserPermissions {
+            owner_wallets: vec![RegisteredWallet {
+                wallet_type: wallet_type.clone(),
+                public_key,
+                chain_address: chain_address.clone(),
+            }],
+            allowed_operations: UnorderedMap::new(format!("ops:{}", derivation_path).as_bytes()),
+            next_nonce: 1,
+            frozen: false,
+            threshold: 1,
+            proposals: Vec::new(),
+            spending_limits: Vec::new(),
+            destinations: Vec::new(),
+            legacy_operations: None,
         };
+        self.save_permissions(&derivation_path, perms);
 
-        if let Some(mut perms) = self.permissions.get(&derivation_path) {
-            // Add wallet to existing permissions
-            if !perms
-                .owner_wallets
-                .iter()
-                .any(|w| w.chain_address == chain_address)
-            {
-                perms.owner_wallets.push(registered_wallet);
-                self.permissions.insert(&derivation_path, &perms);
-            }
-        } else {
-            // Create new permission set
-            let perms = UserPermissions {
-                owner_wallets: vec![registered_wallet],
-                allowed_operations: UnorderedMap::new(
-                    format!("ops:{}", derivation_path).as_bytes(),
-                ),
-                next_nonce: 1,
-            };
-            self.permissions.insert(&derivation_path, &perms);
+        self.link_wallet_path(&chain_address, &derivation_path);
+        self.charge_storage(&derivation_path, initial_storage);
+
+        PermissionEvent::WalletRegistered {
+            derivation_path,
+            wallet_type,
+            chain_address,
         }
+        .emit();
+    }
+
+    /// Add a wallet to an existing path
+    /// The new wallet and a current owner wallet both sign the same link
+    /// message; the owner's nonce is consumed
+    pub fn link_wallet(
+        &mut self,
+        derivation_path: DerivationPath,
+        new_wallet: RegisteredWallet,
+        new_wallet_signature: Vec<u8>,
+        new_wallet_message: Vec<u8>,
+        signature: Vec<u8>,
+        message: Vec<u8>,
+        signer_address: String,
+        nonce: u64,
+    ) {
+        self.assert_not_paused();
+        self.assert_tee_relayer("link_wallet", &derivation_path);
+        self.ensure_storage_sponsor(&derivation_path);
+        let initial_storage = env::storage_usage();
 
-        // Map wallet address to derivation path
-        self.wallet_to_path
-            .insert(&chain_address, &derivation_path);
+        let action = SignedAction::LinkWallet {
+            derivation_path: &derivation_path,
+            new_wallet: &new_wallet,
+            signer_address: &signer_address,
+            nonce,
+        };
+        let mut perms =
+            self.authorize_owner_action(&action, &signer_address, &signature, &message, true);
+        self.verify_user_signature(
+            &new_wallet.wallet_type,
+            &new_wallet.public_key,
+            &new_wallet.chain_address,
+            &new_wallet_message,
+            &new_wallet_signature,
+            &action,
+        );
 
-        env::log_str(&format!(
-            "Registered wallet {} for path {}",
-            chain_address, derivation_path
-        ));
+        if let Err(e) = perms.check_new_wallet(&new_wallet.chain_address) {
+            env::panic_str(e);
+        }
+        self.apply_link_wallet(&derivation_path, &mut perms, new_wallet, signer_address);
+        self.commit_permissions(&derivation_path, perms, initial_storage);
     }
 
     /// Add an allowed operation (user must sign)
@@ -200,64 +710,28 @@ impl PermissionContract {
         signature: Vec<u8>,
         message: Vec<u8>,
         signer_address: String,
+        nonce: u64,
     ) -> String {
-        self.assert_tee_relayer();
-
-        // Get user permissions
-        let mut perms = self
-            .permissions
-            .get(&derivation_path)
-            .expect("No permissions for derivation path");
-
-        // Find signer's wallet
-        let signer_wallet = perms
-            .owner_wallets
-            .iter()
-            .find(|w| w.chain_address == signer_address)
-            .expect("Signer not authorized for this derivation path");
-
-        // Verify signature
-        let is_valid = self.verify_user_signature(
-            &signer_wallet.wallet_type,
-            &signer_wallet.public_key,
-            &signer_address,
-            &message,
-            &signature,
-        );
-        assert!(is_valid, "Invalid signature");
+        self.assert_not_paused();
+        self.assert_tee_relayer("add_allowed_operation", &derivation_path);
+        self.assert_relayer_scope(&operation.operation_type, None);
+        self.ensure_storage_sponsor(&derivation_path);
+        let initial_storage = env::storage_usage();
 
-        // Generate operation ID
-        let operation_id = format!("{}-{}", derivation_path, perms.next_nonce);
-        perms.next_nonce += 1;
-
-        // Create allowed operation
-        let allowed_op = AllowedOperation {
-            operation_id: operation_id.clone(),
-            derivation_path: derivation_path.clone(),
-            operation_type: operation.operation_type,
-            destination_address: operation.destination_address,
-            destination_chain: operation.destination_chain,
-            slippage_bps: operation.slippage_bps,
-            expires_at: operation.expires_at,
-            executed: false,
-            nonce: perms.next_nonce - 1,
-            created_at: env::block_timestamp(),
+        // Verify signature over this exact operation
+        let action = SignedAction::AddOperation {
+            derivation_path: &derivation_path,
+            operation: &operation,
+            nonce,
         };
+        let mut perms =
+            self.authorize_owner_action(&action, &signer_address, &signature, &message, true);
 
-        // Store operation
-        perms
-            .allowed_operations
-            .insert(&operation_id, &allowed_op);
-        self.permissions.insert(&derivation_path, &perms);
-
-        // Add to active operations index
-        let active_key = format!("{}:{}", derivation_path, operation_id);
-        self.active_operations.insert(&active_key);
-
-        env::log_str(&format!(
-            "Added operation {} for path {}",
-            operation_id, derivation_path
-        ));
+        if let Err(e) = perms.check_new_operation(&operation) {
+            env::panic_str(e);
+        }
+        let operation_id = self.apply_add_operation(&derivation_path, &mut perms, operation);
+        self.commit_permissions(&derivation_path, perms, initial_storage);
 
         operation_id
     }
@@ -270,117 +744,615 @@ impl PermissionContract {
         signature: Vec<u8>,
         message: Vec<u8>,
         signer_address: String,
+        nonce: u64,
     ) {
-        self.assert_tee_relayer();
-
-        // Get user permissions
-        let mut perms = self
-            .permissions
-            .get(&derivation_path)
-            .expect("No permissions for derivation path");
+        self.assert_not_paused();
+        self.assert_tee_relayer("remove_allowed_operation", &derivation_path);
+        let initial_storage = env::storage_usage();
 
-        // Find signer's wallet
-        let signer_wallet = perms
-            .owner_wallets
-            .iter()
-            .find(|w| w.chain_address == signer_address)
-            .expect("Signer not authorized for this derivation path");
+        // Verify signature over this exact operation ID
+        let action = SignedAction::RemoveOperation {
+            derivation_path: &derivation_path,
+            operation_id: &operation_id,
+            nonce,
+        };
+        let mut perms =
+            self.authorize_owner_action(&action, &signer_address, &signature, &message, true);
 
-        // Verify signature
-        let is_valid = self.verify_user_signature(
-            &signer_wallet.wallet_type,
-            &signer_wallet.public_key,
-            &signer_address,
-            &message,
-            &signature,
-        );
-        assert!(is_valid, "Invalid signature");
+        self.apply_remove_operation(&derivation_path, &mut perms, operation_id);
+        self.commit_permissions(&derivation_path, perms, initial_storage);
+    }
 
-        // Remove operation
-        perms.allowed_operations.remove(&operation_id);
-        self.permissions.insert(&derivation_path, &perms);
+    /// Cap how much of `asset` may leave the path per rolling `window`
+    /// (nanoseconds, at most 31 days), across all operations (user must sign)
+    /// Replacing an existing limit keeps its recorded spends
+    pub fn set_spending_limit(
+        &mut self,
+        derivation_path: DerivationPath,
+        asset: String,
+        limit: U128,
+        window: u64,
+        signature: Vec<u8>,
+        message: Vec<u8>,
+        signer_address: String,
+        nonce: u64,
+    ) {
+        self.assert_not_paused();
+        self.assert_tee_relayer("set_spending_limit", &derivation_path);
+        self.ensure_storage_sponsor(&derivation_path);
+        let initial_storage = env::storage_usage();
 
-        // Remove from active operations index
-        let active_key = format!("{}:{}", derivation_path, operation_id);
-        self.active_operations.remove(&active_key);
+        let action = SignedAction::SetSpendingLimit {
+            derivation_path: &derivation_path,
+            asset: &asset,
+            limit: limit.0,
+            window,
+            nonce,
+        };
+        let mut perms =
+            self.authorize_owner_action(&action, &signer_address, &signature, &message, true);
 
-        env::log_str(&format!(
-            "Removed operation {} from path {}",
-            operation_id, derivation_path
-        ));
+        if let Err(e) = SpendingLimit::check_window(window) {
+            env::panic_str(e);
+        }
+        self.apply_set_spending_limit(&derivation_path, &mut perms, asset, limit, window);
+        self.commit_permissions(&derivation_path, perms, initial_storage);
     }
 
-    // ═══════════════════════════════════════════════════════════════════════════
-    // Signature Requests (called by TEE)
-    // ═══════════════════════════════════════════════════════════════════════════
-
-    /// Request signature for an allowed operation
-    /// This validates against allowlist then calls MPC
-    pub fn sign_allowed(
+    /// Drop the spending limit for `asset` (user must sign)
+    pub fn remove_spending_limit(
         &mut self,
         derivation_path: DerivationPath,
-        operation_id: String,
-        payload: Vec<u8>,
-        key_type: String,
-        tee_price: Option<u128>,
-        tee_timestamp: Option<u64>,
-    ) -> Promise {
-        self.assert_tee_relayer();
-
-        // Get user permissions
-        let mut perms = self
-            .permissions
-            .get(&derivation_path)
-            .expect("No permissions for derivation path");
+        asset: String,
+        signature: Vec<u8>,
+        message: Vec<u8>,
+        signer_address: String,
+        nonce: u64,
+    ) {
+        self.assert_not_paused();
+        self.assert_tee_relayer("remove_spending_limit", &derivation_path);
+        let initial_storage = env::storage_usage();
 
-        // Get operation
-        let mut operation = perms
-            .allowed_operations
-            .get(&operation_id)
-            .expect("Operation not in allowlist");
+        let action = SignedAction::RemoveSpendingLimit {
+            derivation_path: &derivation_path,
+            asset: &asset,
+            nonce,
+        };
+        let mut perms =
+            self.authorize_owner_action(&action, &signer_address, &signature, &message, true);
 
-        // Validate operation
-        assert!(!operation.executed, "Operation already executed");
+        self.apply_remove_spending_limit(&derivation_path, &mut perms, asset);
+        self.commit_permissions(&derivation_path, perms, initial_storage);
+    }
 
-        if let Some(expires) = operation.expires_at {
-            assert!(
-                env::block_timestamp() < expires,
-                "Operation has expired"
-            );
-        }
+    /// Add a destination to the path's address book (user must sign)
+    /// It can receive funds after DESTINATION_ACTIVATION_DELAY_NS; operations
+    /// may only be created for and pay destinations in the book
+    pub fn add_destination(
+        &mut self,
+        derivation_path: DerivationPath,
+        chain: String,
+        address: String,
+        signature: Vec<u8>,
+        message: Vec<u8>,
+        signer_address: String,
+        nonce: u64,
+    ) {
+        self.assert_not_paused();
+        self.assert_tee_relayer("add_destination", &derivation_path);
+        self.ensure_storage_sponsor(&derivation_path);
+        let initial_storage = env::storage_usage();
 
-        // For conditional orders, validate price
-        if let Some(price) = tee_price {
-            if let Err(e) = self.validate_price_condition(&operation, price, tee_timestamp) {
-                env::panic_str(e);
-            }
-        }
+        let action = SignedAction::AddDestination {
+            derivation_path: &derivation_path,
+            chain: &chain,
+            address: &address,
+            nonce,
+        };
+        let mut perms =
+            self.authorize_owner_action(&action, &signer_address, &signature, &message, true);
 
-        // Mark as executed (prevent replay)
-        operation.executed = true;
-        perms.allowed_operations.insert(&operation_id, &operation);
-        self.permissions.insert(&derivation_path, &perms);
+        self.apply_add_destination(&derivation_path, &mut perms, chain, address);
+        self.commit_permissions(&derivation_path, perms, initial_storage);
+    }
 
-        // Remove from active operations index
-        let active_key = format!("{}:{}", derivation_path, operation_id);
-        self.active_operations.remove(&active_key);
+    /// Remove a destination from the path's address book (user must sign)
+    /// Operations paying it can no longer be signed; the last destination
+    /// cannot be removed
+    pub fn remove_destination(
+        &mut self,
+        derivation_path: DerivationPath,
+        chain: String,
+        address: String,
+        signature: Vec<u8>,
+        message: Vec<u8>,
+        signer_address: String,
+        nonce: u64,
+    ) {
+        self.assert_not_paused();
+        self.assert_tee_relayer("remove_destination", &derivation_path);
+        let initial_storage = env::storage_usage();
 
-        // Prepare MPC sign request
-        let domain_id = match key_type.as_str() {
-            "Eddsa" => 1u8,
-            "Ecdsa" => 0u8,
-            _ => panic!("Invalid key type"),
+        let action = SignedAction::RemoveDestination {
+            derivation_path: &derivation_path,
+            chain: &chain,
+            address: &address,
+            nonce,
         };
+        let mut perms =
+            self.authorize_owner_action(&action, &signer_address, &signature, &message, true);
 
-        let payload_hex = hex::encode(&payload);
-        let payload_v2 = if key_type == "Eddsa" {
-            PayloadV2 {
-                eddsa: Some(payload_hex),
-                ecdsa: None,
-            }
-        } else {
-            PayloadV2 {
-                eddsa: None,
+        self.apply_remove_destination(&derivation_path, &mut perms, chain, address);
+        self.commit_permissions(&derivation_path, perms, initial_storage);
+    }
+
+    /// Propose an allowlist, threshold, spending limit, address book, wallet
+    /// or unfreeze change, signed by an owner wallet
+    /// The proposer signs the same message as for the direct change; it is
+    /// applied as soon as `threshold` owner wallets have approved it
+    /// Returns the proposal ID
+    pub fn propose_change(
+        &mut self,
+        derivation_path: DerivationPath,
+        change: PathChange,
+        signature: Vec<u8>,
+        message: Vec<u8>,
+        signer_address: String,
+        nonce: u64,
+    ) -> String {
+        self.assert_not_paused();
+        self.assert_tee_relayer("propose_change", &derivation_path);
+        if let PathChange::AddOperation { operation } = &change {
+            self.assert_relayer_scope(&operation.operation_type, None);
+        }
+        self.ensure_storage_sponsor(&derivation_path);
+        let initial_storage = env::storage_usage();
+
+        let action = match &change {
+            PathChange::AddOperation { operation } => SignedAction::AddOperation {
+                derivation_path: &derivation_path,
+                operation,
+                nonce,
+            },
+            PathChange::RemoveOperation { operation_id } => SignedAction::RemoveOperation {
+                derivation_path: &derivation_path,
+                operation_id,
+                nonce,
+            },
+            PathChange::SetThreshold { threshold } => SignedAction::SetThreshold {
+                derivation_path: &derivation_path,
+                threshold: *threshold,
+                nonce,
+            },
+            PathChange::SetSpendingLimit {
+                asset,
+                limit,
+                window,
+            } => SignedAction::SetSpendingLimit {
+                derivation_path: &derivation_path,
+                asset,
+                limit: limit.0,
+                window: *window,
+                nonce,
+            },
+            PathChange::RemoveSpendingLimit { asset } => SignedAction::RemoveSpendingLimit {
+                derivation_path: &derivation_path,
+                asset,
+                nonce,
+            },
+            PathChange::AddDestination { chain, address } => SignedAction::AddDestination {
+                derivation_path: &derivation_path,
+                chain,
+                address,
+                nonce,
+            },
+            PathChange::RemoveDestination { chain, address } => SignedAction::RemoveDestination {
+                derivation_path: &derivation_path,
+                chain,
+                address,
+                nonce,
+            },
+            PathChange::Unfreeze => SignedAction::UnfreezePath {
+                derivation_path: &derivation_path,
+                nonce,
+            },
+            PathChange::LinkWallet { new_wallet, .. } => SignedAction::LinkWallet {
+                derivation_path: &derivation_path,
+                new_wallet,
+                signer_address: &signer_address,
+                nonce,
+            },
+            PathChange::RemoveWallet { wallet_address } => SignedAction::RemoveWallet {
+                derivation_path: &derivation_path,
+                wallet_address,
+                close_path: false,
+                nonce,
+            },
+            PathChange::RotateWallet {
+                old_address,
+                new_wallet,
+                ..
+            } => SignedAction::RotateWallet {
+                derivation_path: &derivation_path,
+                old_address,
+                new_wallet,
+                nonce,
+            },
+        };
+        let mut perms =
+            self.authorize_owner_action(&action, &signer_address, &signature, &message, false);
+        // A linked or rotated-in wallet proves it holds its key up front
+        if let PathChange::LinkWallet {
+            new_wallet,
+            new_wallet_signature,
+            new_wallet_message,
+        }
+        | PathChange::RotateWallet {
+            new_wallet,
+            new_wallet_signature,
+            new_wallet_message,
+            ..
+        } = &change
+        {
+            self.verify_user_signature(
+                &new_wallet.wallet_type,
+                &new_wallet.public_key,
+                &new_wallet.chain_address,
+                new_wallet_message,
+                new_wallet_signature,
+                &action,
+            );
+        }
+
+        if let Err(e) = perms.validate_change(&change) {
+            env::panic_str(e);
+        }
+
+        let now = env::block_timestamp();
+        perms.proposals.retain(|p| p.expires_at > now);
+        assert!(
+            perms.proposals.len() < MAX_PENDING_PROPOSALS,
+            "Too many pending proposals"
+        );
+
+        let id_input = borsh::to_vec(&(&derivation_path, &change, &signer_address, nonce))
+            .expect("Failed to serialize proposal");
+        let proposal = Proposal {
+            proposal_id: hex::encode(env::sha256(&id_input)),
+            change,
+            approvals: vec![signer_address.clone()],
+            expires_at: now + PROPOSAL_TTL_NS,
+        };
+        let proposal_id = proposal.proposal_id.clone();
+
+        PermissionEvent::ProposalCreated {
+            derivation_path: derivation_path.clone(),
+            proposal_id: proposal_id.clone(),
+            change: proposal.change.clone(),
+            signer_address,
+        }
+        .emit();
+
+        self.execute_if_approved(&derivation_path, &mut perms, proposal);
+        self.commit_permissions(&derivation_path, perms, initial_storage);
+
+        proposal_id
+    }
+
+    /// Approve a pending proposal with another owner wallet
+    pub fn approve_proposal(
+        &mut self,
+        derivation_path: DerivationPath,
+        proposal_id: String,
+        signature: Vec<u8>,
+        message: Vec<u8>,
+        signer_address: String,
+        nonce: u64,
+    ) {
+        self.assert_not_paused();
+        self.assert_tee_relayer("approve_proposal", &derivation_path);
+        let initial_storage = env::storage_usage();
+
+        let action = SignedAction::ApproveProposal {
+            derivation_path: &derivation_path,
+            proposal_id: &proposal_id,
+            nonce,
+        };
+        let mut perms =
+            self.authorize_owner_action(&action, &signer_address, &signature, &message, false);
+
+        let now = env::block_timestamp();
+        perms.proposals.retain(|p| p.expires_at > now);
+        let index = perms
+            .proposals
+            .iter()
+            .position(|p| p.proposal_id == proposal_id)
+            .expect("Proposal not found or expired");
+        let mut proposal = perms.proposals.remove(index);
+        assert!(
+            !proposal.approvals.contains(&signer_address),
+            "Wallet already approved this proposal"
+        );
+        proposal.approvals.push(signer_address.clone());
+
+        PermissionEvent::ProposalApproved {
+            derivation_path: derivation_path.clone(),
+            proposal_id,
+            signer_address,
+        }
+        .emit();
+
+        self.execute_if_approved(&derivation_path, &mut perms, proposal);
+        self.commit_permissions(&derivation_path, perms, initial_storage);
+    }
+
+    /// Remove an owner wallet (signed by it or another owner wallet)
+    /// Removing the last wallet requires `close_path`, which also deletes
+    /// every operation for the path and refunds its storage
+    pub fn remove_wallet(
+        &mut self,
+        derivation_path: DerivationPath,
+        wallet_address: String,
+        close_path: bool,
+        signature: Vec<u8>,
+        message: Vec<u8>,
+        signer_address: String,
+        nonce: u64,
+    ) {
+        self.assert_not_paused();
+        self.assert_tee_relayer("remove_wallet", &derivation_path);
+        let initial_storage = env::storage_usage();
+
+        let action = SignedAction::RemoveWallet {
+            derivation_path: &derivation_path,
+            wallet_address: &wallet_address,
+            close_path,
+            nonce,
+        };
+        let mut perms =
+            self.authorize_owner_action(&action, &signer_address, &signature, &message, true);
+
+        assert!(
+            perms
+                .owner_wallets
+                .iter()
+                .any(|w| w.chain_address == wallet_address),
+            "Wallet not registered for this derivation path"
+        );
+        let is_last = perms.owner_wallets.len() == 1;
+        assert!(
+            !is_last || close_path,
+            "Cannot remove the last wallet unless closing the path"
+        );
+        assert!(
+            is_last || !close_path,
+            "Cannot close a path that has other wallets"
+        );
+        self.apply_remove_wallet(&derivation_path, &mut perms, wallet_address, signer_address);
+
+        if close_path {
+            for operation in perms.operations() {
+                let active_key = format!("{}:{}", derivation_path, operation.operation_id);
+                self.active_operations.remove(&active_key);
+            }
+            perms.clear_operations();
+            self.permissions.remove(&derivation_path);
+            self.legacy_permissions.remove(&derivation_path);
+            if let Some(sponsor) = self.path_sponsors.remove(&derivation_path) {
+                self.charge_sponsor(&sponsor, initial_storage);
+            }
+            PermissionEvent::PathClosed { derivation_path }.emit();
+        } else {
+            self.save_permissions(&derivation_path, perms);
+            self.charge_storage(&derivation_path, initial_storage);
+        }
+    }
+
+    /// Replace an owner wallet with a new key (signed by it or another owner wallet)
+    /// The new wallet also signs the same rotate message to prove it holds the key
+    pub fn rotate_wallet(
+        &mut self,
+        derivation_path: DerivationPath,
+        old_address: String,
+        new_wallet: RegisteredWallet,
+        new_wallet_signature: Vec<u8>,
+        new_wallet_message: Vec<u8>,
+        signature: Vec<u8>,
+        message: Vec<u8>,
+        signer_address: String,
+        nonce: u64,
+    ) {
+        self.assert_not_paused();
+        self.assert_tee_relayer("rotate_wallet", &derivation_path);
+        let initial_storage = env::storage_usage();
+
+        let action = SignedAction::RotateWallet {
+            derivation_path: &derivation_path,
+            old_address: &old_address,
+            new_wallet: &new_wallet,
+            nonce,
+        };
+        let mut perms =
+            self.authorize_owner_action(&action, &signer_address, &signature, &message, true);
+        self.verify_user_signature(
+            &new_wallet.wallet_type,
+            &new_wallet.public_key,
+            &new_wallet.chain_address,
+            &new_wallet_message,
+            &new_wallet_signature,
+            &action,
+        );
+
+        if let Err(e) = perms.check_new_wallet(&new_wallet.chain_address) {
+            env::panic_str(e);
+        }
+        self.apply_rotate_wallet(
+            &derivation_path,
+            &mut perms,
+            old_address,
+            new_wallet,
+            signer_address,
+        );
+        self.commit_permissions(&derivation_path, perms, initial_storage);
+    }
+
+    /// Block all signing for a path (any owner wallet must sign)
+    /// Callable by anyone holding the signed message, so users can freeze
+    /// without a relayer and while the contract is paused; the contract
+    /// covers any storage it adds instead of the path sponsor
+    pub fn freeze_path(
+        &mut self,
+        derivation_path: DerivationPath,
+        signature: Vec<u8>,
+        message: Vec<u8>,
+        signer_address: String,
+        nonce: u64,
+    ) {
+        self.set_path_frozen(
+            derivation_path,
+            true,
+            signature,
+            message,
+            signer_address,
+            nonce,
+        );
+    }
+
+    /// Allow signing for a frozen path again (any owner wallet must sign)
+    /// Paths with a threshold above 1 unfreeze through `propose_change`
+    pub fn unfreeze_path(
+        &mut self,
+        derivation_path: DerivationPath,
+        signature: Vec<u8>,
+        message: Vec<u8>,
+        signer_address: String,
+        nonce: u64,
+    ) {
+        self.assert_not_paused();
+        self.set_path_frozen(
+            derivation_path,
+            false,
+            signature,
+            message,
+            signer_address,
+            nonce,
+        );
+    }
+
+    // ═══════════════════════════════════════════════════════════════════════════
+    // Signature Requests (called by TEE)
+    // ═══════════════════════════════════════════════════════════════════════════
+
+    /// Request signature for an allowed operation
+    /// This validates against allowlist then calls MPC; conditional
+    /// operations first fetch the price from the oracle. `amount` is the
+    /// source asset spent by this payload, taken from the remaining budget.
+    /// Resolves to the signature, or None if MPC signing failed and the
+    /// execution was reverted
+    pub fn sign_allowed(
+        &mut self,
+        derivation_path: DerivationPath,
+        operation_id: String,
+        payload: Vec<u8>,
+        key_type: String,
+        amount: U128,
+    ) -> Promise {
+        self.assert_not_paused();
+        self.assert_tee_relayer("sign_allowed", &derivation_path);
+
+        // Get user permissions
+        let perms = self
+            .load_permissions(&derivation_path)
+            .expect("No permissions for derivation path");
+
+        // Get operation
+        let operation = perms
+            .operation(&operation_id)
+            .expect("Operation not in allowlist");
+        self.assert_relayer_scope(&operation.operation_type, Some(&key_type));
+
+        // Validate operation, including its recurring schedule and budget
+        assert!(!perms.frozen, "Derivation path is frozen");
+        if let Err(e) = operation.check_executable(env::block_timestamp()) {
+            env::panic_str(e);
+        }
+        assert!(amount.0 > 0, "Amount must be positive");
+        assert!(
+            amount.0 <= operation.execution_max_amount(),
+            "Amount exceeds remaining budget"
+        );
+        let source_asset = operation.operation_type.source_asset();
+        if let Err(e) = perms.check_spend(source_asset, amount.0, env::block_timestamp()) {
+            env::panic_str(e);
+        }
+        if let Err(e) = perms.check_destination(
+            &operation.destination_chain,
+            &operation.destination_address,
+            env::block_timestamp(),
+        ) {
+            env::panic_str(e);
+        }
+
+        // Check the payload only does what the operation allows and
+        // derive the bytes the MPC signs from it
+        let (domain_id, sign_payload) = match key_type.as_str() {
+            "Eddsa" if operation.source_chain.eq_ignore_ascii_case("near") => {
+                let allowed_methods = &self.allowed_near_methods;
+                let is_allowed_method = |receiver: &str, method: &str| {
+                    allowed_methods.contains(&format!("{}:{}", receiver, method))
+                };
+                // The transaction must be signed for the path's own account
+                let root_key = self
+                    .mpc_ed25519_key
+                    .unwrap_or_else(|| env::panic_str("MPC Ed25519 key not configured"));
+                let path_account = derived_near_account(
+                    &root_key,
+                    env::current_account_id().as_str(),
+                    &derivation_path,
+                )
+                .unwrap_or_else(|e| env::panic_str(e));
+                if let Err(e) = validate_near_payload(
+                    &operation,
+                    &payload,
+                    &path_account,
+                    amount.0,
+                    is_allowed_method,
+                ) {
+                    env::panic_str(e);
+                }
+                // NEAR signs the sha256 of the borsh-serialized transaction
+                (1u8, near_signing_hash(&payload).to_vec())
+            }
+            "Eddsa" => {
+                let allowed_programs = &self.allowed_solana_programs;
+                if let Err(e) = validate_solana_payload(&operation, &payload, amount.0, |program| {
+                    allowed_programs.contains(&program.to_string())
+                }) {
+                    env::panic_str(e);
+                }
+                // Solana signs the message bytes directly
+                (1u8, payload)
+            }
+            "Ecdsa" => {
+                if let Err(e) = validate_evm_payload(&operation, &payload, amount.0) {
+                    env::panic_str(e);
+                }
+                // EVM signs the keccak256 of the unsigned transaction
+                (0u8, evm_signing_hash(&payload).to_vec())
+            }
+            _ => panic!("Invalid key type"),
+        };
+
+        // Prepare MPC sign request
+        let payload_hex = hex::encode(&sign_payload);
+        let payload_v2 = if key_type == "Eddsa" {
+            PayloadV2 {
+                eddsa: Some(payload_hex),
+                ecdsa: None,
+            }
+        } else {
+            PayloadV2 {
+                eddsa: None,
                 ecdsa: Some(payload_hex),
             }
         };
@@ -391,10 +1363,127 @@ impl PermissionContract {
             domain_id,
         };
 
-        env::log_str(&format!(
-            "Requesting MPC signature for operation {}",
-            operation_id
-        ));
+        // For conditional orders, check the oracle price before signing
+        if let Some((price_asset, quote_asset)) = operation.operation_type.price_assets() {
+            return self.query_oracle_prices(price_asset, quote_asset).then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_ORACLE_CALLBACK)
+                    .on_oracle_price(derivation_path, operation_id, sign_request, amount),
+            );
+        }
+
+        self.request_mpc_signature(
+            derivation_path,
+            operation_id,
+            perms,
+            operation,
+            sign_request,
+            amount,
+        )
+    }
+
+    /// Callback with oracle prices for a conditional operation
+    /// Re-checks the operation, validates the price condition, then calls MPC
+    #[private]
+    pub fn on_oracle_price(
+        &mut self,
+        derivation_path: DerivationPath,
+        operation_id: String,
+        sign_request: SignRequest,
+        amount: U128,
+    ) -> Promise {
+        self.assert_not_paused();
+        let perms = self
+            .load_permissions(&derivation_path)
+            .expect("No permissions for derivation path");
+        let operation = perms
+            .operation(&operation_id)
+            .expect("Operation not in allowlist");
+
+        // The path or operation may have changed while the oracle was queried
+        assert!(!perms.frozen, "Derivation path is frozen");
+        if let Err(e) = operation.check_executable(env::block_timestamp()) {
+            env::panic_str(e);
+        }
+        assert!(
+            amount.0 <= operation.execution_max_amount(),
+            "Amount exceeds remaining budget"
+        );
+        let source_asset = operation.operation_type.source_asset();
+        if let Err(e) = perms.check_spend(source_asset, amount.0, env::block_timestamp()) {
+            env::panic_str(e);
+        }
+        if let Err(e) = perms.check_destination(
+            &operation.destination_chain,
+            &operation.destination_address,
+            env::block_timestamp(),
+        ) {
+            env::panic_str(e);
+        }
+
+        let base = oracle_price_result(0);
+        let quote = if env::promise_results_count() > 1 {
+            oracle_price_result(1)
+        } else {
+            OraclePrice::usd()
+        };
+        let price = quote_price(&base, &quote).unwrap_or_else(|e| env::panic_str(e));
+        let published_at =
+            (base.publish_time.min(quote.publish_time).max(0) as u64).saturating_mul(1_000_000_000);
+
+        if let Err(e) = self.validate_price_condition(&operation, price, Some(published_at)) {
+            env::panic_str(e);
+        }
+
+        self.request_mpc_signature(
+            derivation_path,
+            operation_id,
+            perms,
+            operation,
+            sign_request,
+            amount,
+        )
+    }
+
+    /// Record an execution of the operation and request its MPC signature
+    fn request_mpc_signature(
+        &mut self,
+        derivation_path: DerivationPath,
+        operation_id: String,
+        mut perms: UserPermissions,
+        mut operation: AllowedOperation,
+        sign_request: SignRequest,
+        amount: U128,
+    ) -> Promise {
+        let initial_storage = env::storage_usage();
+
+        // Count the execution and spend its amount (prevents replay within
+        // the interval and beyond the budget)
+        let now = env::block_timestamp();
+        let previous_executed_at = operation.last_executed_at;
+        operation.record_execution(now, amount.0);
+        perms.record_spend(operation.operation_type.source_asset(), amount.0, now);
+        let execution_count = operation.execution_count;
+        let consumed_amount = operation.consumed_amount;
+        let finished = operation.executed;
+        perms.insert_operation(&operation);
+        self.save_permissions(&derivation_path, perms);
+
+        // Remove from active operations index once the budget or executions run out
+        if finished {
+            let active_key = format!("{}:{}", derivation_path, operation_id);
+            self.active_operations.remove(&active_key);
+        }
+        self.charge_storage(&derivation_path, initial_storage);
+
+        PermissionEvent::OperationExecuted {
+            derivation_path: derivation_path.clone(),
+            operation_id: operation_id.clone(),
+            execution_count,
+            amount,
+            consumed_amount,
+        }
+        .emit();
 
         // Cross-contract call to ChainSignatureContract
         ext_chain_sig::ext(self.mpc_contract.clone())
@@ -404,41 +1493,69 @@ impl PermissionContract {
             .then(
                 Self::ext(env::current_account_id())
                     .with_static_gas(GAS_FOR_CALLBACK)
-                    .on_mpc_sign_complete(derivation_path, operation_id),
+                    .on_mpc_sign_complete(
+                        derivation_path,
+                        operation_id,
+                        now,
+                        previous_executed_at,
+                        amount,
+                    ),
             )
     }
 
     /// Callback after MPC sign completes
+    /// On failure the execution recorded at `executed_at` is reverted and
+    /// None is returned; panicking here would also roll back the revert
     #[private]
     pub fn on_mpc_sign_complete(
         &mut self,
         derivation_path: DerivationPath,
         operation_id: String,
-        #[callback_result] result: Result<Ed25519SignatureResponse, PromiseError>,
-    ) -> Vec<u8> {
-        match result {
-            Ok(response) => {
-                env::log_str(&format!(
-                    "MPC signature received for operation {} ({} bytes)",
+        executed_at: u64,
+        previous_executed_at: Option<u64>,
+        amount: U128,
+        #[callback_result] result: Result<SignatureResponse, PromiseError>,
+    ) -> Option<MpcSignature> {
+        let signature = result
+            .map_err(|e| format!("{:?}", e))
+            .and_then(|response| response.normalize().map_err(str::to_string));
+
+        match signature {
+            Ok(signature) => {
+                PermissionEvent::MpcSignatureReceived {
+                    derivation_path,
                     operation_id,
-                    response.signature.len()
-                ));
-                response.signature
+                }
+                .emit();
+                Some(signature)
             }
             Err(e) => {
-                // Revert executed flag on failure
-                if let Some(mut perms) = self.permissions.get(&derivation_path) {
-                    if let Some(mut operation) = perms.allowed_operations.get(&operation_id) {
-                        operation.executed = false;
-                        perms.allowed_operations.insert(&operation_id, &operation);
-                        self.permissions.insert(&derivation_path, &perms);
-
-                        // Re-add to active operations
-                        let active_key = format!("{}:{}", derivation_path, operation_id);
-                        self.active_operations.insert(&active_key);
+                // Revert the recorded execution, unless the operation was removed
+                if let Some(mut perms) = self.load_permissions(&derivation_path) {
+                    if let Some(mut operation) = perms.operation(&operation_id) {
+                        perms.refund_spend(
+                            operation.operation_type.source_asset(),
+                            amount.0,
+                            executed_at,
+                        );
+                        operation.revert_execution(executed_at, previous_executed_at, amount.0);
+                        let active = !operation.executed;
+                        perms.insert_operation(&operation);
+                        self.save_permissions(&derivation_path, perms);
+
+                        if active {
+                            let active_key = format!("{}:{}", derivation_path, operation_id);
+                            self.active_operations.insert(&active_key);
+                        }
                     }
                 }
-                env::panic_str(&format!("MPC sign failed: {:?}", e));
+                PermissionEvent::MpcSignatureFailed {
+                    derivation_path,
+                    operation_id,
+                    error: e,
+                }
+                .emit();
+                None
             }
         }
     }
@@ -462,8 +1579,8 @@ impl PermissionContract {
                 let path = parts[0].to_string();
                 let op_id = parts[1..].join(":");
 
-                if let Some(perms) = self.permissions.get(&path) {
-                    if let Some(op) = perms.allowed_operations.get(&op_id) {
+                if let Some(perms) = self.load_permissions(&path) {
+                    if let Some(op) = perms.operation(&op_id) {
                         results.push((path, op));
                     }
                 }
@@ -473,10 +1590,16 @@ impl PermissionContract {
         results
     }
 
+    /// Get owner wallets and operations for a derivation path
+    pub fn get_permissions(&self, derivation_path: DerivationPath) -> Option<UserPermissionsView> {
+        self.load_permissions(&derivation_path)
+            .map(|perms| UserPermissionsView::from(&perms))
+    }
+
     /// Get operations for a specific derivation path
     pub fn get_operations(&self, derivation_path: DerivationPath) -> Vec<AllowedOperation> {
-        if let Some(perms) = self.permissions.get(&derivation_path) {
-            perms.allowed_operations.values().collect()
+        if let Some(perms) = self.load_permissions(&derivation_path) {
+            perms.operations()
         } else {
             Vec::new()
         }
@@ -488,50 +1611,152 @@ impl PermissionContract {
         derivation_path: DerivationPath,
         operation_id: String,
     ) -> Option<AllowedOperation> {
-        self.permissions
-            .get(&derivation_path)
-            .and_then(|perms| perms.allowed_operations.get(&operation_id))
+        self.load_permissions(&derivation_path)
+            .and_then(|perms| perms.operation(&operation_id))
     }
 
-    /// Check if an operation is allowed (not executed, not expired)
+    /// Check if an operation may be signed now (not executed or expired,
+    /// and due under its recurring schedule)
     pub fn is_operation_allowed(
         &self,
         derivation_path: DerivationPath,
         operation_id: String,
     ) -> bool {
-        if let Some(perms) = self.permissions.get(&derivation_path) {
-            if let Some(op) = perms.allowed_operations.get(&operation_id) {
-                if op.executed {
-                    return false;
-                }
-                if let Some(expires) = op.expires_at {
-                    if env::block_timestamp() >= expires {
-                        return false;
-                    }
-                }
-                return true;
+        if let Some(perms) = self.load_permissions(&derivation_path) {
+            if self.paused || perms.frozen {
+                return false;
+            }
+            if let Some(op) = perms.operation(&operation_id) {
+                return op.check_executable(env::block_timestamp()).is_ok();
             }
         }
         false
     }
 
-    /// Get derivation path for a wallet address
+    /// Get unexpired proposals for a derivation path
+    pub fn get_proposals(&self, derivation_path: DerivationPath) -> Vec<Proposal> {
+        let now = env::block_timestamp();
+        self.load_permissions(&derivation_path)
+            .map(|perms| {
+                perms
+                    .proposals
+                    .into_iter()
+                    .filter(|p| p.expires_at > now)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Get spending limits for a derivation path with their current usage
+    pub fn get_spending_limits(&self, derivation_path: DerivationPath) -> Vec<SpendingLimitView> {
+        let now = env::block_timestamp();
+        self.load_permissions(&derivation_path)
+            .map(|perms| {
+                perms
+                    .spending_limits
+                    .iter()
+                    .map(|limit| SpendingLimitView::new(limit, now))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Get the address book for a derivation path, including pending destinations
+    pub fn get_destinations(&self, derivation_path: DerivationPath) -> Vec<ApprovedDestination> {
+        self.load_permissions(&derivation_path)
+            .map(|perms| perms.destinations)
+            .unwrap_or_default()
+    }
+
+    /// Get the primary derivation path for a wallet address
+    /// Wallets that own several paths report the first one linked;
+    /// use `get_paths_for_wallet` to list all of them
     pub fn get_path_for_wallet(&self, chain_address: String) -> Option<DerivationPath> {
         self.wallet_to_path.get(&chain_address)
     }
 
-    /// Check if account is a registered TEE relayer
+    /// Get all derivation paths a wallet address owns (paginated)
+    pub fn get_paths_for_wallet(
+        &self,
+        chain_address: String,
+        from_index: u64,
+        limit: u64,
+    ) -> Vec<DerivationPath> {
+        match self.wallet_paths.get(&chain_address) {
+            Some(paths) => paths
+                .iter()
+                .skip(from_index as usize)
+                .take(limit as usize)
+                .collect(),
+            // Linked before path sets existed: only the primary path is known
+            None => self
+                .wallet_to_path
+                .get(&chain_address)
+                .into_iter()
+                .skip(from_index as usize)
+                .take(limit as usize)
+                .collect(),
+        }
+    }
+
+    /// Get the lowest nonce the next signed mutation from this wallet may use
+    pub fn get_next_nonce(&self, chain_address: String) -> u64 {
+        self.wallet_nonces.get(&chain_address).unwrap_or(0)
+    }
+
+    /// Check if account may act as a TEE relayer: validly attested, or
+    /// registered while attestation is not required
     pub fn is_tee_relayer(&self, account: AccountId) -> bool {
-        self.tee_relayers.contains(&account)
+        let attested = self
+            .attested_relayers
+            .get(&account)
+            .is_some_and(|relayer| self.is_attestation_valid(&relayer));
+        attested || (!self.require_attestation && self.tee_relayers.contains(&account))
+    }
+
+    /// Get a relayer's scope; None if it is unrestricted
+    pub fn get_relayer_scope(&self, account: AccountId) -> Option<RelayerScope> {
+        self.relayer_scopes.get(&account)
+    }
+
+    /// Get a relayer's attestation, including expired ones
+    pub fn get_attested_relayer(&self, account: AccountId) -> Option<AttestedRelayer> {
+        self.attested_relayers.get(&account)
+    }
+
+    /// Check if an account holds a role (the owner holds every role)
+    pub fn has_role(&self, account_id: AccountId, role: Role) -> bool {
+        account_id == self.owner
+            || self
+                .roles
+                .get(&account_id)
+                .is_some_and(|roles| roles.contains(&role))
+    }
+
+    /// Get roles granted to an account
+    pub fn get_roles(&self, account_id: AccountId) -> Vec<Role> {
+        self.roles.get(&account_id).unwrap_or_default()
     }
 
     /// Get contract configuration
     pub fn get_config(&self) -> ContractConfig {
         ContractConfig {
             owner: self.owner.clone(),
+            pending_owner: self.pending_owner.clone(),
+            paused: self.paused,
             mpc_contract: self.mpc_contract.clone(),
             tee_relayers: self.tee_relayers.iter().collect(),
-            active_operations_count: self.active_operations.len() as u64,
+            active_operations_count: self.active_operations.len(),
+            allowed_solana_programs: self.allowed_solana_programs.to_vec(),
+            allowed_near_methods: self.allowed_near_methods.to_vec(),
+            price_oracle: self.price_oracle.clone(),
+            price_feeds: self.price_feeds.to_vec(),
+            require_attestation: self.require_attestation,
+            attestation_roots: self.attestation_roots.to_vec(),
+            approved_measurements: self.approved_measurements.to_vec(),
+            mpc_ed25519_key: self
+                .mpc_ed25519_key
+                .map(|key| format!("ed25519:{}", bs58::encode(key).into_string())),
         }
     }
 
@@ -539,33 +1764,680 @@ impl PermissionContract {
     // Internal Methods
     // ═══════════════════════════════════════════════════════════════════════════
 
-    fn assert_owner(&self) {
-        assert_eq!(
-            env::predecessor_account_id(),
-            self.owner,
-            "Only owner can call this method"
-        );
+    /// Permissions for a path, upgraded to the current layout
+    fn load_permissions(&self, derivation_path: &DerivationPath) -> Option<UserPermissions> {
+        self.permissions
+            .get(derivation_path)
+            .map(Into::into)
+            .or_else(|| {
+                self.legacy_permissions
+                    .get(derivation_path)
+                    .map(|perms| UserPermissions::from_legacy(perms, derivation_path))
+            })
     }
 
-    fn assert_tee_relayer(&self) {
-        assert!(
-            self.tee_relayers.contains(&env::predecessor_account_id()),
-            "Only authorized TEE relayers can call this method"
-        );
+    /// Store permissions for a path in the current layout, moving a record
+    /// of the first release and its operations out of legacy storage
+    fn save_permissions(&mut self, derivation_path: &DerivationPath, mut perms: UserPermissions) {
+        if perms.legacy_operations.is_some() {
+            perms.upgrade_legacy_operations();
+            self.legacy_permissions.remove(derivation_path);
+        }
+        self.permissions.insert(derivation_path, &perms.into());
     }
 
-    fn verify_user_signature(
-        &self,
-        wallet_type: &WalletType,
-        public_key: &[u8],
-        chain_address: &str,
-        message: &[u8],
-        signature: &[u8],
-    ) -> bool {
-        match wallet_type {
-            WalletType::Near => verify_near_signature(public_key, message, signature),
-            WalletType::Solana => verify_solana_signature(public_key, message, signature),
-            WalletType::Evm => verify_evm_signature(chain_address, message, signature),
+    /// Save permissions and charge the path sponsor for storage used since
+    /// `initial_storage`
+    fn commit_permissions(
+        &mut self,
+        derivation_path: &DerivationPath,
+        perms: UserPermissions,
+        initial_storage: u64,
+    ) {
+        self.save_permissions(derivation_path, perms);
+        self.charge_storage(derivation_path, initial_storage);
+    }
+
+    /// Load the permissions `action` changes and check that owner wallet
+    /// `signer_address` signed it, consuming the action's nonce
+    /// `single_approval` rejects paths whose changes need a proposal
+    fn authorize_owner_action(
+        &mut self,
+        action: &SignedAction,
+        signer_address: &str,
+        signature: &[u8],
+        message: &[u8],
+        single_approval: bool,
+    ) -> UserPermissions {
+        let derivation_path = action.derivation_path().to_string();
+        let perms = self
+            .load_permissions(&derivation_path)
+            .expect("No permissions for derivation path");
+
+        let signer_wallet = perms
+            .owner_wallets
+            .iter()
+            .find(|w| w.chain_address == signer_address)
+            .expect("Signer not authorized for this derivation path");
+        if single_approval {
+            perms.assert_single_approval();
+        }
+
+        self.verify_user_signature(
+            &signer_wallet.wallet_type,
+            &signer_wallet.public_key,
+            signer_address,
+            message,
+            signature,
+            action,
+        );
+        self.consume_wallet_nonce(signer_address, action.nonce());
+
+        perms
+    }
+
+    fn assert_owner(&self) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner,
+            "Only owner can call this method"
+        );
+    }
+
+    fn assert_role(&self, role: Role) {
+        assert!(
+            self.has_role(env::predecessor_account_id(), role),
+            "Caller does not have role {:?}",
+            role
+        );
+    }
+
+    fn assert_not_paused(&self) {
+        assert!(!self.paused, "Contract is paused");
+    }
+
+    /// Verify an owner wallet's signed freeze/unfreeze and apply it
+    fn set_path_frozen(
+        &mut self,
+        derivation_path: DerivationPath,
+        frozen: bool,
+        signature: Vec<u8>,
+        message: Vec<u8>,
+        signer_address: String,
+        nonce: u64,
+    ) {
+        let initial_storage = env::storage_usage();
+        let action = if frozen {
+            SignedAction::FreezePath {
+                derivation_path: &derivation_path,
+                nonce,
+            }
+        } else {
+            SignedAction::UnfreezePath {
+                derivation_path: &derivation_path,
+                nonce,
+            }
+        };
+        // Freezing stays single-signer so any owner can stop a compromise
+        let mut perms =
+            self.authorize_owner_action(&action, &signer_address, &signature, &message, !frozen);
+
+        perms.frozen = frozen;
+        if frozen {
+            // The contract absorbs a freeze's storage so a drained sponsor
+            // cannot block an owner from stopping a compromise
+            self.save_permissions(&derivation_path, perms);
+        } else {
+            self.commit_permissions(&derivation_path, perms, initial_storage);
+        }
+
+        if frozen {
+            PermissionEvent::PathFrozen {
+                derivation_path,
+                signer_address,
+            }
+            .emit();
+        } else {
+            PermissionEvent::PathUnfrozen {
+                derivation_path,
+                signer_address,
+            }
+            .emit();
+        }
+    }
+
+    /// Add an owner wallet and index the path under it
+    fn apply_link_wallet(
+        &mut self,
+        derivation_path: &DerivationPath,
+        perms: &mut UserPermissions,
+        new_wallet: RegisteredWallet,
+        signer_address: String,
+    ) {
+        perms.owner_wallets.push(new_wallet.clone());
+        self.link_wallet_path(&new_wallet.chain_address, derivation_path);
+
+        PermissionEvent::WalletLinked {
+            derivation_path: derivation_path.clone(),
+            wallet_type: new_wallet.wallet_type,
+            chain_address: new_wallet.chain_address,
+            signer_address,
+        }
+        .emit();
+    }
+
+    /// Remove an owner wallet and drop the path from its index
+    fn apply_remove_wallet(
+        &mut self,
+        derivation_path: &DerivationPath,
+        perms: &mut UserPermissions,
+        wallet_address: String,
+        signer_address: String,
+    ) {
+        perms
+            .owner_wallets
+            .retain(|w| w.chain_address != wallet_address);
+        self.unlink_wallet_path(&wallet_address, derivation_path);
+
+        PermissionEvent::WalletRemoved {
+            derivation_path: derivation_path.clone(),
+            chain_address: wallet_address,
+            signer_address,
+        }
+        .emit();
+    }
+
+    /// Replace an owner wallet in place, keeping its position
+    fn apply_rotate_wallet(
+        &mut self,
+        derivation_path: &DerivationPath,
+        perms: &mut UserPermissions,
+        old_address: String,
+        new_wallet: RegisteredWallet,
+        signer_address: String,
+    ) {
+        let old_wallet = perms
+            .owner_wallets
+            .iter_mut()
+            .find(|w| w.chain_address == old_address)
+            .expect("Wallet not registered for this derivation path");
+        *old_wallet = new_wallet.clone();
+        self.unlink_wallet_path(&old_address, derivation_path);
+        self.link_wallet_path(&new_wallet.chain_address, derivation_path);
+
+        PermissionEvent::WalletRotated {
+            derivation_path: derivation_path.clone(),
+            old_address,
+            new_address: new_wallet.chain_address,
+            signer_address,
+        }
+        .emit();
+    }
+
+    /// Create an operation from `operation` and index it as active
+    fn apply_add_operation(
+        &mut self,
+        derivation_path: &DerivationPath,
+        perms: &mut UserPermissions,
+        operation: AllowedOperationInput,
+    ) -> String {
+        // Generate operation ID
+        let operation_id = format!("{}-{}", derivation_path, perms.next_nonce);
+        perms.next_nonce += 1;
+
+        // Create allowed operation
+        let allowed_op = AllowedOperation {
+            operation_id: operation_id.clone(),
+            derivation_path: derivation_path.clone(),
+            operation_type: operation.operation_type,
+            source_chain: operation.source_chain,
+            destination_address: operation.destination_address,
+            destination_chain: operation.destination_chain,
+            slippage_bps: operation.slippage_bps,
+            expires_at: operation.expires_at,
+            executed: false,
+            nonce: perms.next_nonce - 1,
+            created_at: env::block_timestamp(),
+            recurring: operation.recurring,
+            execution_count: 0,
+            last_executed_at: None,
+            consumed_amount: U128(0),
+        };
+
+        // Store operation and add it to the active operations index
+        perms.insert_operation(&allowed_op);
+        let active_key = format!("{}:{}", derivation_path, operation_id);
+        self.active_operations.insert(&active_key);
+
+        PermissionEvent::OperationAdded {
+            derivation_path: derivation_path.clone(),
+            operation: Box::new(allowed_op),
+        }
+        .emit();
+
+        operation_id
+    }
+
+    /// Delete an operation and drop it from the active operations index
+    fn apply_remove_operation(
+        &mut self,
+        derivation_path: &DerivationPath,
+        perms: &mut UserPermissions,
+        operation_id: String,
+    ) {
+        if let Some(operation) = perms.operation(&operation_id) {
+            self.assert_relayer_scope(&operation.operation_type, None);
+        }
+        perms.remove_operation(&operation_id);
+        let active_key = format!("{}:{}", derivation_path, operation_id);
+        self.active_operations.remove(&active_key);
+
+        PermissionEvent::OperationRemoved {
+            derivation_path: derivation_path.clone(),
+            operation_id,
+        }
+        .emit();
+    }
+
+    /// Add or replace the spending limit for `asset`
+    fn apply_set_spending_limit(
+        &mut self,
+        derivation_path: &DerivationPath,
+        perms: &mut UserPermissions,
+        asset: String,
+        limit: U128,
+        window: u64,
+    ) {
+        match perms.spending_limits.iter_mut().find(|l| l.asset == asset) {
+            Some(existing) => {
+                existing.limit = limit.0;
+                existing.window = window;
+            }
+            None => perms.spending_limits.push(SpendingLimit {
+                asset: asset.clone(),
+                limit: limit.0,
+                window,
+                spends: Vec::new(),
+            }),
+        }
+
+        PermissionEvent::SpendingLimitSet {
+            derivation_path: derivation_path.clone(),
+            asset,
+            limit,
+            window,
+        }
+        .emit();
+    }
+
+    /// Delete the spending limit for `asset`
+    fn apply_remove_spending_limit(
+        &mut self,
+        derivation_path: &DerivationPath,
+        perms: &mut UserPermissions,
+        asset: String,
+    ) {
+        let index = perms
+            .spending_limits
+            .iter()
+            .position(|l| l.asset == asset)
+            .expect("No spending limit for asset");
+        perms.spending_limits.remove(index);
+
+        PermissionEvent::SpendingLimitRemoved {
+            derivation_path: derivation_path.clone(),
+            asset,
+        }
+        .emit();
+    }
+
+    /// Add a destination to the address book, active after the delay
+    fn apply_add_destination(
+        &mut self,
+        derivation_path: &DerivationPath,
+        perms: &mut UserPermissions,
+        chain: String,
+        address: String,
+    ) {
+        assert!(
+            perms.destination(&chain, &address).is_none(),
+            "Destination already in address book"
+        );
+
+        let active_at = env::block_timestamp() + DESTINATION_ACTIVATION_DELAY_NS;
+        perms.destinations.push(ApprovedDestination {
+            chain: chain.clone(),
+            address: address.clone(),
+            active_at,
+        });
+
+        PermissionEvent::DestinationAdded {
+            derivation_path: derivation_path.clone(),
+            chain,
+            address,
+            active_at,
+        }
+        .emit();
+    }
+
+    /// Delete a destination from the address book
+    fn apply_remove_destination(
+        &mut self,
+        derivation_path: &DerivationPath,
+        perms: &mut UserPermissions,
+        chain: String,
+        address: String,
+    ) {
+        let index = perms
+            .destinations
+            .iter()
+            .position(|d| d.matches(&chain, &address))
+            .expect("Destination not in address book");
+        perms.destinations.remove(index);
+
+        PermissionEvent::DestinationRemoved {
+            derivation_path: derivation_path.clone(),
+            chain,
+            address,
+        }
+        .emit();
+    }
+
+    /// Apply `proposal` once enough current owner wallets approved it,
+    /// otherwise keep it pending
+    fn execute_if_approved(
+        &mut self,
+        derivation_path: &DerivationPath,
+        perms: &mut UserPermissions,
+        proposal: Proposal,
+    ) {
+        let approvals = proposal
+            .approvals
+            .iter()
+            .filter(|a| perms.owner_wallets.iter().any(|w| &w.chain_address == *a))
+            .count();
+        if approvals < perms.threshold as usize {
+            perms.proposals.push(proposal);
+            return;
+        }
+        if let Err(e) = perms.validate_change(&proposal.change) {
+            env::panic_str(e);
+        }
+
+        let signer_address = proposal.approvals.last().cloned().unwrap_or_default();
+        match proposal.change {
+            PathChange::AddOperation { operation } => {
+                // The approving relayer may differ from the proposing one
+                self.assert_relayer_scope(&operation.operation_type, None);
+                self.apply_add_operation(derivation_path, perms, *operation);
+            }
+            PathChange::RemoveOperation { operation_id } => {
+                self.apply_remove_operation(derivation_path, perms, operation_id);
+            }
+            PathChange::SetThreshold { threshold } => {
+                perms.threshold = threshold;
+                PermissionEvent::ThresholdChanged {
+                    derivation_path: derivation_path.clone(),
+                    threshold,
+                }
+                .emit();
+            }
+            PathChange::SetSpendingLimit {
+                asset,
+                limit,
+                window,
+            } => {
+                self.apply_set_spending_limit(derivation_path, perms, asset, limit, window);
+            }
+            PathChange::RemoveSpendingLimit { asset } => {
+                self.apply_remove_spending_limit(derivation_path, perms, asset);
+            }
+            PathChange::AddDestination { chain, address } => {
+                self.apply_add_destination(derivation_path, perms, chain, address);
+            }
+            PathChange::RemoveDestination { chain, address } => {
+                self.apply_remove_destination(derivation_path, perms, chain, address);
+            }
+            PathChange::Unfreeze => {
+                perms.frozen = false;
+                PermissionEvent::PathUnfrozen {
+                    derivation_path: derivation_path.clone(),
+                    signer_address,
+                }
+                .emit();
+            }
+            PathChange::LinkWallet { new_wallet, .. } => {
+                self.apply_link_wallet(derivation_path, perms, new_wallet, signer_address);
+            }
+            PathChange::RemoveWallet { wallet_address } => {
+                self.apply_remove_wallet(derivation_path, perms, wallet_address, signer_address);
+            }
+            PathChange::RotateWallet {
+                old_address,
+                new_wallet,
+                ..
+            } => {
+                self.apply_rotate_wallet(
+                    derivation_path,
+                    perms,
+                    old_address,
+                    new_wallet,
+                    signer_address,
+                );
+            }
+        }
+
+        PermissionEvent::ProposalExecuted {
+            derivation_path: derivation_path.clone(),
+            proposal_id: proposal.proposal_id,
+        }
+        .emit();
+    }
+
+    /// Load a wallet's path set, seeding it from `wallet_to_path` for
+    /// wallets linked before path sets existed
+    fn load_wallet_paths(&self, chain_address: &String) -> UnorderedSet<DerivationPath> {
+        self.wallet_paths.get(chain_address).unwrap_or_else(|| {
+            let mut paths = UnorderedSet::new(format!("wp:{}", chain_address).as_bytes());
+            if let Some(path) = self.wallet_to_path.get(chain_address) {
+                paths.insert(&path);
+            }
+            paths
+        })
+    }
+
+    /// Record that a wallet owns a path; the first path becomes its primary
+    fn link_wallet_path(&mut self, chain_address: &String, derivation_path: &DerivationPath) {
+        let mut paths = self.load_wallet_paths(chain_address);
+        paths.insert(derivation_path);
+        self.wallet_paths.insert(chain_address, &paths);
+        if self.wallet_to_path.get(chain_address).is_none() {
+            self.wallet_to_path.insert(chain_address, derivation_path);
+        }
+    }
+
+    /// Forget that a wallet owns a path, promoting another path to primary
+    fn unlink_wallet_path(&mut self, chain_address: &String, derivation_path: &DerivationPath) {
+        let mut paths = self.load_wallet_paths(chain_address);
+        paths.remove(derivation_path);
+
+        if self.wallet_to_path.get(chain_address).as_ref() == Some(derivation_path) {
+            match paths.iter().next() {
+                Some(next) => self.wallet_to_path.insert(chain_address, &next),
+                None => self.wallet_to_path.remove(chain_address),
+            };
+        }
+        if paths.is_empty() {
+            self.wallet_paths.remove(chain_address);
+        } else {
+            self.wallet_paths.insert(chain_address, &paths);
+        }
+    }
+
+    /// Check the caller is an authorized relayer whose scope, if any, covers
+    /// `method` on `derivation_path`
+    fn assert_tee_relayer(&self, method: &str, derivation_path: &str) {
+        let relayer_account = env::predecessor_account_id();
+        let attested = self
+            .attested_relayers
+            .get(&relayer_account)
+            .is_some_and(|relayer| {
+                relayer.public_key == env::signer_account_pk()
+                    && self.is_attestation_valid(&relayer)
+            });
+        if !attested {
+            assert!(
+                !self.require_attestation,
+                "Relayer attestation missing or expired"
+            );
+            assert!(
+                self.tee_relayers.contains(&relayer_account),
+                "Only authorized TEE relayers can call this method"
+            );
+        }
+
+        if let Some(scope) = self.relayer_scopes.get(&relayer_account) {
+            assert!(
+                scope.methods.iter().any(|m| m == method),
+                "Relayer scope does not allow this method"
+            );
+            assert!(
+                scope.allows_path(derivation_path),
+                "Relayer scope does not allow this derivation path"
+            );
+        }
+    }
+
+    /// Check a scoped relayer may handle this operation type and, when
+    /// signing, key type
+    fn assert_relayer_scope(&self, operation_type: &AllowedOperationType, key_type: Option<&str>) {
+        let Some(scope) = self.relayer_scopes.get(&env::predecessor_account_id()) else {
+            return;
+        };
+        assert!(
+            scope
+                .operation_types
+                .iter()
+                .any(|t| t == operation_type.name()),
+            "Relayer scope does not allow this operation type"
+        );
+        if let Some(key_type) = key_type {
+            assert!(
+                scope.key_types.iter().any(|k| k == key_type),
+                "Relayer scope does not allow this key type"
+            );
+        }
+    }
+
+    /// Attestation is unexpired and its measurement is still approved
+    fn is_attestation_valid(&self, relayer: &AttestedRelayer) -> bool {
+        env::block_timestamp() < relayer.expires_at
+            && self.approved_measurements.contains(&measurement_key(
+                &relayer.measurement,
+                &relayer.compose_hash,
+            ))
+    }
+
+    /// Consume a wallet's mutation nonce; nonces must strictly increase per wallet
+    fn consume_wallet_nonce(&mut self, chain_address: &str, nonce: u64) {
+        let next = self
+            .wallet_nonces
+            .get(&chain_address.to_string())
+            .unwrap_or(0);
+        assert!(nonce >= next, "Nonce already used");
+        let next = nonce.checked_add(1).expect("Nonce overflow");
+        self.wallet_nonces.insert(&chain_address.to_string(), &next);
+    }
+
+    /// Verify a user's signature and that the content they approved is `action`
+    /// EIP-712 wallets sign a typed-data digest of `action` directly;
+    /// Solana wallets may submit an off-chain message envelope as `message`;
+    /// NEAR wallets submit the borsh-serialized NEP-413 payload as `message`;
+    /// its recipient must be this contract and its nonce is consumed
+    fn verify_user_signature(
+        &mut self,
+        wallet_type: &WalletType,
+        public_key: &[u8],
+        chain_address: &str,
+        message: &[u8],
+        signature: &[u8],
+        action: &SignedAction,
+    ) {
+        let signed_message = match wallet_type {
+            WalletType::Near => {
+                let payload = Nep413Payload::try_from_slice(message)
+                    .unwrap_or_else(|_| env::panic_str("Malformed NEP-413 payload"));
+                assert_eq!(
+                    payload.recipient,
+                    env::current_account_id().as_str(),
+                    "NEP-413 recipient must be this contract"
+                );
+                assert!(
+                    verify_near_signature(public_key, &payload, signature),
+                    "Invalid signature"
+                );
+
+                let nonce_key = format!("{}:nep413:{}", chain_address, hex::encode(payload.nonce));
+                assert!(
+                    !self.used_nonces.contains_key(&nonce_key),
+                    "Nonce already used"
+                );
+                self.used_nonces.insert(&nonce_key, &true);
+
+                payload.message.into_bytes()
+            }
+            WalletType::Solana => {
+                assert!(
+                    verify_solana_signature(public_key, message, signature),
+                    "Invalid signature"
+                );
+                solana_message_content(message)
+                    .unwrap_or_else(|e| env::panic_str(e))
+                    .to_vec()
+            }
+            WalletType::Evm => {
+                assert!(
+                    verify_evm_signature(chain_address, message, signature),
+                    "Invalid signature"
+                );
+                message.to_vec()
+            }
+            WalletType::Eip712 => {
+                // Typed data is rebuilt from the action; `message` is unused
+                let digest = eip712::action_digest(action, env::current_account_id().as_str());
+                assert!(
+                    verify_evm_digest(chain_address, &digest, signature),
+                    "Invalid signature"
+                );
+                return;
+            }
+        };
+
+        if let Err(e) = verify_action_message(action, &signed_message) {
+            env::panic_str(e);
+        }
+    }
+
+    /// Query the oracle for `price_asset` and, unless it is USD, `quote_asset`
+    fn query_oracle_prices(&self, price_asset: &str, quote_asset: &str) -> Promise {
+        let oracle = self
+            .price_oracle
+            .clone()
+            .unwrap_or_else(|| env::panic_str("Price oracle not configured"));
+        let query = |asset: &str| {
+            let price_id = self
+                .price_feeds
+                .get(&asset.to_string())
+                .unwrap_or_else(|| env::panic_str(&format!("No price feed for {}", asset)));
+            ext_price_oracle::ext(oracle.clone())
+                .with_static_gas(GAS_FOR_PRICE_QUERY)
+                .get_price_no_older_than(price_id, MAX_PRICE_AGE_SECS)
+        };
+
+        if quote_asset.eq_ignore_ascii_case(oracle::USD) {
+            query(price_asset)
+        } else {
+            query(price_asset).and(query(quote_asset))
         }
     }
 
@@ -575,11 +2447,10 @@ impl PermissionContract {
         current_price: u128,
         timestamp: Option<u64>,
     ) -> Result<(), &'static str> {
-        // Check timestamp is recent (within 60 seconds)
+        // Check timestamp is recent (within MAX_PRICE_AGE_SECS)
         if let Some(ts) = timestamp {
             let now = env::block_timestamp();
-            if now > ts && now - ts > 60_000_000_000 {
-                // 60 seconds in nanoseconds
+            if now > ts && now - ts > MAX_PRICE_AGE_SECS * 1_000_000_000 {
                 return Err("Price timestamp too old");
             }
         }
@@ -630,18 +2501,132 @@ mod tests {
     use near_sdk::test_utils::VMContextBuilder;
     use near_sdk::testing_env;
 
+    use ed25519_dalek::{Signer, SigningKey};
+
     fn get_context(predecessor: AccountId) -> VMContextBuilder {
         let mut builder = VMContextBuilder::new();
         builder.predecessor_account_id(predecessor);
         builder
     }
 
-    #[test]
-    fn test_init() {
-        let owner: AccountId = "owner.near".parse().unwrap();
-        let mpc: AccountId = "mpc.near".parse().unwrap();
+    const TEST_PATH: &str = "solana-1,test";
+    const TEST_WALLET: &str = "test-solana-wallet";
 
-        testing_env!(get_context(owner.clone()).build());
+    /// Contract with a registered relayer and a Solana wallet owning TEST_PATH.
+    /// Leaves the relayer as predecessor.
+    fn setup_with_wallet() -> (PermissionContract, SigningKey) {
+        let (mut contract, key) = setup_with_empty_book();
+        approve_test_destinations(&mut contract, TEST_PATH);
+        (contract, key)
+    }
+
+    /// Approve the destinations test operations pay, active immediately,
+    /// without spending TEST_WALLET nonces
+    fn approve_test_destinations(contract: &mut PermissionContract, derivation_path: &str) {
+        let path = derivation_path.to_string();
+        let mut perms = contract.load_permissions(&path).unwrap();
+        let sol_account = bs58::encode([2u8; 32]).into_string();
+        for (chain, address) in [
+            ("solana", "user"),
+            ("solana", sol_account.as_str()),
+            ("solana", "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM"),
+            ("near", "user.near"),
+            ("near", sol_account.as_str()),
+        ] {
+            perms.destinations.push(ApprovedDestination {
+                chain: chain.to_string(),
+                address: address.to_string(),
+                active_at: 0,
+            });
+        }
+        contract.save_permissions(&path, perms);
+    }
+
+    fn setup_with_empty_book() -> (PermissionContract, SigningKey) {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let mpc: AccountId = "mpc.near".parse().unwrap();
+        let relayer: AccountId = "relayer.near".parse().unwrap();
+
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = PermissionContract::new(owner, mpc);
+        contract.register_tee_relayer(relayer.clone(), None);
+
+        // The relayer sponsors storage for the paths it registers
+        testing_env!(get_context(relayer.clone())
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        contract.storage_deposit(None, None);
+
+        testing_env!(get_context(relayer).build());
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let message = format!(
+            "Register wallet for derivation path: {} with nonce: {}",
+            TEST_PATH, 0
+        );
+        contract.register_wallet(
+            TEST_PATH.to_string(),
+            WalletType::Solana,
+            key.verifying_key().to_bytes().to_vec(),
+            TEST_WALLET.to_string(),
+            key.sign(message.as_bytes()).to_bytes().to_vec(),
+            message.into_bytes(),
+            0,
+        );
+
+        (contract, key)
+    }
+
+    fn swap_input(destination: &str) -> AllowedOperationInput {
+        AllowedOperationInput {
+            operation_type: AllowedOperationType::Swap {
+                source_asset: "USDC".to_string(),
+                target_asset: "SOL".to_string(),
+                max_amount: near_sdk::json_types::U128(100_000_000),
+            },
+            source_chain: "solana".to_string(),
+            destination_address: destination.to_string(),
+            destination_chain: "solana".to_string(),
+            slippage_bps: 50,
+            expires_at: None,
+            recurring: None,
+        }
+    }
+
+    fn add_message(operation: &AllowedOperationInput, nonce: u64) -> Vec<u8> {
+        near_sdk::serde_json::to_vec(&near_sdk::serde_json::json!({
+            "action": "add_operation",
+            "derivation_path": TEST_PATH,
+            "operation": operation,
+            "nonce": nonce,
+        }))
+        .unwrap()
+    }
+
+    fn remove_message(operation_id: &str, nonce: u64) -> Vec<u8> {
+        near_sdk::serde_json::to_vec(&near_sdk::serde_json::json!({
+            "action": "remove_operation",
+            "derivation_path": TEST_PATH,
+            "operation_id": operation_id,
+            "nonce": nonce,
+        }))
+        .unwrap()
+    }
+
+    fn path_message(action: &str, nonce: u64) -> Vec<u8> {
+        near_sdk::serde_json::to_vec(&near_sdk::serde_json::json!({
+            "action": action,
+            "derivation_path": TEST_PATH,
+            "nonce": nonce,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_init() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let mpc: AccountId = "mpc.near".parse().unwrap();
+
+        testing_env!(get_context(owner.clone()).build());
         let contract = PermissionContract::new(owner.clone(), mpc);
 
         assert_eq!(contract.owner, owner);
@@ -656,10 +2641,37 @@ mod tests {
         testing_env!(get_context(owner.clone()).build());
         let mut contract = PermissionContract::new(owner.clone(), mpc);
 
-        contract.register_tee_relayer(relayer.clone());
+        contract.register_tee_relayer(relayer.clone(), None);
         assert!(contract.is_tee_relayer(relayer));
     }
 
+    #[test]
+    fn test_register_tee_relayer_emits_event() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let relayer: AccountId = "relayer.near".parse().unwrap();
+
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = PermissionContract::new(owner, "mpc.near".parse().unwrap());
+        contract.register_tee_relayer(relayer.clone(), None);
+
+        let logs = near_sdk::test_utils::get_logs();
+        assert_eq!(
+            logs,
+            vec![
+                r#"EVENT_JSON:{"standard":"shadelink-permission","version":"1.0.0","event":"tee_relayer_registered","data":{"relayer_account":"relayer.near"}}"#
+            ]
+        );
+
+        let log: EventLog =
+            near_sdk::serde_json::from_str(logs[0].strip_prefix("EVENT_JSON:").unwrap()).unwrap();
+        assert_eq!(
+            log.event,
+            PermissionEvent::TeeRelayerRegistered {
+                relayer_account: relayer
+            }
+        );
+    }
+
     #[test]
     fn test_add_and_remove_tee_relayer() {
         let owner: AccountId = "owner.near".parse().unwrap();
@@ -670,7 +2682,7 @@ mod tests {
         let mut contract = PermissionContract::new(owner.clone(), mpc);
 
         // Add relayer
-        contract.register_tee_relayer(relayer.clone());
+        contract.register_tee_relayer(relayer.clone(), None);
         assert!(contract.is_tee_relayer(relayer.clone()));
 
         // Remove relayer
@@ -686,7 +2698,7 @@ mod tests {
 
         testing_env!(get_context(owner.clone()).build());
         let mut contract = PermissionContract::new(owner.clone(), mpc.clone());
-        contract.register_tee_relayer(relayer.clone());
+        contract.register_tee_relayer(relayer.clone(), None);
 
         let config = contract.get_config();
         assert_eq!(config.owner, owner);
@@ -697,7 +2709,7 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "Only owner can call this method")]
+    #[should_panic(expected = "Caller does not have role RelayerManager")]
     fn test_register_relayer_not_owner() {
         let owner: AccountId = "owner.near".parse().unwrap();
         let mpc: AccountId = "mpc.near".parse().unwrap();
@@ -709,9 +2721,74 @@ mod tests {
 
         // Switch to non-owner
         testing_env!(get_context(not_owner).build());
-        contract.register_tee_relayer(relayer);
+        contract.register_tee_relayer(relayer, None);
+    }
+
+    #[test]
+    fn test_relayer_manager_rotates_relayers() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let ops: AccountId = "ops.near".parse().unwrap();
+        let relayer: AccountId = "relayer.near".parse().unwrap();
+
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = PermissionContract::new(owner, "mpc.near".parse().unwrap());
+        contract.grant_role(ops.clone(), Role::RelayerManager);
+        assert_eq!(contract.get_roles(ops.clone()), vec![Role::RelayerManager]);
+
+        testing_env!(get_context(ops.clone()).build());
+        contract.register_tee_relayer(relayer.clone(), None);
+        assert!(contract.is_tee_relayer(relayer.clone()));
+        contract.remove_tee_relayer(relayer.clone());
+        assert!(!contract.is_tee_relayer(relayer));
+        assert!(!contract.has_role(ops, Role::ConfigManager));
+    }
+
+    #[test]
+    #[should_panic(expected = "Caller does not have role RelayerManager")]
+    fn test_revoked_role_cannot_register_relayer() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let ops: AccountId = "ops.near".parse().unwrap();
+
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = PermissionContract::new(owner, "mpc.near".parse().unwrap());
+        contract.grant_role(ops.clone(), Role::RelayerManager);
+        contract.revoke_role(ops.clone(), Role::RelayerManager);
+        assert!(contract.get_roles(ops.clone()).is_empty());
+
+        testing_env!(get_context(ops).build());
+        contract.register_tee_relayer("relayer.near".parse().unwrap(), None);
+    }
+
+    #[test]
+    fn test_two_step_ownership_transfer() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let new_owner: AccountId = "new-owner.near".parse().unwrap();
+
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = PermissionContract::new(owner.clone(), "mpc.near".parse().unwrap());
+        contract.propose_owner(new_owner.clone());
+        assert_eq!(contract.get_config().owner, owner);
+        assert_eq!(contract.get_config().pending_owner, Some(new_owner.clone()));
+
+        testing_env!(get_context(new_owner.clone()).build());
+        contract.accept_ownership();
+        assert_eq!(contract.get_config().owner, new_owner);
+        assert_eq!(contract.get_config().pending_owner, None);
+        assert!(!contract.has_role(owner, Role::Admin));
     }
 
+    #[test]
+    #[should_panic(expected = "Only the pending owner can accept ownership")]
+    fn test_accept_ownership_requires_pending_owner() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = PermissionContract::new(owner, "mpc.near".parse().unwrap());
+        contract.propose_owner("new-owner.near".parse().unwrap());
+
+        testing_env!(get_context("attacker.near".parse().unwrap()).build());
+        contract.accept_ownership();
+    }
     #[test]
     fn test_validate_price_condition_limit_above() {
         let owner: AccountId = "owner.near".parse().unwrap();
@@ -732,6 +2809,7 @@ mod tests {
                 target_asset: "SOL".to_string(),
                 max_amount: near_sdk::json_types::U128(100_000_000),
             },
+            source_chain: "solana".to_string(),
             destination_address: "user".to_string(),
             destination_chain: "solana".to_string(),
             slippage_bps: 100,
@@ -739,13 +2817,21 @@ mod tests {
             executed: false,
             nonce: 1,
             created_at: 0,
+            recurring: None,
+            execution_count: 0,
+            last_executed_at: None,
+            consumed_amount: U128(0),
         };
 
         // Price above trigger - should pass
-        assert!(contract.validate_price_condition(&operation, 160_000_000, None).is_ok());
+        assert!(contract
+            .validate_price_condition(&operation, 160_000_000, None)
+            .is_ok());
 
         // Price below trigger - should fail
-        assert!(contract.validate_price_condition(&operation, 140_000_000, None).is_err());
+        assert!(contract
+            .validate_price_condition(&operation, 140_000_000, None)
+            .is_err());
     }
 
     #[test]
@@ -767,6 +2853,7 @@ mod tests {
                 target_asset: "USDC".to_string(),
                 max_amount: near_sdk::json_types::U128(1_000_000_000),
             },
+            source_chain: "solana".to_string(),
             destination_address: "user".to_string(),
             destination_chain: "solana".to_string(),
             slippage_bps: 200,
@@ -774,13 +2861,21 @@ mod tests {
             executed: false,
             nonce: 1,
             created_at: 0,
+            recurring: None,
+            execution_count: 0,
+            last_executed_at: None,
+            consumed_amount: U128(0),
         };
 
         // Price below trigger - stop-loss should pass
-        assert!(contract.validate_price_condition(&operation, 90_000_000, None).is_ok());
+        assert!(contract
+            .validate_price_condition(&operation, 90_000_000, None)
+            .is_ok());
 
         // Price above trigger - stop-loss should fail
-        assert!(contract.validate_price_condition(&operation, 110_000_000, None).is_err());
+        assert!(contract
+            .validate_price_condition(&operation, 110_000_000, None)
+            .is_err());
     }
 
     #[test]
@@ -802,6 +2897,7 @@ mod tests {
                 target_asset: "USDC".to_string(),
                 max_amount: near_sdk::json_types::U128(1_000_000_000),
             },
+            source_chain: "solana".to_string(),
             destination_address: "user".to_string(),
             destination_chain: "solana".to_string(),
             slippage_bps: 100,
@@ -809,13 +2905,21 @@ mod tests {
             executed: false,
             nonce: 1,
             created_at: 0,
+            recurring: None,
+            execution_count: 0,
+            last_executed_at: None,
+            consumed_amount: U128(0),
         };
 
         // Price above trigger - take-profit should pass
-        assert!(contract.validate_price_condition(&operation, 210_000_000, None).is_ok());
+        assert!(contract
+            .validate_price_condition(&operation, 210_000_000, None)
+            .is_ok());
 
         // Price below trigger - take-profit should fail
-        assert!(contract.validate_price_condition(&operation, 190_000_000, None).is_err());
+        assert!(contract
+            .validate_price_condition(&operation, 190_000_000, None)
+            .is_err());
     }
 
     #[test]
@@ -834,6 +2938,7 @@ mod tests {
                 target_asset: "SOL".to_string(),
                 max_amount: near_sdk::json_types::U128(100_000_000),
             },
+            source_chain: "solana".to_string(),
             destination_address: "user".to_string(),
             destination_chain: "solana".to_string(),
             slippage_bps: 50,
@@ -841,10 +2946,2775 @@ mod tests {
             executed: false,
             nonce: 1,
             created_at: 0,
+            recurring: None,
+            execution_count: 0,
+            last_executed_at: None,
+            consumed_amount: U128(0),
         };
 
         // Swap has no price condition - should always pass
-        assert!(contract.validate_price_condition(&operation, 0, None).is_ok());
-        assert!(contract.validate_price_condition(&operation, 1_000_000_000, None).is_ok());
+        assert!(contract
+            .validate_price_condition(&operation, 0, None)
+            .is_ok());
+        assert!(contract
+            .validate_price_condition(&operation, 1_000_000_000, None)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_add_and_remove_operation_with_bound_message() {
+        let (mut contract, key) = setup_with_wallet();
+
+        let operation = swap_input("user");
+        let message = add_message(&operation, 1);
+        let operation_id = contract.add_allowed_operation(
+            TEST_PATH.to_string(),
+            operation,
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            1,
+        );
+        assert!(contract.is_operation_allowed(TEST_PATH.to_string(), operation_id.clone()));
+
+        let message = remove_message(&operation_id, 2);
+        contract.remove_allowed_operation(
+            TEST_PATH.to_string(),
+            operation_id.clone(),
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            2,
+        );
+        assert!(contract
+            .get_operation(TEST_PATH.to_string(), operation_id)
+            .is_none());
+    }
+
+    #[test]
+    fn test_migrate_keeps_permissions() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let relayer: AccountId = "relayer.near".parse().unwrap();
+        testing_env!(get_context(owner.clone()).build());
+
+        // Records as the deployed release wrote them: untagged permissions
+        // and operations
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let operation_id = format!("{}-1", TEST_PATH);
+        let mut operations = UnorderedMap::new(format!("ops:{}", TEST_PATH).as_bytes());
+        operations.insert(
+            &operation_id,
+            &LegacyAllowedOperation {
+                operation_id: operation_id.clone(),
+                derivation_path: TEST_PATH.to_string(),
+                operation_type: swap_input("user").operation_type,
+                destination_address: "user".to_string(),
+                destination_chain: "solana".to_string(),
+                slippage_bps: 50,
+                expires_at: None,
+                executed: false,
+                nonce: 1,
+                created_at: 0,
+            },
+        );
+        let mut permissions = LookupMap::new(b"p");
+        permissions.insert(
+            &TEST_PATH.to_string(),
+            &LegacyUserPermissions {
+                owner_wallets: vec![RegisteredWallet {
+                    wallet_type: WalletType::Solana,
+                    public_key: key.verifying_key().to_bytes().to_vec(),
+                    chain_address: TEST_WALLET.to_string(),
+                }],
+                allowed_operations: operations,
+                next_nonce: 2,
+            },
+        );
+        let mut wallet_to_path = LookupMap::new(b"w");
+        wallet_to_path.insert(&TEST_WALLET.to_string(), &TEST_PATH.to_string());
+        let mut tee_relayers = UnorderedSet::new(b"t");
+        tee_relayers.insert(&relayer);
+        let mut active_operations = UnorderedSet::new(b"a");
+        active_operations.insert(&format!("{}:{}", TEST_PATH, operation_id));
+        env::state_write(&migrate::OldState {
+            owner,
+            permissions,
+            wallet_to_path,
+            tee_relayers,
+            mpc_contract: "mpc.near".parse().unwrap(),
+            active_operations,
+            used_nonces: LookupMap::new(b"n"),
+        });
+
+        let mut migrated = PermissionContract::migrate();
+        assert_eq!(migrate::stored_state_version(), migrate::STATE_VERSION);
+        assert!(migrated.is_operation_allowed(TEST_PATH.to_string(), operation_id.clone()));
+        assert_eq!(migrated.get_active_operations(0, 10).len(), 1);
+        assert_eq!(
+            migrated.get_path_for_wallet(TEST_WALLET.to_string()),
+            Some(TEST_PATH.to_string())
+        );
+        assert!(migrated.is_tee_relayer(relayer.clone()));
+        assert_eq!(migrated.get_config().pending_owner, None);
+        // The first release had no address book; its destinations stay payable
+        let book = migrated.get_destinations(TEST_PATH.to_string());
+        assert_eq!(book.len(), 1);
+        assert!(book[0].matches("solana", "user"));
+        assert_eq!(book[0].active_at, 0);
+
+        // The first change moves the record and its operations to the current
+        // layout; the relayer making it becomes the path's storage sponsor
+        testing_env!(get_context(relayer.clone())
+            .attached_deposit(NearToken::from_near(1))
+            .build());
+        migrated.storage_deposit(None, None);
+        testing_env!(get_context(relayer).build());
+        let operation = swap_input("user");
+        let message = add_message(&operation, 1);
+        let new_operation_id = migrated.add_allowed_operation(
+            TEST_PATH.to_string(),
+            operation,
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            1,
+        );
+        assert_eq!(new_operation_id, format!("{}-2", TEST_PATH));
+        assert!(migrated
+            .legacy_permissions
+            .get(&TEST_PATH.to_string())
+            .is_none());
+        let perms = migrated.load_permissions(&TEST_PATH.to_string()).unwrap();
+        assert!(perms.legacy_operations.is_none());
+        assert_eq!(perms.operations().len(), 2);
+        assert!(migrated.is_operation_allowed(TEST_PATH.to_string(), operation_id));
+    }
+
+    #[test]
+    fn test_migrate_keeps_current_state() {
+        let (mut contract, key) = setup_with_wallet();
+        let (operation_id, _) = add_partial_swap(&mut contract, &key);
+        env::state_write(&contract);
+
+        let migrated = PermissionContract::migrate();
+        assert_eq!(migrate::stored_state_version(), migrate::STATE_VERSION);
+        assert!(migrated.is_operation_allowed(TEST_PATH.to_string(), operation_id));
+        assert_eq!(migrated.get_next_nonce(TEST_WALLET.to_string()), 2);
+        assert_eq!(
+            migrated
+                .get_permissions(TEST_PATH.to_string())
+                .unwrap()
+                .owner_wallets
+                .len(),
+            1
+        );
+        assert!(migrated.is_tee_relayer("relayer.near".parse().unwrap()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Unsupported state version")]
+    fn test_migrate_rejects_unknown_state_version() {
+        let (contract, _) = setup_with_wallet();
+        env::state_write(&contract);
+        env::storage_write(b"STATE_VERSION", &borsh::to_vec(&99u32).unwrap());
+        PermissionContract::migrate();
+    }
+
+    #[test]
+    fn test_wallet_owns_multiple_paths() {
+        let (mut contract, key) = setup_with_wallet();
+        let other_path = "near-1,test".to_string();
+        let message = format!(
+            "Register wallet for derivation path: {} with nonce: {}",
+            other_path, 1
+        );
+        contract.register_wallet(
+            other_path.clone(),
+            WalletType::Solana,
+            key.verifying_key().to_bytes().to_vec(),
+            TEST_WALLET.to_string(),
+            key.sign(message.as_bytes()).to_bytes().to_vec(),
+            message.into_bytes(),
+            1,
+        );
+
+        let mut paths = contract.get_paths_for_wallet(TEST_WALLET.to_string(), 0, 10);
+        paths.sort();
+        assert_eq!(paths, vec![other_path.clone(), TEST_PATH.to_string()]);
+        assert_eq!(
+            contract
+                .get_paths_for_wallet(TEST_WALLET.to_string(), 1, 10)
+                .len(),
+            1
+        );
+        // The first path stays primary instead of being overwritten
+        assert_eq!(
+            contract.get_path_for_wallet(TEST_WALLET.to_string()),
+            Some(TEST_PATH.to_string())
+        );
+
+        // Closing the primary path promotes the remaining one
+        let message = remove_wallet_message(TEST_WALLET, true, 1);
+        contract.remove_wallet(
+            TEST_PATH.to_string(),
+            TEST_WALLET.to_string(),
+            true,
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            1,
+        );
+        assert_eq!(
+            contract.get_path_for_wallet(TEST_WALLET.to_string()),
+            Some(other_path.clone())
+        );
+        assert_eq!(
+            contract.get_paths_for_wallet(TEST_WALLET.to_string(), 0, 10),
+            vec![other_path]
+        );
+    }
+
+    #[test]
+    fn test_legacy_wallet_mapping_listed_as_path() {
+        let (mut contract, _) = setup_with_wallet();
+        // Wallets linked before path sets only have a wallet_to_path entry
+        contract.wallet_paths.remove(&TEST_WALLET.to_string());
+
+        assert_eq!(
+            contract.get_paths_for_wallet(TEST_WALLET.to_string(), 0, 10),
+            vec![TEST_PATH.to_string()]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Only owner can call this method")]
+    fn test_update_contract_owner_only() {
+        let (contract, _) = setup_with_wallet();
+        let mut context = get_context("relayer.near".parse().unwrap()).build();
+        context.input = vec![0, 97, 115, 109];
+        testing_env!(context);
+
+        contract.update_contract();
+    }
+
+    #[test]
+    fn test_operation_storage_charged_and_refunded() {
+        let (mut contract, key) = setup_with_wallet();
+        let relayer: AccountId = "relayer.near".parse().unwrap();
+        assert_eq!(
+            contract.get_storage_sponsor(TEST_PATH.to_string()),
+            Some(relayer.clone())
+        );
+        let registered = contract.storage_balance_of(relayer.clone()).unwrap();
+        assert_eq!(registered.total.0, NearToken::from_near(1).as_yoctonear());
+
+        let operation = swap_input("user");
+        let message = add_message(&operation, 1);
+        let operation_id = contract.add_allowed_operation(
+            TEST_PATH.to_string(),
+            operation,
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            1,
+        );
+        let added = contract.storage_balance_of(relayer.clone()).unwrap();
+        assert!(added.available.0 < registered.available.0);
+
+        let message = remove_message(&operation_id, 2);
+        contract.remove_allowed_operation(
+            TEST_PATH.to_string(),
+            operation_id,
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            2,
+        );
+        let removed = contract.storage_balance_of(relayer).unwrap();
+        assert!(removed.available.0 > added.available.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Storage sponsor has no storage deposit")]
+    fn test_register_wallet_requires_storage_deposit() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let relayer: AccountId = "relayer.near".parse().unwrap();
+
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = PermissionContract::new(owner, "mpc.near".parse().unwrap());
+        contract.register_tee_relayer(relayer.clone(), None);
+
+        testing_env!(get_context(relayer).build());
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let message = message::register_wallet_message(TEST_PATH, 0);
+        contract.register_wallet(
+            TEST_PATH.to_string(),
+            WalletType::Solana,
+            key.verifying_key().to_bytes().to_vec(),
+            TEST_WALLET.to_string(),
+            key.sign(message.as_bytes()).to_bytes().to_vec(),
+            message.into_bytes(),
+            0,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Withdraw amount exceeds available balance")]
+    fn test_storage_withdraw_keeps_used_storage() {
+        let (mut contract, _) = setup_with_wallet();
+        testing_env!(get_context("relayer.near".parse().unwrap())
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+
+        contract.storage_withdraw(Some(near_sdk::json_types::U128(
+            NearToken::from_near(1).as_yoctonear(),
+        )));
+    }
+
+    #[test]
+    #[should_panic(expected = "Message operation mismatch")]
+    fn test_add_operation_rejects_reused_signed_message() {
+        let (mut contract, key) = setup_with_wallet();
+
+        // User signed a swap to their own address...
+        let message = add_message(&swap_input("user"), 1);
+        let signature = key.sign(&message).to_bytes().to_vec();
+
+        // ...relayer tries to install one paying out elsewhere
+        contract.add_allowed_operation(
+            TEST_PATH.to_string(),
+            swap_input("attacker"),
+            signature,
+            message,
+            TEST_WALLET.to_string(),
+            1,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Message operation ID mismatch")]
+    fn test_remove_operation_rejects_other_operation_id() {
+        let (mut contract, key) = setup_with_wallet();
+
+        let message = remove_message("solana-1,test-1", 1);
+        contract.remove_allowed_operation(
+            TEST_PATH.to_string(),
+            "solana-1,test-2".to_string(),
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            1,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Nonce already used")]
+    fn test_add_operation_rejects_replayed_message() {
+        let (mut contract, key) = setup_with_wallet();
+
+        let operation = swap_input("user");
+        let message = add_message(&operation, 1);
+        let signature = key.sign(&message).to_bytes().to_vec();
+        let operation_id = contract.add_allowed_operation(
+            TEST_PATH.to_string(),
+            operation.clone(),
+            signature.clone(),
+            message.clone(),
+            TEST_WALLET.to_string(),
+            1,
+        );
+
+        let remove = remove_message(&operation_id, 2);
+        contract.remove_allowed_operation(
+            TEST_PATH.to_string(),
+            operation_id,
+            key.sign(&remove).to_bytes().to_vec(),
+            remove,
+            TEST_WALLET.to_string(),
+            2,
+        );
+
+        // Replaying the original add must not resurrect the operation
+        contract.add_allowed_operation(
+            TEST_PATH.to_string(),
+            operation,
+            signature,
+            message,
+            TEST_WALLET.to_string(),
+            1,
+        );
+    }
+
+    #[test]
+    fn test_get_next_nonce() {
+        let (mut contract, key) = setup_with_wallet();
+        assert_eq!(contract.get_next_nonce(TEST_WALLET.to_string()), 0);
+
+        // Gaps are allowed, the counter jumps past the used nonce
+        let operation = swap_input("user");
+        let message = add_message(&operation, 5);
+        contract.add_allowed_operation(
+            TEST_PATH.to_string(),
+            operation,
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            5,
+        );
+
+        assert_eq!(contract.get_next_nonce(TEST_WALLET.to_string()), 6);
+        assert_eq!(contract.get_next_nonce("unknown-wallet".to_string()), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Transfer recipient does not match destination")]
+    fn test_sign_allowed_rejects_solana_transfer_to_other_recipient() {
+        let (mut contract, key) = setup_with_wallet();
+
+        let operation = AllowedOperationInput {
+            operation_type: AllowedOperationType::Swap {
+                source_asset: "SOL".to_string(),
+                target_asset: "USDC".to_string(),
+                max_amount: near_sdk::json_types::U128(1_000),
+            },
+            source_chain: "solana".to_string(),
+            destination_address: "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM".to_string(),
+            destination_chain: "solana".to_string(),
+            slippage_bps: 50,
+            expires_at: None,
+            recurring: None,
+        };
+        let message = add_message(&operation, 1);
+        let operation_id = contract.add_allowed_operation(
+            TEST_PATH.to_string(),
+            operation,
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            1,
+        );
+
+        // Legacy message: system transfer of 1 lamport from [1; 32] to [2; 32]
+        let mut payload = vec![1, 0, 1, 3];
+        payload.extend_from_slice(&[1u8; 32]);
+        payload.extend_from_slice(&[2u8; 32]);
+        payload.extend_from_slice(&[0u8; 32]);
+        payload.extend_from_slice(&[9u8; 32]);
+        payload.extend_from_slice(&[1, 2, 2, 0, 1, 12, 2, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]);
+
+        contract.sign_allowed(
+            TEST_PATH.to_string(),
+            operation_id,
+            payload,
+            "Eddsa".to_string(),
+            U128(1_000),
+        );
+    }
+
+    /// Allow a SOL swap to [2; 32] every 100ns, twice, at 1 lamport each,
+    /// and return its ID with a matching 1 lamport transfer payload
+    fn add_recurring_swap(
+        contract: &mut PermissionContract,
+        key: &SigningKey,
+    ) -> (String, Vec<u8>) {
+        let operation = AllowedOperationInput {
+            operation_type: AllowedOperationType::Swap {
+                source_asset: "SOL".to_string(),
+                target_asset: "USDC".to_string(),
+                max_amount: near_sdk::json_types::U128(1_000),
+            },
+            source_chain: "solana".to_string(),
+            destination_address: bs58::encode([2u8; 32]).into_string(),
+            destination_chain: "solana".to_string(),
+            slippage_bps: 50,
+            expires_at: None,
+            recurring: Some(RecurringSchedule {
+                interval: 100,
+                max_executions: 2,
+                amount_per_execution: near_sdk::json_types::U128(1),
+                start_at: 0,
+                end_at: None,
+            }),
+        };
+        let message = add_message(&operation, 1);
+        let operation_id = contract.add_allowed_operation(
+            TEST_PATH.to_string(),
+            operation,
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            1,
+        );
+
+        (operation_id, sol_transfer_payload())
+    }
+
+    fn sign_at(contract: &mut PermissionContract, operation_id: &str, payload: &[u8], now: u64) {
+        testing_env!(get_context("relayer.near".parse().unwrap())
+            .block_timestamp(now)
+            .build());
+        contract.sign_allowed(
+            TEST_PATH.to_string(),
+            operation_id.to_string(),
+            payload.to_vec(),
+            "Eddsa".to_string(),
+            U128(1),
+        );
+    }
+
+    #[test]
+    fn test_recurring_operation_runs_each_interval_until_exhausted() {
+        let (mut contract, key) = setup_with_wallet();
+        let (operation_id, payload) = add_recurring_swap(&mut contract, &key);
+
+        sign_at(&mut contract, &operation_id, &payload, 10);
+        let operation = contract
+            .get_operation(TEST_PATH.to_string(), operation_id.clone())
+            .unwrap();
+        assert_eq!(operation.execution_count, 1);
+        assert_eq!(operation.last_executed_at, Some(10));
+        assert!(!operation.executed);
+        assert_eq!(contract.get_active_operations(0, 10).len(), 1);
+
+        sign_at(&mut contract, &operation_id, &payload, 110);
+        let operation = contract
+            .get_operation(TEST_PATH.to_string(), operation_id.clone())
+            .unwrap();
+        assert_eq!(operation.execution_count, 2);
+        assert!(operation.executed);
+        assert!(contract.get_active_operations(0, 10).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Recurring interval has not elapsed")]
+    fn test_recurring_operation_signs_once_per_interval() {
+        let (mut contract, key) = setup_with_wallet();
+        let (operation_id, payload) = add_recurring_swap(&mut contract, &key);
+
+        sign_at(&mut contract, &operation_id, &payload, 10);
+        sign_at(&mut contract, &operation_id, &payload, 109);
+    }
+
+    #[test]
+    #[should_panic(expected = "Transfer amount exceeds max_amount")]
+    fn test_recurring_operation_limits_amount_per_execution() {
+        let (mut contract, key) = setup_with_wallet();
+        let (operation_id, mut payload) = add_recurring_swap(&mut contract, &key);
+        // Transfer 2 lamports instead of 1
+        let lamports = payload.len() - 8;
+        payload[lamports] = 2;
+
+        sign_at(&mut contract, &operation_id, &payload, 10);
+    }
+
+    /// Allow a one-shot SOL swap to [2; 32] with a 3 lamport budget and
+    /// return its ID with a matching 1 lamport transfer payload
+    fn add_partial_swap(contract: &mut PermissionContract, key: &SigningKey) -> (String, Vec<u8>) {
+        add_partial_swap_with_nonce(contract, key, 1)
+    }
+
+    fn add_partial_swap_with_nonce(
+        contract: &mut PermissionContract,
+        key: &SigningKey,
+        nonce: u64,
+    ) -> (String, Vec<u8>) {
+        let operation = AllowedOperationInput {
+            operation_type: AllowedOperationType::Swap {
+                source_asset: "SOL".to_string(),
+                target_asset: "USDC".to_string(),
+                max_amount: near_sdk::json_types::U128(3),
+            },
+            source_chain: "solana".to_string(),
+            destination_address: bs58::encode([2u8; 32]).into_string(),
+            destination_chain: "solana".to_string(),
+            slippage_bps: 50,
+            expires_at: None,
+            recurring: None,
+        };
+        let message = add_message(&operation, nonce);
+        let operation_id = contract.add_allowed_operation(
+            TEST_PATH.to_string(),
+            operation,
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            nonce,
+        );
+
+        (operation_id, sol_transfer_payload())
+    }
+
+    /// Solana message transferring 1 lamport to [2; 32]
+    fn sol_transfer_payload() -> Vec<u8> {
+        let mut payload = vec![1, 0, 1, 3];
+        payload.extend_from_slice(&[1u8; 32]);
+        payload.extend_from_slice(&[2u8; 32]);
+        payload.extend_from_slice(&[0u8; 32]);
+        payload.extend_from_slice(&[9u8; 32]);
+        payload.extend_from_slice(&[1, 2, 2, 0, 1, 12, 2, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]);
+        payload
+    }
+
+    fn sign_amount(
+        contract: &mut PermissionContract,
+        operation_id: &str,
+        payload: &[u8],
+        amount: u128,
+    ) {
+        testing_env!(get_context("relayer.near".parse().unwrap()).build());
+        contract.sign_allowed(
+            TEST_PATH.to_string(),
+            operation_id.to_string(),
+            payload.to_vec(),
+            "Eddsa".to_string(),
+            U128(amount),
+        );
+    }
+
+    #[test]
+    fn test_cross_chain_operation_signs_for_source_chain() {
+        let (mut contract, key) = setup_with_wallet();
+        // SOL leaves the Solana account for a NEAR destination
+        let operation = AllowedOperationInput {
+            operation_type: AllowedOperationType::Swap {
+                source_asset: "SOL".to_string(),
+                target_asset: "wrap.near".to_string(),
+                max_amount: near_sdk::json_types::U128(3),
+            },
+            source_chain: "solana".to_string(),
+            destination_address: bs58::encode([2u8; 32]).into_string(),
+            destination_chain: "near".to_string(),
+            slippage_bps: 50,
+            expires_at: None,
+            recurring: None,
+        };
+        let message = add_message(&operation, 1);
+        let operation_id = contract.add_allowed_operation(
+            TEST_PATH.to_string(),
+            operation,
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            1,
+        );
+
+        // A Solana message, which the NEAR decoder would reject
+        sign_amount(&mut contract, &operation_id, &sol_transfer_payload(), 1);
+
+        let operation = contract
+            .get_operation(TEST_PATH.to_string(), operation_id)
+            .unwrap();
+        assert_eq!(operation.consumed_amount, U128(1));
+    }
+
+    /// Unsigned NEAR transaction from `signer_id` transferring `deposit` to user.near
+    fn near_transfer_payload(signer_id: &str, deposit: u128) -> Vec<u8> {
+        let mut payload = borsh::to_vec(signer_id).unwrap();
+        payload.push(0);
+        payload.extend_from_slice(&[4u8; 32]);
+        payload.extend(borsh::to_vec(&7u64).unwrap());
+        payload.extend(borsh::to_vec("user.near").unwrap());
+        payload.extend_from_slice(&[5u8; 32]);
+        payload.extend(borsh::to_vec(&1u32).unwrap());
+        payload.push(3);
+        payload.extend(borsh::to_vec(&deposit).unwrap());
+        payload
+    }
+
+    fn add_near_transfer(contract: &mut PermissionContract, key: &SigningKey) -> String {
+        let operation = AllowedOperationInput {
+            operation_type: AllowedOperationType::Swap {
+                source_asset: "NEAR".to_string(),
+                target_asset: "wrap.near".to_string(),
+                max_amount: U128(10),
+            },
+            source_chain: "near".to_string(),
+            destination_address: "user.near".to_string(),
+            destination_chain: "near".to_string(),
+            slippage_bps: 50,
+            expires_at: None,
+            recurring: None,
+        };
+        let message = add_message(&operation, 1);
+        contract.add_allowed_operation(
+            TEST_PATH.to_string(),
+            operation,
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            1,
+        )
+    }
+
+    #[test]
+    fn test_near_transaction_signed_for_path_account() {
+        let (mut contract, key) = setup_with_wallet();
+        let operation_id = add_near_transfer(&mut contract, &key);
+
+        testing_env!(get_context("owner.near".parse().unwrap()).build());
+        let root = curve25519_dalek::constants::ED25519_BASEPOINT_COMPRESSED.to_bytes();
+        contract.set_mpc_ed25519_key(format!("ed25519:{}", bs58::encode(root).into_string()));
+
+        let path_account =
+            mpc::derived_near_account(&root, env::current_account_id().as_str(), TEST_PATH)
+                .unwrap();
+        sign_amount(
+            &mut contract,
+            &operation_id,
+            &near_transfer_payload(&path_account, 4),
+            4,
+        );
+        let operation = contract
+            .get_operation(TEST_PATH.to_string(), operation_id)
+            .unwrap();
+        assert_eq!(operation.consumed_amount, U128(4));
+    }
+
+    #[test]
+    #[should_panic(expected = "Transaction signer is not the path's account")]
+    fn test_near_transaction_from_other_account_rejected() {
+        let (mut contract, key) = setup_with_wallet();
+        let operation_id = add_near_transfer(&mut contract, &key);
+
+        testing_env!(get_context("owner.near".parse().unwrap()).build());
+        let root = curve25519_dalek::constants::ED25519_BASEPOINT_COMPRESSED.to_bytes();
+        contract.set_mpc_ed25519_key(format!("ed25519:{}", bs58::encode(root).into_string()));
+
+        // Would spend from another path's account under this path's operation
+        let other_account =
+            mpc::derived_near_account(&root, env::current_account_id().as_str(), "near-1,other")
+                .unwrap();
+        sign_amount(
+            &mut contract,
+            &operation_id,
+            &near_transfer_payload(&other_account, 4),
+            4,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "MPC Ed25519 key not configured")]
+    fn test_near_transaction_requires_mpc_key() {
+        let (mut contract, key) = setup_with_wallet();
+        let operation_id = add_near_transfer(&mut contract, &key);
+        sign_amount(
+            &mut contract,
+            &operation_id,
+            &near_transfer_payload("signer.near", 4),
+            4,
+        );
+    }
+
+    #[test]
+    fn test_partial_fills_run_until_budget_exhausted() {
+        let (mut contract, key) = setup_with_wallet();
+        let (operation_id, payload) = add_partial_swap(&mut contract, &key);
+
+        sign_amount(&mut contract, &operation_id, &payload, 1);
+        let operation = contract
+            .get_operation(TEST_PATH.to_string(), operation_id.clone())
+            .unwrap();
+        assert_eq!(operation.consumed_amount, U128(1));
+        assert!(!operation.executed);
+        assert!(contract.is_operation_allowed(TEST_PATH.to_string(), operation_id.clone()));
+
+        sign_amount(&mut contract, &operation_id, &payload, 2);
+        let operation = contract
+            .get_operation(TEST_PATH.to_string(), operation_id.clone())
+            .unwrap();
+        assert_eq!(operation.consumed_amount, U128(3));
+        assert_eq!(operation.execution_count, 2);
+        assert!(operation.executed);
+        assert!(contract.get_active_operations(0, 10).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Amount exceeds remaining budget")]
+    fn test_partial_fill_beyond_remaining_budget_rejected() {
+        let (mut contract, key) = setup_with_wallet();
+        let (operation_id, payload) = add_partial_swap(&mut contract, &key);
+
+        sign_amount(&mut contract, &operation_id, &payload, 1);
+        sign_amount(&mut contract, &operation_id, &payload, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Transfer amount exceeds max_amount")]
+    fn test_payload_limited_to_declared_amount() {
+        let (mut contract, key) = setup_with_wallet();
+        let (operation_id, mut payload) = add_partial_swap(&mut contract, &key);
+        // Transfer 2 lamports while declaring 1
+        let lamports = payload.len() - 8;
+        payload[lamports] = 2;
+
+        sign_amount(&mut contract, &operation_id, &payload, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Amount must be positive")]
+    fn test_zero_amount_rejected() {
+        let (mut contract, key) = setup_with_wallet();
+        let (operation_id, payload) = add_partial_swap(&mut contract, &key);
+
+        sign_amount(&mut contract, &operation_id, &payload, 0);
+    }
+
+    /// Allow a SOL stop-loss at 100 USD paying out to [2; 32] and return
+    /// its ID with a matching 1 lamport transfer payload
+    fn add_stop_loss(contract: &mut PermissionContract, key: &SigningKey) -> (String, Vec<u8>) {
+        let operation = AllowedOperationInput {
+            operation_type: AllowedOperationType::StopLoss {
+                price_asset: "SOL".to_string(),
+                quote_asset: "USD".to_string(),
+                trigger_price: near_sdk::json_types::U128(100 * 100_000_000),
+                source_asset: "SOL".to_string(),
+                target_asset: "USDC".to_string(),
+                max_amount: near_sdk::json_types::U128(1_000),
+            },
+            source_chain: "solana".to_string(),
+            destination_address: bs58::encode([2u8; 32]).into_string(),
+            destination_chain: "solana".to_string(),
+            slippage_bps: 50,
+            expires_at: None,
+            recurring: None,
+        };
+        let message = add_message(&operation, 1);
+        let operation_id = contract.add_allowed_operation(
+            TEST_PATH.to_string(),
+            operation,
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            1,
+        );
+
+        (operation_id, sol_transfer_payload())
+    }
+
+    /// Run on_oracle_price for `operation_id` with a SOL/USD oracle reply
+    fn deliver_oracle_price(
+        contract: &mut PermissionContract,
+        operation_id: &str,
+        price: Option<OraclePrice>,
+    ) {
+        let data = near_sdk::serde_json::to_vec(&price).unwrap();
+        testing_env!(
+            get_context(env::current_account_id()).build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![near_sdk::PromiseResult::Successful(data)],
+        );
+
+        let sign_request = SignRequest {
+            payload_v2: PayloadV2 {
+                eddsa: Some(String::new()),
+                ecdsa: None,
+            },
+            path: TEST_PATH.to_string(),
+            domain_id: 1,
+        };
+        contract.on_oracle_price(
+            TEST_PATH.to_string(),
+            operation_id.to_string(),
+            sign_request,
+            U128(1_000),
+        );
+    }
+
+    fn sol_usd(price: i64) -> OraclePrice {
+        OraclePrice {
+            price: near_sdk::json_types::I64(price),
+            conf: near_sdk::json_types::U64(0),
+            expo: -8,
+            publish_time: (env::block_timestamp() / 1_000_000_000) as i64,
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Price oracle not configured")]
+    fn test_sign_allowed_conditional_requires_oracle() {
+        let (mut contract, key) = setup_with_wallet();
+        let (operation_id, payload) = add_stop_loss(&mut contract, &key);
+
+        contract.sign_allowed(
+            TEST_PATH.to_string(),
+            operation_id,
+            payload,
+            "Eddsa".to_string(),
+            U128(1_000),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is paused")]
+    fn test_pause_blocks_sign_allowed() {
+        let (mut contract, key) = setup_with_wallet();
+        let (operation_id, payload) = add_stop_loss(&mut contract, &key);
+
+        testing_env!(get_context("owner.near".parse().unwrap()).build());
+        contract.pause();
+        assert!(contract.get_config().paused);
+        assert!(!contract.is_operation_allowed(TEST_PATH.to_string(), operation_id.clone()));
+
+        testing_env!(get_context("relayer.near".parse().unwrap()).build());
+        contract.sign_allowed(
+            TEST_PATH.to_string(),
+            operation_id,
+            payload,
+            "Eddsa".to_string(),
+            U128(1_000),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Derivation path is frozen")]
+    fn test_frozen_path_blocks_sign_allowed() {
+        let (mut contract, key) = setup_with_wallet();
+        let (operation_id, payload) = add_stop_loss(&mut contract, &key);
+
+        // Submitted by the user directly, not the relayer
+        testing_env!(get_context("user.near".parse().unwrap()).build());
+        let message = path_message("freeze_path", 2);
+        contract.freeze_path(
+            TEST_PATH.to_string(),
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            2,
+        );
+        assert!(
+            contract
+                .get_permissions(TEST_PATH.to_string())
+                .unwrap()
+                .frozen
+        );
+        assert!(!contract.is_operation_allowed(TEST_PATH.to_string(), operation_id.clone()));
+
+        testing_env!(get_context("relayer.near".parse().unwrap()).build());
+        contract.sign_allowed(
+            TEST_PATH.to_string(),
+            operation_id,
+            payload,
+            "Eddsa".to_string(),
+            U128(1_000),
+        );
+    }
+
+    #[test]
+    fn test_unfreeze_path() {
+        let (mut contract, key) = setup_with_wallet();
+
+        let message = path_message("freeze_path", 1);
+        contract.freeze_path(
+            TEST_PATH.to_string(),
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            1,
+        );
+        let message = path_message("unfreeze_path", 2);
+        contract.unfreeze_path(
+            TEST_PATH.to_string(),
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            2,
+        );
+        assert!(
+            !contract
+                .get_permissions(TEST_PATH.to_string())
+                .unwrap()
+                .frozen
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Message action mismatch")]
+    fn test_freeze_message_cannot_unfreeze() {
+        let (mut contract, key) = setup_with_wallet();
+        let message = path_message("freeze_path", 1);
+        contract.unfreeze_path(
+            TEST_PATH.to_string(),
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            1,
+        );
+    }
+
+    const SECOND_WALLET: &str = "second-solana-wallet";
+
+    fn link_message(new_wallet: &RegisteredWallet, nonce: u64) -> Vec<u8> {
+        near_sdk::serde_json::to_vec(&near_sdk::serde_json::json!({
+            "action": "link_wallet",
+            "derivation_path": TEST_PATH,
+            "new_wallet": {
+                "wallet_type": new_wallet.wallet_type,
+                "public_key": hex::encode(&new_wallet.public_key),
+                "chain_address": new_wallet.chain_address,
+            },
+            "signer_address": TEST_WALLET,
+            "nonce": nonce,
+        }))
+        .unwrap()
+    }
+
+    /// Link SECOND_WALLET to TEST_PATH with the first owner's consent (nonce 1)
+    fn register_second_wallet(
+        contract: &mut PermissionContract,
+        owner_key: &SigningKey,
+    ) -> SigningKey {
+        let key = SigningKey::from_bytes(&[9u8; 32]);
+        let new_wallet = RegisteredWallet {
+            wallet_type: WalletType::Solana,
+            public_key: key.verifying_key().to_bytes().to_vec(),
+            chain_address: SECOND_WALLET.to_string(),
+        };
+        let message = link_message(&new_wallet, 1);
+        contract.link_wallet(
+            TEST_PATH.to_string(),
+            new_wallet,
+            key.sign(&message).to_bytes().to_vec(),
+            message.clone(),
+            owner_key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            1,
+        );
+        key
+    }
+
+    #[test]
+    fn test_freeze_with_drained_sponsor() {
+        let (mut contract, key) = setup_with_wallet();
+        let second_key = register_second_wallet(&mut contract, &key);
+
+        let relayer: AccountId = "relayer.near".parse().unwrap();
+        testing_env!(get_context(relayer.clone())
+            .attached_deposit(NearToken::from_yoctonear(1))
+            .build());
+        contract.storage_withdraw(None);
+        assert_eq!(contract.storage_balance_of(relayer).unwrap().available.0, 0);
+
+        // The second wallet's first nonce adds storage the sponsor cannot cover
+        testing_env!(get_context("user.near".parse().unwrap()).build());
+        let message = path_message("freeze_path", 0);
+        contract.freeze_path(
+            TEST_PATH.to_string(),
+            second_key.sign(&message).to_bytes().to_vec(),
+            message,
+            SECOND_WALLET.to_string(),
+            0,
+        );
+        assert!(
+            contract
+                .get_permissions(TEST_PATH.to_string())
+                .unwrap()
+                .frozen
+        );
+    }
+
+    #[test]
+    fn test_link_wallet_with_owner_consent() {
+        let (mut contract, key) = setup_with_wallet();
+        let second_key = register_second_wallet(&mut contract, &key);
+
+        let perms = contract.get_permissions(TEST_PATH.to_string()).unwrap();
+        assert_eq!(perms.owner_wallets.len(), 2);
+        assert_eq!(
+            contract.get_path_for_wallet(SECOND_WALLET.to_string()),
+            Some(TEST_PATH.to_string())
+        );
+
+        // The linked wallet can now authorize changes
+        let operation = swap_input("user");
+        let message = add_message(&operation, 0);
+        contract.add_allowed_operation(
+            TEST_PATH.to_string(),
+            operation,
+            second_key.sign(&message).to_bytes().to_vec(),
+            message,
+            SECOND_WALLET.to_string(),
+            0,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Derivation path already registered")]
+    fn test_register_wallet_cannot_join_existing_path() {
+        let (mut contract, _) = setup_with_wallet();
+        let key = SigningKey::from_bytes(&[9u8; 32]);
+        let message = format!(
+            "Register wallet for derivation path: {} with nonce: {}",
+            TEST_PATH, 0
+        );
+        contract.register_wallet(
+            TEST_PATH.to_string(),
+            WalletType::Solana,
+            key.verifying_key().to_bytes().to_vec(),
+            SECOND_WALLET.to_string(),
+            key.sign(message.as_bytes()).to_bytes().to_vec(),
+            message.into_bytes(),
+            0,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid signature")]
+    fn test_link_wallet_requires_owner_signature() {
+        let (mut contract, _) = setup_with_wallet();
+        let key = SigningKey::from_bytes(&[9u8; 32]);
+        let new_wallet = RegisteredWallet {
+            wallet_type: WalletType::Solana,
+            public_key: key.verifying_key().to_bytes().to_vec(),
+            chain_address: SECOND_WALLET.to_string(),
+        };
+        // The new wallet signs for the owner too
+        let message = link_message(&new_wallet, 1);
+        let signature = key.sign(&message).to_bytes().to_vec();
+        contract.link_wallet(
+            TEST_PATH.to_string(),
+            new_wallet,
+            signature.clone(),
+            message.clone(),
+            signature,
+            message,
+            TEST_WALLET.to_string(),
+            1,
+        );
+    }
+
+    fn remove_wallet_message(wallet_address: &str, close_path: bool, nonce: u64) -> Vec<u8> {
+        near_sdk::serde_json::to_vec(&near_sdk::serde_json::json!({
+            "action": "remove_wallet",
+            "derivation_path": TEST_PATH,
+            "wallet_address": wallet_address,
+            "close_path": close_path,
+            "nonce": nonce,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_remove_wallet_signed_by_other_owner() {
+        let (mut contract, key) = setup_with_wallet();
+        register_second_wallet(&mut contract, &key);
+
+        let message = remove_wallet_message(SECOND_WALLET, false, 2);
+        contract.remove_wallet(
+            TEST_PATH.to_string(),
+            SECOND_WALLET.to_string(),
+            false,
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            2,
+        );
+
+        let perms = contract.get_permissions(TEST_PATH.to_string()).unwrap();
+        assert_eq!(perms.owner_wallets.len(), 1);
+        assert_eq!(
+            contract.get_path_for_wallet(SECOND_WALLET.to_string()),
+            None
+        );
+        assert_eq!(
+            contract.get_path_for_wallet(TEST_WALLET.to_string()),
+            Some(TEST_PATH.to_string())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot remove the last wallet unless closing the path")]
+    fn test_remove_last_wallet_requires_close() {
+        let (mut contract, key) = setup_with_wallet();
+        let message = remove_wallet_message(TEST_WALLET, false, 1);
+        contract.remove_wallet(
+            TEST_PATH.to_string(),
+            TEST_WALLET.to_string(),
+            false,
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            1,
+        );
+    }
+
+    #[test]
+    fn test_close_path_deletes_operations_and_refunds_storage() {
+        let (mut contract, key) = setup_with_wallet();
+        let relayer: AccountId = "relayer.near".parse().unwrap();
+
+        let operation = swap_input("user");
+        let message = add_message(&operation, 1);
+        contract.add_allowed_operation(
+            TEST_PATH.to_string(),
+            operation,
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            1,
+        );
+        let before = contract.storage_balance_of(relayer.clone()).unwrap();
+
+        let message = remove_wallet_message(TEST_WALLET, true, 2);
+        contract.remove_wallet(
+            TEST_PATH.to_string(),
+            TEST_WALLET.to_string(),
+            true,
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            2,
+        );
+
+        assert!(contract.get_permissions(TEST_PATH.to_string()).is_none());
+        assert!(contract.get_active_operations(0, 10).is_empty());
+        assert_eq!(contract.get_path_for_wallet(TEST_WALLET.to_string()), None);
+        assert_eq!(contract.get_storage_sponsor(TEST_PATH.to_string()), None);
+        let after = contract.storage_balance_of(relayer).unwrap();
+        assert!(after.available.0 > before.available.0);
+    }
+
+    fn rotated_wallet(key: &SigningKey) -> RegisteredWallet {
+        RegisteredWallet {
+            wallet_type: WalletType::Solana,
+            public_key: key.verifying_key().to_bytes().to_vec(),
+            chain_address: "rotated-solana-wallet".to_string(),
+        }
+    }
+
+    fn rotate_message(new_wallet: &RegisteredWallet, nonce: u64) -> Vec<u8> {
+        near_sdk::serde_json::to_vec(&near_sdk::serde_json::json!({
+            "action": "rotate_wallet",
+            "derivation_path": TEST_PATH,
+            "old_address": TEST_WALLET,
+            "new_wallet": {
+                "wallet_type": "Solana",
+                "public_key": hex::encode(&new_wallet.public_key),
+                "chain_address": new_wallet.chain_address,
+            },
+            "nonce": nonce,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_rotate_wallet_moves_wallet_mapping() {
+        let (mut contract, key) = setup_with_wallet();
+        let new_key = SigningKey::from_bytes(&[11u8; 32]);
+        let new_wallet = rotated_wallet(&new_key);
+
+        let message = rotate_message(&new_wallet, 1);
+        contract.rotate_wallet(
+            TEST_PATH.to_string(),
+            TEST_WALLET.to_string(),
+            new_wallet.clone(),
+            new_key.sign(&message).to_bytes().to_vec(),
+            message.clone(),
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            1,
+        );
+
+        assert_eq!(contract.get_path_for_wallet(TEST_WALLET.to_string()), None);
+        assert_eq!(
+            contract.get_path_for_wallet(new_wallet.chain_address.clone()),
+            Some(TEST_PATH.to_string())
+        );
+
+        // Only the new key can authorize changes now
+        let operation = swap_input("user");
+        let message = add_message(&operation, 0);
+        contract.add_allowed_operation(
+            TEST_PATH.to_string(),
+            operation,
+            new_key.sign(&message).to_bytes().to_vec(),
+            message,
+            new_wallet.chain_address,
+            0,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid signature")]
+    fn test_rotate_wallet_requires_new_wallet_signature() {
+        let (mut contract, key) = setup_with_wallet();
+        let new_wallet = rotated_wallet(&SigningKey::from_bytes(&[11u8; 32]));
+
+        let message = rotate_message(&new_wallet, 1);
+        contract.rotate_wallet(
+            TEST_PATH.to_string(),
+            TEST_WALLET.to_string(),
+            new_wallet,
+            vec![],
+            vec![],
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            1,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid signature")]
+    fn test_rotate_wallet_rejects_key_not_held() {
+        let (mut contract, key) = setup_with_wallet();
+        // An owner cannot rotate to a public key it does not control
+        let new_wallet = rotated_wallet(&SigningKey::from_bytes(&[11u8; 32]));
+
+        let message = rotate_message(&new_wallet, 1);
+        let signature = key.sign(&message).to_bytes().to_vec();
+        contract.rotate_wallet(
+            TEST_PATH.to_string(),
+            TEST_WALLET.to_string(),
+            new_wallet,
+            signature.clone(),
+            message.clone(),
+            signature,
+            message,
+            TEST_WALLET.to_string(),
+            1,
+        );
+    }
+
+    fn approve_message(proposal_id: &str, nonce: u64) -> Vec<u8> {
+        near_sdk::serde_json::to_vec(&near_sdk::serde_json::json!({
+            "action": "approve_proposal",
+            "derivation_path": TEST_PATH,
+            "proposal_id": proposal_id,
+            "nonce": nonce,
+        }))
+        .unwrap()
+    }
+
+    /// TEST_PATH owned by TEST_WALLET and SECOND_WALLET with a 2-of-2 threshold;
+    /// TEST_WALLET's next nonce is 3
+    fn setup_with_threshold() -> (PermissionContract, SigningKey, SigningKey) {
+        let (mut contract, key) = setup_with_wallet();
+        let second_key = register_second_wallet(&mut contract, &key);
+
+        let message = near_sdk::serde_json::to_vec(&near_sdk::serde_json::json!({
+            "action": "set_threshold",
+            "derivation_path": TEST_PATH,
+            "threshold": 2,
+            "nonce": 2,
+        }))
+        .unwrap();
+        contract.propose_change(
+            TEST_PATH.to_string(),
+            PathChange::SetThreshold { threshold: 2 },
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            2,
+        );
+
+        (contract, key, second_key)
+    }
+
+    #[test]
+    fn test_proposal_applied_at_threshold() {
+        let (mut contract, key, second_key) = setup_with_threshold();
+        let perms = contract.get_permissions(TEST_PATH.to_string()).unwrap();
+        assert_eq!(perms.threshold, 2);
+
+        let operation = swap_input("user");
+        let message = add_message(&operation, 3);
+        let proposal_id = contract.propose_change(
+            TEST_PATH.to_string(),
+            PathChange::AddOperation {
+                operation: Box::new(operation),
+            },
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            3,
+        );
+        assert!(contract.get_operations(TEST_PATH.to_string()).is_empty());
+        assert_eq!(contract.get_proposals(TEST_PATH.to_string()).len(), 1);
+
+        let message = approve_message(&proposal_id, 0);
+        contract.approve_proposal(
+            TEST_PATH.to_string(),
+            proposal_id,
+            second_key.sign(&message).to_bytes().to_vec(),
+            message,
+            SECOND_WALLET.to_string(),
+            0,
+        );
+        assert_eq!(contract.get_operations(TEST_PATH.to_string()).len(), 1);
+        assert!(contract.get_proposals(TEST_PATH.to_string()).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Path requires threshold approval; submit a proposal")]
+    fn test_direct_add_rejected_under_threshold() {
+        let (mut contract, key, _) = setup_with_threshold();
+        let operation = swap_input("user");
+        let message = add_message(&operation, 3);
+        contract.add_allowed_operation(
+            TEST_PATH.to_string(),
+            operation,
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            3,
+        );
+    }
+
+    /// Freeze the threshold path with the second wallet alone
+    fn freeze_with_second_wallet(contract: &mut PermissionContract, second_key: &SigningKey) {
+        let message = path_message("freeze_path", 0);
+        contract.freeze_path(
+            TEST_PATH.to_string(),
+            second_key.sign(&message).to_bytes().to_vec(),
+            message,
+            SECOND_WALLET.to_string(),
+            0,
+        );
+        assert!(
+            contract
+                .get_permissions(TEST_PATH.to_string())
+                .unwrap()
+                .frozen
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Path requires threshold approval; submit a proposal")]
+    fn test_direct_unfreeze_rejected_under_threshold() {
+        let (mut contract, key, second_key) = setup_with_threshold();
+        freeze_with_second_wallet(&mut contract, &second_key);
+
+        let message = path_message("unfreeze_path", 3);
+        contract.unfreeze_path(
+            TEST_PATH.to_string(),
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            3,
+        );
+    }
+
+    #[test]
+    fn test_unfreeze_by_proposal() {
+        let (mut contract, key, second_key) = setup_with_threshold();
+        freeze_with_second_wallet(&mut contract, &second_key);
+
+        let message = path_message("unfreeze_path", 3);
+        let proposal_id = contract.propose_change(
+            TEST_PATH.to_string(),
+            PathChange::Unfreeze,
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            3,
+        );
+        assert!(
+            contract
+                .get_permissions(TEST_PATH.to_string())
+                .unwrap()
+                .frozen
+        );
+
+        let message = approve_message(&proposal_id, 1);
+        contract.approve_proposal(
+            TEST_PATH.to_string(),
+            proposal_id,
+            second_key.sign(&message).to_bytes().to_vec(),
+            message,
+            SECOND_WALLET.to_string(),
+            1,
+        );
+        assert!(
+            !contract
+                .get_permissions(TEST_PATH.to_string())
+                .unwrap()
+                .frozen
+        );
+    }
+
+    /// Approve a pending proposal with SECOND_WALLET (nonce 1)
+    fn approve_with_second_wallet(
+        contract: &mut PermissionContract,
+        second_key: &SigningKey,
+        proposal_id: String,
+    ) {
+        let message = approve_message(&proposal_id, 1);
+        contract.approve_proposal(
+            TEST_PATH.to_string(),
+            proposal_id,
+            second_key.sign(&message).to_bytes().to_vec(),
+            message,
+            SECOND_WALLET.to_string(),
+            1,
+        );
+    }
+
+    #[test]
+    fn test_link_wallet_by_proposal() {
+        let (mut contract, key, second_key) = setup_with_threshold();
+        let new_key = SigningKey::from_bytes(&[12u8; 32]);
+        let new_wallet = RegisteredWallet {
+            wallet_type: WalletType::Solana,
+            public_key: new_key.verifying_key().to_bytes().to_vec(),
+            chain_address: "third-solana-wallet".to_string(),
+        };
+
+        let message = link_message(&new_wallet, 3);
+        let proposal_id = contract.propose_change(
+            TEST_PATH.to_string(),
+            PathChange::LinkWallet {
+                new_wallet: new_wallet.clone(),
+                new_wallet_signature: new_key.sign(&message).to_bytes().to_vec(),
+                new_wallet_message: message.clone(),
+            },
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            3,
+        );
+        assert_eq!(
+            contract.get_path_for_wallet(new_wallet.chain_address.clone()),
+            None
+        );
+
+        approve_with_second_wallet(&mut contract, &second_key, proposal_id);
+        let perms = contract.get_permissions(TEST_PATH.to_string()).unwrap();
+        assert_eq!(perms.owner_wallets.len(), 3);
+        assert_eq!(
+            contract.get_path_for_wallet(new_wallet.chain_address),
+            Some(TEST_PATH.to_string())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid signature")]
+    fn test_propose_link_wallet_requires_new_wallet_signature() {
+        let (mut contract, key, _) = setup_with_threshold();
+        let new_wallet = RegisteredWallet {
+            wallet_type: WalletType::Solana,
+            public_key: SigningKey::from_bytes(&[12u8; 32])
+                .verifying_key()
+                .to_bytes()
+                .to_vec(),
+            chain_address: "third-solana-wallet".to_string(),
+        };
+
+        let message = link_message(&new_wallet, 3);
+        let signature = key.sign(&message).to_bytes().to_vec();
+        contract.propose_change(
+            TEST_PATH.to_string(),
+            PathChange::LinkWallet {
+                new_wallet,
+                new_wallet_signature: signature.clone(),
+                new_wallet_message: message.clone(),
+            },
+            signature,
+            message,
+            TEST_WALLET.to_string(),
+            3,
+        );
+    }
+
+    #[test]
+    fn test_rotate_wallet_by_proposal() {
+        let (mut contract, key, second_key) = setup_with_threshold();
+        let new_key = SigningKey::from_bytes(&[11u8; 32]);
+        let new_wallet = rotated_wallet(&new_key);
+
+        let message = rotate_message(&new_wallet, 3);
+        let proposal_id = contract.propose_change(
+            TEST_PATH.to_string(),
+            PathChange::RotateWallet {
+                old_address: TEST_WALLET.to_string(),
+                new_wallet: new_wallet.clone(),
+                new_wallet_signature: new_key.sign(&message).to_bytes().to_vec(),
+                new_wallet_message: message.clone(),
+            },
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            3,
+        );
+
+        approve_with_second_wallet(&mut contract, &second_key, proposal_id);
+        assert_eq!(contract.get_path_for_wallet(TEST_WALLET.to_string()), None);
+        assert_eq!(
+            contract.get_path_for_wallet(new_wallet.chain_address),
+            Some(TEST_PATH.to_string())
+        );
+        assert_eq!(
+            contract
+                .get_permissions(TEST_PATH.to_string())
+                .unwrap()
+                .threshold,
+            2
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Removing the wallet would leave fewer wallets than the threshold")]
+    fn test_propose_remove_wallet_below_threshold_rejected() {
+        let (mut contract, key, _) = setup_with_threshold();
+        let message = remove_wallet_message(SECOND_WALLET, false, 3);
+        contract.propose_change(
+            TEST_PATH.to_string(),
+            PathChange::RemoveWallet {
+                wallet_address: SECOND_WALLET.to_string(),
+            },
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            3,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Wallet already approved this proposal")]
+    fn test_proposer_cannot_approve_again() {
+        let (mut contract, key, _) = setup_with_threshold();
+        let message = remove_message("missing", 3);
+        let proposal_id = contract.propose_change(
+            TEST_PATH.to_string(),
+            PathChange::RemoveOperation {
+                operation_id: "missing".to_string(),
+            },
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            3,
+        );
+        let message = approve_message(&proposal_id, 4);
+        contract.approve_proposal(
+            TEST_PATH.to_string(),
+            proposal_id,
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            4,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Proposal not found or expired")]
+    fn test_expired_proposal_cannot_be_approved() {
+        let (mut contract, key, second_key) = setup_with_threshold();
+        let message = remove_message("missing", 3);
+        let proposal_id = contract.propose_change(
+            TEST_PATH.to_string(),
+            PathChange::RemoveOperation {
+                operation_id: "missing".to_string(),
+            },
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            3,
+        );
+
+        testing_env!(get_context("relayer.near".parse().unwrap())
+            .block_timestamp(PROPOSAL_TTL_NS + 1)
+            .build());
+        let message = approve_message(&proposal_id, 0);
+        contract.approve_proposal(
+            TEST_PATH.to_string(),
+            proposal_id,
+            second_key.sign(&message).to_bytes().to_vec(),
+            message,
+            SECOND_WALLET.to_string(),
+            0,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Threshold must be between 1 and the number of owner wallets")]
+    fn test_threshold_cannot_exceed_wallet_count() {
+        let (mut contract, key) = setup_with_wallet();
+        let message = near_sdk::serde_json::to_vec(&near_sdk::serde_json::json!({
+            "action": "set_threshold",
+            "derivation_path": TEST_PATH,
+            "threshold": 2,
+            "nonce": 1,
+        }))
+        .unwrap();
+        contract.propose_change(
+            TEST_PATH.to_string(),
+            PathChange::SetThreshold { threshold: 2 },
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            1,
+        );
+    }
+
+    #[test]
+    fn test_oracle_price_triggers_stop_loss() {
+        let (mut contract, key) = setup_with_wallet();
+        let (operation_id, payload) = add_stop_loss(&mut contract, &key);
+
+        testing_env!(get_context("owner.near".parse().unwrap()).build());
+        contract.set_price_oracle("oracle.near".parse().unwrap());
+        contract.set_price_feed("SOL".to_string(), "ef0d8b6f".to_string());
+
+        // Relayer call defers to the oracle and leaves the operation pending
+        testing_env!(get_context("relayer.near".parse().unwrap()).build());
+        contract.sign_allowed(
+            TEST_PATH.to_string(),
+            operation_id.clone(),
+            payload,
+            "Eddsa".to_string(),
+            U128(1_000),
+        );
+        assert!(contract.is_operation_allowed(TEST_PATH.to_string(), operation_id.clone()));
+
+        // SOL at $99.50 is below the $100 trigger
+        deliver_oracle_price(&mut contract, &operation_id, Some(sol_usd(9_950_000_000)));
+        assert!(!contract.is_operation_allowed(TEST_PATH.to_string(), operation_id));
+    }
+
+    #[test]
+    #[should_panic(expected = "Stop-loss condition not met: price above trigger")]
+    fn test_oracle_price_above_stop_loss_rejected() {
+        let (mut contract, key) = setup_with_wallet();
+        let (operation_id, _) = add_stop_loss(&mut contract, &key);
+
+        deliver_oracle_price(&mut contract, &operation_id, Some(sol_usd(10_050_000_000)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Oracle price unavailable or stale")]
+    fn test_stale_oracle_price_rejected() {
+        let (mut contract, key) = setup_with_wallet();
+        let (operation_id, _) = add_stop_loss(&mut contract, &key);
+
+        deliver_oracle_price(&mut contract, &operation_id, None);
+    }
+
+    #[test]
+    fn test_mpc_sign_complete_returns_secp256k1_signature() {
+        let (mut contract, _) = setup_with_wallet();
+        testing_env!(get_context(env::current_account_id()).build());
+
+        let response: SignatureResponse =
+            near_sdk::serde_json::from_value(near_sdk::serde_json::json!({
+                "scheme": "Secp256k1",
+                "big_r": { "affine_point": format!("02{}", "11".repeat(32)) },
+                "s": { "scalar": "22".repeat(32) },
+                "recovery_id": 0,
+            }))
+            .unwrap();
+        let signature = contract.on_mpc_sign_complete(
+            TEST_PATH.to_string(),
+            "op".to_string(),
+            0,
+            None,
+            U128(1),
+            Ok(response),
+        );
+
+        assert_eq!(
+            signature,
+            Some(MpcSignature::Secp256k1 {
+                r: "11".repeat(32),
+                s: "22".repeat(32),
+                v: 0,
+            })
+        );
+    }
+
+    /// Deliver a failed MPC response for an execution recorded at `executed_at`
+    fn fail_mpc_sign(
+        contract: &mut PermissionContract,
+        operation_id: &str,
+        executed_at: u64,
+        previous_executed_at: Option<u64>,
+        amount: u128,
+    ) -> Option<MpcSignature> {
+        testing_env!(get_context(env::current_account_id()).build());
+        contract.on_mpc_sign_complete(
+            TEST_PATH.to_string(),
+            operation_id.to_string(),
+            executed_at,
+            previous_executed_at,
+            U128(amount),
+            Err(PromiseError::Failed),
+        )
+    }
+
+    #[test]
+    fn test_mpc_sign_failure_reverts_execution() {
+        let (mut contract, key) = setup_with_wallet();
+        let (operation_id, payload) = add_partial_swap(&mut contract, &key);
+        spend_at(&mut contract, &operation_id, &payload, 3, 10);
+        assert!(!contract.is_operation_allowed(TEST_PATH.to_string(), operation_id.clone()));
+        assert!(contract.get_active_operations(0, 10).is_empty());
+
+        assert_eq!(
+            fail_mpc_sign(&mut contract, &operation_id, 10, None, 3),
+            None
+        );
+
+        let operation = contract
+            .get_operation(TEST_PATH.to_string(), operation_id.clone())
+            .unwrap();
+        assert_eq!(operation.consumed_amount, U128(0));
+        assert_eq!(operation.execution_count, 0);
+        assert_eq!(operation.last_executed_at, None);
+        assert!(!operation.executed);
+        assert_eq!(contract.get_active_operations(0, 10).len(), 1);
+        assert!(contract.is_operation_allowed(TEST_PATH.to_string(), operation_id));
+    }
+
+    #[test]
+    fn test_mpc_sign_failure_emits_event() {
+        let (mut contract, key) = setup_with_wallet();
+        let (operation_id, payload) = add_partial_swap(&mut contract, &key);
+        spend_at(&mut contract, &operation_id, &payload, 1, 10);
+        fail_mpc_sign(&mut contract, &operation_id, 10, None, 1);
+
+        let logs = near_sdk::test_utils::get_logs();
+        let log: EventLog = near_sdk::serde_json::from_str(
+            logs.last().unwrap().strip_prefix("EVENT_JSON:").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            log.event,
+            PermissionEvent::MpcSignatureFailed {
+                derivation_path: TEST_PATH.to_string(),
+                operation_id,
+                error: "Failed".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_mpc_sign_failure_keeps_later_execution() {
+        let (mut contract, key) = setup_with_wallet();
+        let (operation_id, payload) = add_partial_swap(&mut contract, &key);
+        spend_at(&mut contract, &operation_id, &payload, 1, 10);
+        spend_at(&mut contract, &operation_id, &payload, 2, 20);
+
+        // The first fill fails after the second completed the budget
+        fail_mpc_sign(&mut contract, &operation_id, 10, None, 1);
+
+        let operation = contract
+            .get_operation(TEST_PATH.to_string(), operation_id.clone())
+            .unwrap();
+        assert_eq!(operation.consumed_amount, U128(2));
+        assert_eq!(operation.execution_count, 1);
+        assert_eq!(operation.last_executed_at, Some(20));
+        assert!(!operation.executed);
+        assert_eq!(contract.get_active_operations(0, 10).len(), 1);
+    }
+
+    fn nep413_register(
+        contract: &mut PermissionContract,
+        recipient: &str,
+        nep413_nonce: [u8; 32],
+        nonce: u64,
+    ) {
+        let key = SigningKey::from_bytes(&[11u8; 32]);
+        let payload = signature::Nep413Payload {
+            message: format!(
+                "Register wallet for derivation path: near-1,test with nonce: {}",
+                nonce
+            ),
+            nonce: nep413_nonce,
+            recipient: recipient.to_string(),
+            callback_url: None,
+        };
+        let mut signed = signature::NEP413_TAG.to_le_bytes().to_vec();
+        signed.extend(near_sdk::borsh::to_vec(&payload).unwrap());
+
+        contract.register_wallet(
+            "near-1,test".to_string(),
+            WalletType::Near,
+            key.verifying_key().to_bytes().to_vec(),
+            "user.near".to_string(),
+            key.sign(&env::sha256(&signed)).to_bytes().to_vec(),
+            near_sdk::borsh::to_vec(&payload).unwrap(),
+            nonce,
+        );
+    }
+
+    #[test]
+    fn test_register_near_wallet_nep413() {
+        let (mut contract, _) = setup_with_wallet();
+        let recipient = env::current_account_id().to_string();
+
+        nep413_register(&mut contract, &recipient, [1u8; 32], 1);
+        assert_eq!(
+            contract.get_path_for_wallet("user.near".to_string()),
+            Some("near-1,test".to_string())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "NEP-413 recipient must be this contract")]
+    fn test_register_near_wallet_wrong_recipient() {
+        let (mut contract, _) = setup_with_wallet();
+        nep413_register(&mut contract, "other-dapp.near", [1u8; 32], 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Nonce already used")]
+    fn test_register_near_wallet_reused_nep413_nonce() {
+        let (mut contract, _) = setup_with_wallet();
+        let recipient = env::current_account_id().to_string();
+
+        nep413_register(&mut contract, &recipient, [1u8; 32], 1);
+        nep413_register(&mut contract, &recipient, [1u8; 32], 2);
+    }
+
+    #[test]
+    fn test_register_solana_wallet_offchain_message() {
+        let (mut contract, _) = setup_with_wallet();
+        let key = SigningKey::from_bytes(&[13u8; 32]);
+
+        // Ledger-style envelope: domain, version 0, restricted ASCII, u16 LE length
+        let body = b"Register wallet for derivation path: solana-1,ledger with nonce: 1";
+        let mut message = signature::SOLANA_OFFCHAIN_DOMAIN.to_vec();
+        message.extend([0, 0]);
+        message.extend((body.len() as u16).to_le_bytes());
+        message.extend_from_slice(body);
+
+        contract.register_wallet(
+            "solana-1,ledger".to_string(),
+            WalletType::Solana,
+            key.verifying_key().to_bytes().to_vec(),
+            "ledger-wallet".to_string(),
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            1,
+        );
+        assert_eq!(
+            contract.get_path_for_wallet("ledger-wallet".to_string()),
+            Some("solana-1,ledger".to_string())
+        );
+    }
+
+    /// k256 key, its address and an EIP-712 signature over `action`
+    fn eip712_sign(action: &SignedAction) -> (String, Vec<u8>) {
+        let key = k256::ecdsa::SigningKey::from_bytes(&[9u8; 32].into()).unwrap();
+        let encoded = key.verifying_key().to_encoded_point(false);
+        let address = format!(
+            "0x{}",
+            hex::encode(&env::keccak256(&encoded.as_bytes()[1..])[12..])
+        );
+
+        let digest = eip712::action_digest(action, env::current_account_id().as_str());
+        let (sig, recid) = key.sign_prehash_recoverable(&digest).unwrap();
+        let mut signature = sig.to_bytes().to_vec();
+        signature.push(recid.to_byte() + 27);
+        (address, signature)
+    }
+
+    #[test]
+    fn test_register_and_add_with_eip712() {
+        let (mut contract, _) = setup_with_wallet();
+        let (address, signature) = eip712_sign(&SignedAction::RegisterWallet {
+            derivation_path: "evm-1,test",
+            nonce: 1,
+        });
+        contract.register_wallet(
+            "evm-1,test".to_string(),
+            WalletType::Eip712,
+            vec![],
+            address.clone(),
+            signature,
+            vec![],
+            1,
+        );
+        assert_eq!(
+            contract.get_path_for_wallet(address.clone()),
+            Some("evm-1,test".to_string())
+        );
+        approve_test_destinations(&mut contract, "evm-1,test");
+
+        let operation = swap_input("user");
+        let (_, signature) = eip712_sign(&SignedAction::AddOperation {
+            derivation_path: "evm-1,test",
+            operation: &operation,
+            nonce: 0,
+        });
+        contract.add_allowed_operation(
+            "evm-1,test".to_string(),
+            operation,
+            signature,
+            vec![],
+            address.clone(),
+            0,
+        );
+        assert_eq!(contract.get_next_nonce(address), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid signature")]
+    fn test_eip712_signature_for_other_path_rejected() {
+        let (mut contract, _) = setup_with_wallet();
+        let (address, signature) = eip712_sign(&SignedAction::RegisterWallet {
+            derivation_path: "evm-1,other",
+            nonce: 1,
+        });
+        contract.register_wallet(
+            "evm-1,test".to_string(),
+            WalletType::Eip712,
+            vec![],
+            address,
+            signature,
+            vec![],
+            1,
+        );
+    }
+
+    fn spending_limit_message(limit: u128, window: u64, nonce: u64) -> Vec<u8> {
+        near_sdk::serde_json::to_vec(&near_sdk::serde_json::json!({
+            "action": "set_spending_limit",
+            "derivation_path": TEST_PATH,
+            "asset": "SOL",
+            "limit": limit.to_string(),
+            "window": window,
+            "nonce": nonce,
+        }))
+        .unwrap()
+    }
+
+    /// Cap SOL leaving TEST_PATH at 2 lamports per 100ns, signed with `nonce`
+    fn set_sol_limit(contract: &mut PermissionContract, key: &SigningKey, nonce: u64) {
+        let message = spending_limit_message(2, 100, nonce);
+        contract.set_spending_limit(
+            TEST_PATH.to_string(),
+            "SOL".to_string(),
+            U128(2),
+            100,
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            nonce,
+        );
+    }
+
+    fn spend_at(
+        contract: &mut PermissionContract,
+        operation_id: &str,
+        payload: &[u8],
+        amount: u128,
+        now: u64,
+    ) {
+        testing_env!(get_context("relayer.near".parse().unwrap())
+            .block_timestamp(now)
+            .build());
+        contract.sign_allowed(
+            TEST_PATH.to_string(),
+            operation_id.to_string(),
+            payload.to_vec(),
+            "Eddsa".to_string(),
+            U128(amount),
+        );
+    }
+
+    #[test]
+    fn test_mpc_sign_failure_refunds_its_own_spend() {
+        let (mut contract, key) = setup_with_wallet();
+        let (operation_id, payload) = add_partial_swap(&mut contract, &key);
+        set_sol_limit(&mut contract, &key, 2);
+        spend_at(&mut contract, &operation_id, &payload, 1, 10);
+        spend_at(&mut contract, &operation_id, &payload, 1, 20);
+
+        // Refunds the spend at 10, not the later one at 20
+        fail_mpc_sign(&mut contract, &operation_id, 10, None, 1);
+
+        testing_env!(get_context("relayer.near".parse().unwrap())
+            .block_timestamp(115)
+            .build());
+        let limits = contract.get_spending_limits(TEST_PATH.to_string());
+        assert_eq!(limits[0].used, U128(1));
+
+        // Both spends are within the window at 20; one remains after the refund
+        testing_env!(get_context("relayer.near".parse().unwrap())
+            .block_timestamp(20)
+            .build());
+        let limits = contract.get_spending_limits(TEST_PATH.to_string());
+        assert_eq!(limits[0].remaining, U128(1));
+    }
+
+    #[test]
+    fn test_spending_limit_tracks_rolling_window() {
+        let (mut contract, key) = setup_with_wallet();
+        let (operation_id, payload) = add_partial_swap(&mut contract, &key);
+        set_sol_limit(&mut contract, &key, 2);
+
+        spend_at(&mut contract, &operation_id, &payload, 1, 10);
+        spend_at(&mut contract, &operation_id, &payload, 1, 20);
+        let limits = contract.get_spending_limits(TEST_PATH.to_string());
+        assert_eq!(limits.len(), 1);
+        assert_eq!(limits[0].used, U128(2));
+        assert_eq!(limits[0].remaining, U128(0));
+
+        // The first spend leaves the window at 110
+        spend_at(&mut contract, &operation_id, &payload, 1, 110);
+        let limits = contract.get_spending_limits(TEST_PATH.to_string());
+        assert_eq!(limits[0].used, U128(2));
+        let limit = &contract
+            .load_permissions(&TEST_PATH.to_string())
+            .unwrap()
+            .spending_limits[0];
+        assert_eq!(limit.spends.len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Spending limit exceeded")]
+    fn test_spending_limit_applies_across_operations() {
+        let (mut contract, key) = setup_with_wallet();
+        let (first_id, payload) = add_partial_swap(&mut contract, &key);
+        set_sol_limit(&mut contract, &key, 2);
+        let operation = AllowedOperationInput {
+            operation_type: AllowedOperationType::Swap {
+                source_asset: "SOL".to_string(),
+                target_asset: "USDC".to_string(),
+                max_amount: U128(3),
+            },
+            source_chain: "solana".to_string(),
+            destination_address: bs58::encode([2u8; 32]).into_string(),
+            destination_chain: "solana".to_string(),
+            slippage_bps: 50,
+            expires_at: None,
+            recurring: None,
+        };
+        let message = add_message(&operation, 3);
+        let second_id = contract.add_allowed_operation(
+            TEST_PATH.to_string(),
+            operation,
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            3,
+        );
+
+        spend_at(&mut contract, &first_id, &payload, 2, 10);
+        spend_at(&mut contract, &second_id, &payload, 1, 20);
+    }
+
+    #[test]
+    fn test_removed_spending_limit_no_longer_applies() {
+        let (mut contract, key) = setup_with_wallet();
+        let (operation_id, payload) = add_partial_swap(&mut contract, &key);
+        set_sol_limit(&mut contract, &key, 2);
+
+        let message = near_sdk::serde_json::to_vec(&near_sdk::serde_json::json!({
+            "action": "remove_spending_limit",
+            "derivation_path": TEST_PATH,
+            "asset": "SOL",
+            "nonce": 3,
+        }))
+        .unwrap();
+        contract.remove_spending_limit(
+            TEST_PATH.to_string(),
+            "SOL".to_string(),
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            3,
+        );
+        assert!(contract
+            .get_spending_limits(TEST_PATH.to_string())
+            .is_empty());
+
+        spend_at(&mut contract, &operation_id, &payload, 3, 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "Message spending limit mismatch")]
+    fn test_spending_limit_message_must_match() {
+        let (mut contract, key) = setup_with_wallet();
+        let message = spending_limit_message(2, 100, 1);
+        contract.set_spending_limit(
+            TEST_PATH.to_string(),
+            "SOL".to_string(),
+            U128(1_000),
+            100,
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            1,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Spending window too long")]
+    fn test_spending_window_capped() {
+        let (mut contract, key) = setup_with_wallet();
+        let window = MAX_SPENDING_WINDOW_NS + 1;
+        let message = spending_limit_message(2, window, 1);
+        contract.set_spending_limit(
+            TEST_PATH.to_string(),
+            "SOL".to_string(),
+            U128(2),
+            window,
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            1,
+        );
+    }
+
+    #[test]
+    fn test_spending_limit_by_proposal() {
+        let (mut contract, key, second_key) = setup_with_threshold();
+        let message = spending_limit_message(2, 100, 3);
+        let proposal_id = contract.propose_change(
+            TEST_PATH.to_string(),
+            PathChange::SetSpendingLimit {
+                asset: "SOL".to_string(),
+                limit: U128(2),
+                window: 100,
+            },
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            3,
+        );
+        assert!(contract
+            .get_spending_limits(TEST_PATH.to_string())
+            .is_empty());
+
+        let message = approve_message(&proposal_id, 0);
+        contract.approve_proposal(
+            TEST_PATH.to_string(),
+            proposal_id,
+            second_key.sign(&message).to_bytes().to_vec(),
+            message,
+            SECOND_WALLET.to_string(),
+            0,
+        );
+        let limits = contract.get_spending_limits(TEST_PATH.to_string());
+        assert_eq!(limits[0].limit, U128(2));
+        assert_eq!(limits[0].window, 100);
+    }
+
+    fn destination_message(action: &str, address: &str, nonce: u64) -> Vec<u8> {
+        near_sdk::serde_json::to_vec(&near_sdk::serde_json::json!({
+            "action": action,
+            "derivation_path": TEST_PATH,
+            "chain": "solana",
+            "address": address,
+            "nonce": nonce,
+        }))
+        .unwrap()
+    }
+
+    fn add_destination(
+        contract: &mut PermissionContract,
+        key: &SigningKey,
+        address: &str,
+        nonce: u64,
+    ) {
+        let message = destination_message("add_destination", address, nonce);
+        contract.add_destination(
+            TEST_PATH.to_string(),
+            "solana".to_string(),
+            address.to_string(),
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            nonce,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Destination not in address book")]
+    fn test_empty_address_book_rejects_operations() {
+        let (mut contract, key) = setup_with_empty_book();
+        add_partial_swap(&mut contract, &key);
+    }
+
+    #[test]
+    #[should_panic(expected = "Destination not in address book")]
+    fn test_address_book_rejects_unknown_destination() {
+        let (mut contract, key) = setup_with_empty_book();
+        add_destination(
+            &mut contract,
+            &key,
+            &bs58::encode([3u8; 32]).into_string(),
+            1,
+        );
+
+        // add_partial_swap pays [2; 32], which is not in the book
+        add_partial_swap_with_nonce(&mut contract, &key, 2);
+    }
+
+    #[test]
+    fn test_address_book_destination_activates_after_delay() {
+        let (mut contract, key) = setup_with_empty_book();
+        let destination = bs58::encode([2u8; 32]).into_string();
+        add_destination(&mut contract, &key, &destination, 1);
+        // Pending entries already allow creating operations
+        let (operation_id, payload) = add_partial_swap_with_nonce(&mut contract, &key, 2);
+
+        let book = contract.get_destinations(TEST_PATH.to_string());
+        assert_eq!(book.len(), 1);
+        assert_eq!(book[0].active_at, DESTINATION_ACTIVATION_DELAY_NS);
+
+        spend_at(
+            &mut contract,
+            &operation_id,
+            &payload,
+            1,
+            DESTINATION_ACTIVATION_DELAY_NS,
+        );
+        let operation = contract
+            .get_operation(TEST_PATH.to_string(), operation_id)
+            .unwrap();
+        assert_eq!(operation.consumed_amount, U128(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Destination not active yet")]
+    fn test_pending_destination_blocks_signing() {
+        let (mut contract, key) = setup_with_empty_book();
+        add_destination(
+            &mut contract,
+            &key,
+            &bs58::encode([2u8; 32]).into_string(),
+            1,
+        );
+        let (operation_id, payload) = add_partial_swap_with_nonce(&mut contract, &key, 2);
+
+        spend_at(
+            &mut contract,
+            &operation_id,
+            &payload,
+            1,
+            DESTINATION_ACTIVATION_DELAY_NS - 1,
+        );
+    }
+
+    /// Pay [2; 32] through a new operation, then remove the book's only entry
+    fn add_swap_and_remove_destination(
+        contract: &mut PermissionContract,
+        key: &SigningKey,
+    ) -> (String, Vec<u8>) {
+        let destination = bs58::encode([2u8; 32]).into_string();
+        add_destination(contract, key, &destination, 1);
+        let added = add_partial_swap_with_nonce(contract, key, 2);
+
+        let message = destination_message("remove_destination", &destination, 3);
+        contract.remove_destination(
+            TEST_PATH.to_string(),
+            "solana".to_string(),
+            destination,
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            3,
+        );
+        added
+    }
+
+    #[test]
+    #[should_panic(expected = "Destination not in address book")]
+    fn test_removed_destination_blocks_signing() {
+        let (mut contract, key) = setup_with_empty_book();
+        let (operation_id, payload) = add_swap_and_remove_destination(&mut contract, &key);
+        assert!(contract.get_destinations(TEST_PATH.to_string()).is_empty());
+
+        // An emptied book blocks signing rather than allowing any destination
+        spend_at(
+            &mut contract,
+            &operation_id,
+            &payload,
+            1,
+            DESTINATION_ACTIVATION_DELAY_NS,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Destination not in address book")]
+    fn test_emptied_address_book_rejects_operations() {
+        let (mut contract, key) = setup_with_empty_book();
+        add_swap_and_remove_destination(&mut contract, &key);
+        add_partial_swap_with_nonce(&mut contract, &key, 4);
+    }
+
+    const ATTESTATION_ROOT: [u8; 32] = [4u8; 32];
+
+    fn relayer_key() -> near_sdk::PublicKey {
+        let key = SigningKey::from_bytes(&[9u8; 32]);
+        near_sdk::PublicKey::from_parts(
+            near_sdk::CurveType::ED25519,
+            key.verifying_key().to_bytes().to_vec(),
+        )
+        .unwrap()
+    }
+
+    /// Registration for measurement [1; 48] / compose hash [2; 32], certified by
+    /// ATTESTATION_ROOT
+    fn signed_registration(report_data: &[u8; 64], issued_at: u64) -> Vec<u8> {
+        let root = SigningKey::from_bytes(&ATTESTATION_ROOT);
+        let signing_key = SigningKey::from_bytes(&[5u8; 32]);
+        let mut body = registration::REGISTRATION_VERSION.to_le_bytes().to_vec();
+        body.extend_from_slice(&issued_at.to_le_bytes());
+        body.extend_from_slice(&[1u8; 48]);
+        body.extend_from_slice(&[2u8; 32]);
+        body.extend_from_slice(report_data);
+        body.extend_from_slice(signing_key.verifying_key().as_bytes());
+
+        let mut certified = registration::SIGNING_KEY_DOMAIN.to_vec();
+        certified.extend_from_slice(signing_key.verifying_key().as_bytes());
+
+        let mut registration = body.clone();
+        registration.extend_from_slice(&signing_key.sign(&body).to_bytes());
+        registration.extend_from_slice(root.verifying_key().as_bytes());
+        registration.extend_from_slice(&root.sign(&certified).to_bytes());
+        registration
+    }
+
+    /// Contract trusting ATTESTATION_ROOT with the fixture measurement approved
+    fn setup_attestation() -> PermissionContract {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = PermissionContract::new(owner, "mpc.near".parse().unwrap());
+        let root = SigningKey::from_bytes(&ATTESTATION_ROOT);
+        contract.add_attestation_root(hex::encode(root.verifying_key().as_bytes()));
+        contract.approve_measurement(hex::encode([1u8; 48]), hex::encode([2u8; 32]));
+        contract
+    }
+
+    /// Transaction signed by relayer.near with `relayer_key()` at `now`
+    fn relayer_context(now: u64) -> VMContextBuilder {
+        let relayer: AccountId = "relayer.near".parse().unwrap();
+        let mut builder = get_context(relayer.clone());
+        builder
+            .signer_account_id(relayer)
+            .signer_account_pk(relayer_key())
+            .block_timestamp(now);
+        builder
+    }
+
+    fn register_attested(contract: &mut PermissionContract, issued_at: u64, now: u64) {
+        testing_env!(relayer_context(now).build());
+        let report_data =
+            registration::relayer_report_data("relayer.near", relayer_key().as_bytes());
+        contract.register_attested_relayer(signed_registration(&report_data, issued_at));
+    }
+
+    #[test]
+    fn test_register_attested_relayer() {
+        let mut contract = setup_attestation();
+        register_attested(&mut contract, 1_000, 2_000);
+
+        let relayer: AccountId = "relayer.near".parse().unwrap();
+        let attested = contract.get_attested_relayer(relayer.clone()).unwrap();
+        assert_eq!(attested.public_key, relayer_key());
+        assert_eq!(attested.measurement, hex::encode([1u8; 48]));
+        assert_eq!(
+            attested.expires_at,
+            1_000 + registration::ATTESTATION_TTL_NS
+        );
+        assert!(contract.is_tee_relayer(relayer.clone()));
+        assert_eq!(
+            contract.get_relayer_scope(relayer.clone()),
+            Some(RelayerScope::deny_all())
+        );
+
+        // Once granted a scope, attested relayers pass without
+        // register_tee_relayer, even when attestation is required
+        testing_env!(get_context("owner.near".parse().unwrap()).build());
+        contract.set_relayer_scope(relayer, solana_scope());
+        contract.set_require_attestation(true);
+        testing_env!(relayer_context(3_000).build());
+        contract.assert_tee_relayer("sign_allowed", TEST_PATH);
+    }
+
+    #[test]
+    #[should_panic(expected = "Relayer scope does not allow this method")]
+    fn test_attested_relayer_starts_without_scope() {
+        let mut contract = setup_attestation();
+        register_attested(&mut contract, 1_000, 2_000);
+        testing_env!(relayer_context(3_000).build());
+        contract.assert_tee_relayer("sign_allowed", TEST_PATH);
+    }
+
+    #[test]
+    fn test_registered_relayer_keeps_scope_when_attesting() {
+        let mut contract = setup_attestation();
+        let relayer: AccountId = "relayer.near".parse().unwrap();
+        contract.register_tee_relayer(relayer.clone(), None);
+        register_attested(&mut contract, 1_000, 2_000);
+        assert_eq!(contract.get_relayer_scope(relayer), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Relayer key revoked")]
+    fn test_removed_relayer_registration_not_replayable() {
+        let mut contract = setup_attestation();
+        register_attested(&mut contract, 1_000, 2_000);
+        testing_env!(get_context("owner.near".parse().unwrap()).build());
+        contract.remove_tee_relayer("relayer.near".parse().unwrap());
+
+        // Same registration, still within MAX_REGISTRATION_AGE_NS
+        register_attested(&mut contract, 1_000, 3_000);
+    }
+
+    #[test]
+    #[should_panic(expected = "Measurement not approved")]
+    fn test_attested_relayer_unapproved_measurement() {
+        let mut contract = setup_attestation();
+        testing_env!(get_context("owner.near".parse().unwrap()).build());
+        contract.revoke_measurement(hex::encode([1u8; 48]), hex::encode([2u8; 32]));
+        register_attested(&mut contract, 1_000, 2_000);
+    }
+
+    #[test]
+    #[should_panic(expected = "Attestation root not trusted")]
+    fn test_attested_relayer_untrusted_root() {
+        let mut contract = setup_attestation();
+        let root = SigningKey::from_bytes(&ATTESTATION_ROOT);
+        contract.remove_attestation_root(hex::encode(root.verifying_key().as_bytes()));
+        register_attested(&mut contract, 1_000, 2_000);
+    }
+
+    #[test]
+    #[should_panic(expected = "Registration does not bind the relayer key")]
+    fn test_attested_relayer_wrong_key() {
+        let mut contract = setup_attestation();
+        testing_env!(relayer_context(2_000).build());
+        // Registration binds another account's key
+        let report_data = registration::relayer_report_data("other.near", relayer_key().as_bytes());
+        contract.register_attested_relayer(signed_registration(&report_data, 1_000));
+    }
+
+    #[test]
+    #[should_panic(expected = "Relayer registration is stale")]
+    fn test_attested_relayer_stale_registration() {
+        let mut contract = setup_attestation();
+        register_attested(
+            &mut contract,
+            1_000,
+            1_001 + registration::MAX_REGISTRATION_AGE_NS,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Relayer attestation missing or expired")]
+    fn test_attested_relayer_expires() {
+        let mut contract = setup_attestation();
+        register_attested(&mut contract, 1_000, 2_000);
+        testing_env!(get_context("owner.near".parse().unwrap()).build());
+        contract.set_require_attestation(true);
+
+        testing_env!(relayer_context(1_000 + registration::ATTESTATION_TTL_NS).build());
+        assert!(!contract.is_tee_relayer("relayer.near".parse().unwrap()));
+        contract.assert_tee_relayer("sign_allowed", TEST_PATH);
+    }
+
+    #[test]
+    fn test_revoked_measurement_disables_relayer() {
+        let mut contract = setup_attestation();
+        register_attested(&mut contract, 1_000, 2_000);
+        let relayer: AccountId = "relayer.near".parse().unwrap();
+        assert!(contract.is_tee_relayer(relayer.clone()));
+
+        testing_env!(get_context("owner.near".parse().unwrap()).build());
+        contract.revoke_measurement(hex::encode([1u8; 48]), hex::encode([2u8; 32]));
+        assert!(!contract.is_tee_relayer(relayer));
+    }
+
+    #[test]
+    #[should_panic(expected = "Relayer attestation missing or expired")]
+    fn test_require_attestation_blocks_unattested_relayer() {
+        let (mut contract, _) = setup_with_wallet();
+        testing_env!(get_context("owner.near".parse().unwrap()).build());
+        contract.set_require_attestation(true);
+
+        testing_env!(get_context("relayer.near".parse().unwrap()).build());
+        contract.assert_tee_relayer("sign_allowed", TEST_PATH);
+    }
+
+    /// Scope for a Solana swap relayer
+    fn solana_scope() -> RelayerScope {
+        RelayerScope {
+            methods: vec![
+                "add_allowed_operation".to_string(),
+                "sign_allowed".to_string(),
+            ],
+            operation_types: vec!["Swap".to_string()],
+            key_types: vec!["Eddsa".to_string()],
+            path_prefixes: Some(vec!["solana-1".to_string()]),
+        }
+    }
+
+    /// Re-register relayer.near with `scope`; leaves the relayer as predecessor
+    fn scope_relayer(contract: &mut PermissionContract, scope: RelayerScope) {
+        testing_env!(get_context("owner.near".parse().unwrap()).build());
+        contract.register_tee_relayer("relayer.near".parse().unwrap(), Some(scope));
+        testing_env!(get_context("relayer.near".parse().unwrap()).build());
+    }
+
+    #[test]
+    fn test_scoped_relayer_within_scope() {
+        let (mut contract, key) = setup_with_wallet();
+        scope_relayer(&mut contract, solana_scope());
+        assert_eq!(
+            contract.get_relayer_scope("relayer.near".parse().unwrap()),
+            Some(solana_scope())
+        );
+
+        let (operation_id, payload) = add_partial_swap(&mut contract, &key);
+        sign_amount(&mut contract, &operation_id, &payload, 1);
+    }
+
+    #[test]
+    fn test_register_relayer_scope() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        let relayer: AccountId = "relayer.near".parse().unwrap();
+
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = PermissionContract::new(owner, "mpc.near".parse().unwrap());
+        contract.register_tee_relayer(relayer.clone(), Some(solana_scope()));
+
+        let logs = near_sdk::test_utils::get_logs();
+        let log: EventLog =
+            near_sdk::serde_json::from_str(logs[1].strip_prefix("EVENT_JSON:").unwrap()).unwrap();
+        assert_eq!(
+            log.event,
+            PermissionEvent::RelayerScopeSet {
+                relayer_account: relayer.clone(),
+                scope: solana_scope(),
+            }
+        );
+
+        // Registering without a scope lifts the restrictions
+        contract.register_tee_relayer(relayer.clone(), None);
+        assert_eq!(contract.get_relayer_scope(relayer.clone()), None);
+
+        contract.register_tee_relayer(relayer.clone(), Some(solana_scope()));
+        contract.remove_tee_relayer(relayer.clone());
+        assert_eq!(contract.get_relayer_scope(relayer), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown relayer method in scope")]
+    fn test_relayer_scope_unknown_method() {
+        let owner: AccountId = "owner.near".parse().unwrap();
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = PermissionContract::new(owner, "mpc.near".parse().unwrap());
+        let scope = RelayerScope {
+            methods: vec!["register_tee_relayer".to_string()],
+            ..solana_scope()
+        };
+        contract.register_tee_relayer("relayer.near".parse().unwrap(), Some(scope));
+    }
+
+    #[test]
+    #[should_panic(expected = "Relayer scope does not allow this method")]
+    fn test_scoped_relayer_method_blocked() {
+        let (mut contract, key) = setup_with_wallet();
+        let scope = RelayerScope {
+            methods: vec!["sign_allowed".to_string()],
+            ..solana_scope()
+        };
+        scope_relayer(&mut contract, scope);
+        add_partial_swap(&mut contract, &key);
+    }
+
+    #[test]
+    #[should_panic(expected = "Relayer scope does not allow this derivation path")]
+    fn test_scoped_relayer_path_blocked() {
+        let (mut contract, key) = setup_with_wallet();
+        let scope = RelayerScope {
+            path_prefixes: Some(vec!["evm-1".to_string()]),
+            ..solana_scope()
+        };
+        scope_relayer(&mut contract, scope);
+        add_partial_swap(&mut contract, &key);
+    }
+
+    #[test]
+    fn test_scope_path_prefix_matches_whole_segments() {
+        let scope = solana_scope();
+        assert!(scope.allows_path("solana-1"));
+        assert!(scope.allows_path("solana-1,test"));
+        assert!(!scope.allows_path("solana-10"));
+        assert!(!scope.allows_path("solana-10,test"));
+        assert!(!scope.allows_path("solana-"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Relayer scope does not allow this operation type")]
+    fn test_scoped_relayer_operation_type_blocked() {
+        let (mut contract, key) = setup_with_wallet();
+        let scope = RelayerScope {
+            operation_types: vec!["LimitOrder".to_string()],
+            ..solana_scope()
+        };
+        scope_relayer(&mut contract, scope);
+        add_partial_swap(&mut contract, &key);
+    }
+
+    #[test]
+    #[should_panic(expected = "Relayer scope does not allow this operation type")]
+    fn test_scoped_relayer_remove_operation_type_blocked() {
+        let (mut contract, key) = setup_with_wallet();
+        let (operation_id, _) = add_partial_swap(&mut contract, &key);
+
+        let scope = RelayerScope {
+            methods: vec!["remove_allowed_operation".to_string()],
+            operation_types: vec!["LimitOrder".to_string()],
+            ..solana_scope()
+        };
+        scope_relayer(&mut contract, scope);
+        let message = remove_message(&operation_id, 2);
+        contract.remove_allowed_operation(
+            TEST_PATH.to_string(),
+            operation_id,
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            2,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Relayer scope does not allow this operation type")]
+    fn test_scoped_relayer_approve_operation_type_blocked() {
+        let (mut contract, key, second_key) = setup_with_threshold();
+        // Proposed through an unscoped relayer
+        let operation = swap_input("user");
+        let message = add_message(&operation, 3);
+        let proposal_id = contract.propose_change(
+            TEST_PATH.to_string(),
+            PathChange::AddOperation {
+                operation: Box::new(operation),
+            },
+            key.sign(&message).to_bytes().to_vec(),
+            message,
+            TEST_WALLET.to_string(),
+            3,
+        );
+
+        let scope = RelayerScope {
+            methods: vec!["approve_proposal".to_string()],
+            operation_types: vec!["LimitOrder".to_string()],
+            ..solana_scope()
+        };
+        scope_relayer(&mut contract, scope);
+        approve_with_second_wallet(&mut contract, &second_key, proposal_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Relayer scope does not allow this key type")]
+    fn test_scoped_relayer_key_type_blocked() {
+        let (mut contract, key) = setup_with_wallet();
+        let (operation_id, payload) = add_partial_swap(&mut contract, &key);
+
+        let scope = RelayerScope {
+            key_types: vec!["Ecdsa".to_string()],
+            ..solana_scope()
+        };
+        scope_relayer(&mut contract, scope);
+        sign_amount(&mut contract, &operation_id, &payload, 1);
     }
 }
@@ -0,0 +1,96 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::{LookupMap, UnorderedMap, UnorderedSet};
+use near_sdk::{env, near, AccountId};
+
+use crate::types::{DerivationPath, LegacyUserPermissions};
+use crate::{PermissionContract, PermissionContractExt};
+
+/// Storage key of the state layout version; the baseline release has none
+const STATE_VERSION_KEY: &[u8] = b"STATE_VERSION";
+/// Layout version of the current PermissionContract state
+/// Bump it with each state layout change and add a branch to `migrate`
+pub const STATE_VERSION: u32 = 1;
+
+/// Record the current state layout version
+pub fn write_state_version() {
+    env::storage_write(
+        STATE_VERSION_KEY,
+        &borsh::to_vec(&STATE_VERSION).expect("State version serializes"),
+    );
+}
+
+/// Layout version of the stored state; 0 for the baseline release
+pub fn stored_state_version() -> u32 {
+    env::storage_read(STATE_VERSION_KEY).map_or(0, |bytes| {
+        u32::try_from_slice(&bytes).unwrap_or_else(|_| env::panic_str("Malformed state version"))
+    })
+}
+
+/// Contract state of the baseline release
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldState {
+    pub owner: AccountId,
+    pub permissions: LookupMap<DerivationPath, LegacyUserPermissions>,
+    pub wallet_to_path: LookupMap<String, DerivationPath>,
+    pub tee_relayers: UnorderedSet<AccountId>,
+    pub mpc_contract: AccountId,
+    pub active_operations: UnorderedSet<String>,
+    pub used_nonces: LookupMap<String, bool>,
+}
+
+#[near]
+impl PermissionContract {
+    /// Re-initialize state after `update_contract` deploys new code
+    /// Branches on the stored state version, so redeploying on the current
+    /// layout keeps its state as is
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let contract = match stored_state_version() {
+            0 => Self::from_old_state(
+                env::state_read().unwrap_or_else(|| env::panic_str("No contract state to migrate")),
+            ),
+            STATE_VERSION => {
+                env::state_read().unwrap_or_else(|| env::panic_str("No contract state to migrate"))
+            }
+            _ => env::panic_str("Unsupported state version"),
+        };
+        write_state_version();
+        contract
+    }
+}
+
+impl PermissionContract {
+    /// State of the baseline release; its permission records stay where
+    /// they are and are upgraded lazily on read
+    fn from_old_state(old: OldState) -> Self {
+        Self {
+            owner: old.owner,
+            permissions: LookupMap::new(b"v"),
+            wallet_to_path: old.wallet_to_path,
+            tee_relayers: old.tee_relayers,
+            mpc_contract: old.mpc_contract,
+            active_operations: old.active_operations,
+            used_nonces: old.used_nonces,
+            wallet_nonces: LookupMap::new(b"o"),
+            allowed_solana_programs: UnorderedSet::new(b"y"),
+            allowed_near_methods: UnorderedSet::new(b"z"),
+            price_oracle: None,
+            price_feeds: UnorderedMap::new(b"f"),
+            roles: UnorderedMap::new(b"r"),
+            pending_owner: None,
+            paused: false,
+            storage_accounts: LookupMap::new(b"s"),
+            path_sponsors: LookupMap::new(b"d"),
+            wallet_paths: LookupMap::new(b"x"),
+            attestation_roots: UnorderedSet::new(b"k"),
+            approved_measurements: UnorderedSet::new(b"m"),
+            attested_relayers: UnorderedMap::new(b"q"),
+            revoked_relayer_keys: UnorderedSet::new(b"g"),
+            require_attestation: false,
+            relayer_scopes: UnorderedMap::new(b"c"),
+            mpc_ed25519_key: None,
+            legacy_permissions: old.permissions,
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use near_sdk::serde::Deserialize;
+use sha3::{Digest, Sha3_256};
+
+use crate::types::MpcSignature;
+
+/// Prefix the chain-signatures contract hashes with the predecessor and path
+const TWEAK_DERIVATION_PREFIX: &str = "near-mpc-recovery v0.1.0 epsilon derivation:";
+
+/// Signature response from the chain-signatures contract
+/// Untagged so responses with or without the "scheme" tag both parse
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(untagged)]
+pub enum SignatureResponse {
+    /// Ecdsa (domain 0)
+    Secp256k1 {
+        big_r: AffinePoint,
+        s: Scalar,
+        recovery_id: u8,
+    },
+    /// Eddsa (domain 1)
+    Ed25519 { signature: Vec<u8> },
+}
+
+/// Compressed curve point as hex (33 bytes)
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AffinePoint {
+    pub affine_point: String,
+}
+
+/// Curve scalar as hex (32 bytes)
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Scalar {
+    pub scalar: String,
+}
+
+impl SignatureResponse {
+    /// Convert to the signature clients attach to their transaction
+    pub fn normalize(self) -> Result<MpcSignature, &'static str> {
+        match self {
+            Self::Secp256k1 {
+                big_r,
+                s,
+                recovery_id,
+            } => {
+                let big_r =
+                    hex::decode(&big_r.affine_point).map_err(|_| "Invalid MPC signature big_r")?;
+                // r is the x coordinate of the compressed point R
+                if big_r.len() != 33 || !matches!(big_r[0], 0x02 | 0x03) {
+                    return Err("Invalid MPC signature big_r");
+                }
+                let s_bytes = hex::decode(&s.scalar).map_err(|_| "Invalid MPC signature s")?;
+                if s_bytes.len() != 32 {
+                    return Err("Invalid MPC signature s");
+                }
+                if recovery_id > 1 {
+                    return Err("Invalid MPC signature recovery_id");
+                }
+                Ok(MpcSignature::Secp256k1 {
+                    r: hex::encode(&big_r[1..]),
+                    s: hex::encode(s_bytes),
+                    v: recovery_id,
+                })
+            }
+            Self::Ed25519 { signature } => {
+                if signature.len() != 64 {
+                    return Err("Invalid MPC signature length");
+                }
+                Ok(MpcSignature::Ed25519 {
+                    signature: hex::encode(signature),
+                })
+            }
+        }
+    }
+}
+
+/// Implicit NEAR account of the Ed25519 key the chain-signatures contract
+/// derives for `predecessor` and `path` from its domain 1 root key:
+/// hex(root + sha3_256(prefix || "predecessor,path") * G)
+pub fn derived_near_account(
+    root_key: &[u8; 32],
+    predecessor: &str,
+    path: &str,
+) -> Result<String, &'static str> {
+    let root = CompressedEdwardsY(*root_key)
+        .decompress()
+        .ok_or("Invalid MPC Ed25519 key")?;
+    let tweak: [u8; 32] = Sha3_256::digest(format!(
+        "{}{},{}",
+        TWEAK_DERIVATION_PREFIX, predecessor, path
+    ))
+    .into();
+    let tweak = curve25519_dalek::Scalar::from_bytes_mod_order(tweak);
+    let derived = root + EdwardsPoint::mul_base(&tweak);
+    Ok(hex::encode(derived.compress().as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> SignatureResponse {
+        near_sdk::serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_normalize_secp256k1_response() {
+        let r = "ab".repeat(32);
+        let s = "cd".repeat(32);
+        let response = parse(&format!(
+            r#"{{"scheme":"Secp256k1","big_r":{{"affine_point":"03{}"}},"s":{{"scalar":"{}"}},"recovery_id":1}}"#,
+            r, s
+        ));
+
+        assert_eq!(
+            response.normalize(),
+            Ok(MpcSignature::Secp256k1 { r, s, v: 1 })
+        );
+    }
+
+    #[test]
+    fn test_normalize_ed25519_response() {
+        let response = parse(&format!(
+            r#"{{"scheme":"Ed25519","signature":{:?}}}"#,
+            vec![7u8; 64]
+        ));
+
+        assert_eq!(
+            response.normalize(),
+            Ok(MpcSignature::Ed25519 {
+                signature: "07".repeat(64)
+            })
+        );
+    }
+
+    #[test]
+    fn test_normalize_rejects_malformed_responses() {
+        let short_r = parse(&format!(
+            r#"{{"big_r":{{"affine_point":"{}"}},"s":{{"scalar":"{}"}},"recovery_id":0}}"#,
+            "02".repeat(32),
+            "cd".repeat(32)
+        ));
+        assert_eq!(short_r.normalize(), Err("Invalid MPC signature big_r"));
+
+        let short_signature = parse(r#"{"signature":[1,2,3]}"#);
+        assert_eq!(
+            short_signature.normalize(),
+            Err("Invalid MPC signature length")
+        );
+    }
+
+    #[test]
+    fn test_derived_near_account_adds_path_tweak() {
+        // Root key of secret scalar 1 is the base point
+        let root = curve25519_dalek::constants::ED25519_BASEPOINT_COMPRESSED.to_bytes();
+        let account = derived_near_account(&root, "permission.near", "near-1,user").unwrap();
+
+        let tweak: [u8; 32] = Sha3_256::digest(
+            "near-mpc-recovery v0.1.0 epsilon derivation:permission.near,near-1,user",
+        )
+        .into();
+        let secret =
+            curve25519_dalek::Scalar::ONE + curve25519_dalek::Scalar::from_bytes_mod_order(tweak);
+        let expected = EdwardsPoint::mul_base(&secret).compress();
+        assert_eq!(account, hex::encode(expected.as_bytes()));
+        assert_eq!(account.len(), 64);
+
+        let other_path = derived_near_account(&root, "permission.near", "near-1,other").unwrap();
+        assert_ne!(account, other_path);
+    }
+
+    #[test]
+    fn test_derived_near_account_rejects_invalid_root() {
+        // y = 2 is not on the curve
+        let mut root = [0u8; 32];
+        root[0] = 2;
+        assert_eq!(
+            derived_near_account(&root, "permission.near", "near-1,user"),
+            Err("Invalid MPC Ed25519 key")
+        );
+    }
+}
@@ -0,0 +1,511 @@
+use near_sdk::borsh::BorshDeserialize;
+use near_sdk::env;
+use near_sdk::serde::Deserialize;
+
+use crate::types::AllowedOperation;
+
+/// Action::FunctionCall borsh tag
+const ACTION_FUNCTION_CALL: u8 = 2;
+/// Action::Transfer borsh tag
+const ACTION_TRANSFER: u8 = 3;
+
+/// PublicKey::ED25519 borsh tag
+const KEY_TYPE_ED25519: u8 = 0;
+/// PublicKey::SECP256K1 borsh tag
+const KEY_TYPE_SECP256K1: u8 = 1;
+
+/// Deposit NEP-141 transfers must attach
+const ONE_YOCTO: u128 = 1;
+/// Largest storage_deposit a token registration may attach (0.1 NEAR)
+const MAX_STORAGE_DEPOSIT: u128 = 100_000_000_000_000_000_000_000;
+
+/// Actions the permission contract knows how to check
+#[derive(Debug, PartialEq)]
+pub enum NearAction {
+    FunctionCall {
+        method_name: String,
+        args: Vec<u8>,
+        gas: u64,
+        deposit: u128,
+    },
+    Transfer {
+        deposit: u128,
+    },
+}
+
+/// Decoded `near_primitives::transaction::Transaction` (V0), keeping the
+/// fields the allowlist checks
+pub struct NearTransaction {
+    /// Account the transaction is signed for; must be the path's own account
+    pub signer_id: String,
+    pub receiver_id: String,
+    pub actions: Vec<NearAction>,
+}
+
+/// Arguments of storage_deposit; account_id defaults to the caller
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+struct StorageDepositArgs {
+    account_id: Option<String>,
+}
+
+/// Arguments of ft_transfer / ft_transfer_call
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+struct FtTransferArgs {
+    receiver_id: String,
+    amount: String,
+}
+
+fn read<T: BorshDeserialize>(reader: &mut &[u8]) -> Result<T, &'static str> {
+    T::deserialize_reader(reader).map_err(|_| "Malformed NEAR transaction")
+}
+
+/// Decode a borsh-serialized transaction; only FunctionCall and Transfer actions are accepted
+pub fn parse_transaction(payload: &[u8]) -> Result<NearTransaction, &'static str> {
+    let mut reader = payload;
+
+    let signer_id: String = read(&mut reader)?;
+    let key_len = match read::<u8>(&mut reader)? {
+        KEY_TYPE_ED25519 => 32,
+        KEY_TYPE_SECP256K1 => 64,
+        _ => return Err("Malformed NEAR transaction"),
+    };
+    if reader.len() < key_len {
+        return Err("Malformed NEAR transaction");
+    }
+    reader = &reader[key_len..];
+    let _nonce: u64 = read(&mut reader)?;
+    let receiver_id: String = read(&mut reader)?;
+    let _block_hash: [u8; 32] = read(&mut reader)?;
+
+    let action_count: u32 = read(&mut reader)?;
+    let mut actions = Vec::new();
+    for _ in 0..action_count {
+        let action = match read::<u8>(&mut reader)? {
+            ACTION_FUNCTION_CALL => NearAction::FunctionCall {
+                method_name: read(&mut reader)?,
+                args: read(&mut reader)?,
+                gas: read(&mut reader)?,
+                deposit: read(&mut reader)?,
+            },
+            ACTION_TRANSFER => NearAction::Transfer {
+                deposit: read(&mut reader)?,
+            },
+            _ => return Err("Unsupported NEAR action"),
+        };
+        actions.push(action);
+    }
+
+    if !reader.is_empty() {
+        return Err("Trailing bytes after NEAR transaction");
+    }
+
+    Ok(NearTransaction {
+        signer_id,
+        receiver_id,
+        actions,
+    })
+}
+
+/// Hash the MPC signs for a NEAR transaction
+pub fn signing_hash(payload: &[u8]) -> [u8; 32] {
+    env::sha256_array(payload)
+}
+
+/// Check a NEAR transaction against an allowed operation:
+/// - the transaction must be signed for `path_account`, the path's own account
+/// - Transfer actions must pay the destination
+/// - ft_transfer / ft_transfer_call must target a source or target asset
+///   token, attach exactly 1 yoctoNEAR and pay the destination
+/// - one storage_deposit on those tokens may attach up to 0.1 NEAR, for the
+///   path's own account or the destination
+/// - other methods must pass `is_allowed_method(receiver, method)`
+///
+/// Token amounts of the source asset and deposits beyond the above count
+/// towards `max_amount`, the amount being executed; native deposits require
+/// NEAR as the source asset. The storage deposit counts too when NEAR is the
+/// source asset.
+pub fn validate_near_payload(
+    operation: &AllowedOperation,
+    payload: &[u8],
+    path_account: &str,
+    max_amount: u128,
+    is_allowed_method: impl Fn(&str, &str) -> bool,
+) -> Result<(), &'static str> {
+    let tx = parse_transaction(payload)?;
+    if tx.signer_id != path_account {
+        return Err("Transaction signer is not the path's account");
+    }
+
+    let source_asset = operation.operation_type.source_asset();
+    let target_asset = operation.operation_type.target_asset();
+    let receiver_is_source = tx.receiver_id == source_asset;
+    let receiver_is_asset = receiver_is_source || tx.receiver_id == target_asset;
+
+    let mut source_total: u128 = 0;
+    let mut native_total: u128 = 0;
+    let mut storage_deposit: Option<u128> = None;
+
+    for action in &tx.actions {
+        match action {
+            NearAction::Transfer { deposit } => {
+                if tx.receiver_id != operation.destination_address {
+                    return Err("Transfer recipient does not match destination");
+                }
+                native_total = native_total.saturating_add(*deposit);
+            }
+            NearAction::FunctionCall {
+                method_name,
+                args,
+                deposit,
+                ..
+            } => match method_name.as_str() {
+                "ft_transfer" | "ft_transfer_call" => {
+                    if !receiver_is_asset {
+                        return Err("Transfer asset not allowed by operation");
+                    }
+                    if *deposit != ONE_YOCTO {
+                        return Err("Token transfer must attach 1 yoctoNEAR");
+                    }
+                    let args: FtTransferArgs = near_sdk::serde_json::from_slice(args)
+                        .map_err(|_| "Malformed token transfer arguments")?;
+                    if args.receiver_id != operation.destination_address {
+                        return Err("Transfer recipient does not match destination");
+                    }
+                    let amount: u128 = args
+                        .amount
+                        .parse()
+                        .map_err(|_| "Malformed token transfer arguments")?;
+                    if receiver_is_source {
+                        source_total = source_total.saturating_add(amount);
+                    }
+                }
+                "storage_deposit" if receiver_is_asset => {
+                    if storage_deposit.is_some() {
+                        return Err("Only one storage deposit allowed");
+                    }
+                    if *deposit > MAX_STORAGE_DEPOSIT {
+                        return Err("Storage deposit too large");
+                    }
+                    let args: StorageDepositArgs = near_sdk::serde_json::from_slice(args)
+                        .map_err(|_| "Malformed storage deposit arguments")?;
+                    if args.account_id.is_some_and(|account_id| {
+                        account_id != tx.signer_id && account_id != operation.destination_address
+                    }) {
+                        return Err("Storage deposit account not allowed");
+                    }
+                    storage_deposit = Some(*deposit);
+                }
+                method => {
+                    if !is_allowed_method(&tx.receiver_id, method) {
+                        return Err("NEAR method not allowed");
+                    }
+                    native_total = native_total.saturating_add(*deposit);
+                }
+            },
+        }
+    }
+
+    if native_total > 0 {
+        if !source_asset.eq_ignore_ascii_case("NEAR") {
+            return Err("Attached deposit not allowed by operation");
+        }
+        source_total = source_total.saturating_add(native_total);
+    }
+    if source_asset.eq_ignore_ascii_case("NEAR") {
+        source_total = source_total.saturating_add(storage_deposit.unwrap_or(0));
+    }
+
+    if source_total > max_amount {
+        return Err("Transfer amount exceeds max_amount");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::AllowedOperationType;
+    use near_sdk::borsh;
+    use near_sdk::json_types::U128;
+
+    const USDC: &str = "17208628f84f5d6ad33f0da3bbbeb27ffcb398eac501a31bd6ad2011e36133a1";
+
+    fn transaction(receiver_id: &str, actions: &[Vec<u8>]) -> Vec<u8> {
+        let mut out = borsh::to_vec("signer.near").unwrap();
+        out.push(KEY_TYPE_ED25519);
+        out.extend_from_slice(&[4u8; 32]);
+        out.extend(borsh::to_vec(&7u64).unwrap());
+        out.extend(borsh::to_vec(receiver_id).unwrap());
+        out.extend_from_slice(&[5u8; 32]);
+        out.extend(borsh::to_vec(&(actions.len() as u32)).unwrap());
+        for action in actions {
+            out.extend_from_slice(action);
+        }
+        out
+    }
+
+    fn function_call(method: &str, args: &str, deposit: u128) -> Vec<u8> {
+        let mut out = vec![ACTION_FUNCTION_CALL];
+        out.extend(borsh::to_vec(method).unwrap());
+        out.extend(borsh::to_vec(args.as_bytes()).unwrap());
+        out.extend(borsh::to_vec(&30_000_000_000_000u64).unwrap());
+        out.extend(borsh::to_vec(&deposit).unwrap());
+        out
+    }
+
+    fn transfer(deposit: u128) -> Vec<u8> {
+        let mut out = vec![ACTION_TRANSFER];
+        out.extend(borsh::to_vec(&deposit).unwrap());
+        out
+    }
+
+    fn operation(source: &str, target: &str, max_amount: u128) -> AllowedOperation {
+        AllowedOperation {
+            operation_id: "op".to_string(),
+            derivation_path: "near-1,test".to_string(),
+            operation_type: AllowedOperationType::Swap {
+                source_asset: source.to_string(),
+                target_asset: target.to_string(),
+                max_amount: U128(max_amount),
+            },
+            source_chain: "near".to_string(),
+            destination_address: "user.near".to_string(),
+            destination_chain: "near".to_string(),
+            slippage_bps: 50,
+            expires_at: None,
+            executed: false,
+            nonce: 1,
+            created_at: 0,
+            recurring: None,
+            execution_count: 0,
+            last_executed_at: None,
+            consumed_amount: U128(0),
+        }
+    }
+
+    #[test]
+    fn test_parse_transaction() {
+        let payload = transaction("user.near", &[transfer(10)]);
+        let tx = parse_transaction(&payload).unwrap();
+
+        assert_eq!(tx.receiver_id, "user.near");
+        assert_eq!(tx.actions, vec![NearAction::Transfer { deposit: 10 }]);
+        assert_eq!(signing_hash(&payload), env::sha256_array(&payload));
+    }
+
+    #[test]
+    fn test_native_transfer() {
+        let payload = transaction("user.near", &[transfer(1_000)]);
+        assert!(validate_near_payload(
+            &operation("NEAR", USDC, 1_000),
+            &payload,
+            "signer.near",
+            1_000,
+            |_, _| { false }
+        )
+        .is_ok());
+        assert_eq!(
+            validate_near_payload(
+                &operation("NEAR", USDC, 999),
+                &payload,
+                "signer.near",
+                999,
+                |_, _| false
+            ),
+            Err("Transfer amount exceeds max_amount")
+        );
+        assert_eq!(
+            validate_near_payload(
+                &operation(USDC, "NEAR", 1_000),
+                &payload,
+                "signer.near",
+                1_000,
+                |_, _| { false }
+            ),
+            Err("Attached deposit not allowed by operation")
+        );
+    }
+
+    #[test]
+    fn test_ft_transfer_call() {
+        let args = r#"{"receiver_id":"user.near","amount":"500","msg":""}"#;
+        let payload = transaction(
+            USDC,
+            &[
+                function_call("storage_deposit", "{}", 1_250_000_000_000_000_000_000),
+                function_call("ft_transfer_call", args, 1),
+            ],
+        );
+        assert!(validate_near_payload(
+            &operation(USDC, "NEAR", 500),
+            &payload,
+            "signer.near",
+            500,
+            |_, _| false
+        )
+        .is_ok());
+        assert_eq!(
+            validate_near_payload(
+                &operation(USDC, "NEAR", 499),
+                &payload,
+                "signer.near",
+                499,
+                |_, _| false
+            ),
+            Err("Transfer amount exceeds max_amount")
+        );
+    }
+
+    #[test]
+    fn test_storage_deposit_limits() {
+        let transfer_args = r#"{"receiver_id":"user.near","amount":"500"}"#;
+        let deposit = 1_250_000_000_000_000_000_000;
+        let op = operation(USDC, "NEAR", 500);
+
+        let payload = transaction(
+            USDC,
+            &[
+                function_call("storage_deposit", r#"{"account_id":"user.near"}"#, deposit),
+                function_call("ft_transfer", transfer_args, 1),
+            ],
+        );
+        assert!(validate_near_payload(&op, &payload, "signer.near", 500, |_, _| false).is_ok());
+
+        let payload = transaction(
+            USDC,
+            &[
+                function_call("storage_deposit", "{}", deposit),
+                function_call("storage_deposit", "{}", deposit),
+            ],
+        );
+        assert_eq!(
+            validate_near_payload(&op, &payload, "signer.near", 500, |_, _| false),
+            Err("Only one storage deposit allowed")
+        );
+
+        let payload = transaction(
+            USDC,
+            &[function_call(
+                "storage_deposit",
+                r#"{"account_id":"attacker.near"}"#,
+                deposit,
+            )],
+        );
+        assert_eq!(
+            validate_near_payload(&op, &payload, "signer.near", 500, |_, _| false),
+            Err("Storage deposit account not allowed")
+        );
+    }
+
+    #[test]
+    fn test_storage_deposit_counts_towards_near_amount() {
+        let deposit = 1_250_000_000_000_000_000_000;
+        let payload = transaction(
+            USDC,
+            &[function_call(
+                "storage_deposit",
+                r#"{"account_id":"signer.near"}"#,
+                deposit,
+            )],
+        );
+        let op = operation("NEAR", USDC, deposit);
+        assert!(validate_near_payload(&op, &payload, "signer.near", deposit, |_, _| false).is_ok());
+        assert_eq!(
+            validate_near_payload(&op, &payload, "signer.near", deposit - 1, |_, _| false),
+            Err("Transfer amount exceeds max_amount")
+        );
+    }
+
+    #[test]
+    fn test_other_signer_rejected() {
+        let payload = transaction("user.near", &[transfer(10)]);
+        assert_eq!(
+            validate_near_payload(
+                &operation("NEAR", USDC, 10),
+                &payload,
+                "other.near",
+                10,
+                |_, _| false
+            ),
+            Err("Transaction signer is not the path's account")
+        );
+    }
+
+    #[test]
+    fn test_ft_transfer_to_other_receiver_rejected() {
+        let args = r#"{"receiver_id":"attacker.near","amount":"1"}"#;
+        let payload = transaction(USDC, &[function_call("ft_transfer", args, 1)]);
+        assert_eq!(
+            validate_near_payload(
+                &operation(USDC, "NEAR", 500),
+                &payload,
+                "signer.near",
+                500,
+                |_, _| false
+            ),
+            Err("Transfer recipient does not match destination")
+        );
+    }
+
+    #[test]
+    fn test_ft_transfer_on_other_token_rejected() {
+        let args = r#"{"receiver_id":"user.near","amount":"1"}"#;
+        let payload = transaction("other.token.near", &[function_call("ft_transfer", args, 1)]);
+        assert_eq!(
+            validate_near_payload(
+                &operation(USDC, "NEAR", 500),
+                &payload,
+                "signer.near",
+                500,
+                |_, _| false
+            ),
+            Err("Transfer asset not allowed by operation")
+        );
+    }
+
+    #[test]
+    fn test_other_methods_require_allowlist() {
+        let payload = transaction(
+            "contract.main.burrow.near",
+            &[function_call("simple_withdraw", "{}", 1)],
+        );
+        let op = operation("NEAR", USDC, 10);
+
+        assert_eq!(
+            validate_near_payload(&op, &payload, "signer.near", 10, |_, _| false),
+            Err("NEAR method not allowed")
+        );
+        assert!(
+            validate_near_payload(&op, &payload, "signer.near", 10, |receiver, method| {
+                receiver == "contract.main.burrow.near" && method == "simple_withdraw"
+            })
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_unsupported_actions_rejected() {
+        // Action::DeleteAccount
+        let mut delete_account = vec![7u8];
+        delete_account.extend(borsh::to_vec("attacker.near").unwrap());
+        let payload = transaction("user.near", &[delete_account]);
+
+        assert_eq!(
+            parse_transaction(&payload).err(),
+            Some("Unsupported NEAR action")
+        );
+    }
+
+    #[test]
+    fn test_trailing_bytes_rejected() {
+        let mut payload = transaction("user.near", &[transfer(1)]);
+        payload.push(0);
+        assert_eq!(
+            parse_transaction(&payload).err(),
+            Some("Trailing bytes after NEAR transaction")
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use near_sdk::json_types::{I64, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, ext_contract, Gas, PromiseResult};
+
+/// Trigger prices are quote-asset per price-asset, scaled by 10^PRICE_DECIMALS
+pub const PRICE_DECIMALS: i32 = 8;
+/// Oldest oracle price accepted for a conditional operation
+pub const MAX_PRICE_AGE_SECS: u64 = 60;
+/// Gas for each oracle price query
+pub const GAS_FOR_PRICE_QUERY: Gas = Gas::from_tgas(10);
+/// Quote asset priced at exactly 1 without an oracle feed
+pub const USD: &str = "USD";
+
+/// Pyth-style price oracle (see pyth-oracle.near)
+/// Only the generated `ext_price_oracle` is called
+#[allow(dead_code)]
+#[ext_contract(ext_price_oracle)]
+pub trait PriceOracle {
+    fn get_price_no_older_than(&self, price_id: String, age: u64) -> Option<OraclePrice>;
+}
+
+/// Price of one whole asset in USD: price * 10^expo
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OraclePrice {
+    pub price: I64,
+    pub conf: U64,
+    pub expo: i32,
+    pub publish_time: i64,
+}
+
+impl OraclePrice {
+    /// Price used for a USD quote asset
+    pub fn usd() -> Self {
+        Self {
+            price: I64(1),
+            conf: U64(0),
+            expo: 0,
+            publish_time: i64::MAX,
+        }
+    }
+}
+
+/// Oracle price returned by promise `index`; panics if it failed or was stale
+pub fn oracle_price_result(index: u64) -> OraclePrice {
+    match env::promise_result(index) {
+        PromiseResult::Successful(data) => near_sdk::serde_json::from_slice(&data)
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| env::panic_str("Oracle price unavailable or stale")),
+        PromiseResult::Failed => env::panic_str("Oracle price query failed"),
+    }
+}
+
+/// Price of `base` in units of `quote`, scaled by 10^PRICE_DECIMALS
+pub fn quote_price(base: &OraclePrice, quote: &OraclePrice) -> Result<u128, &'static str> {
+    if base.price.0 <= 0 || quote.price.0 <= 0 {
+        return Err("Oracle price must be positive");
+    }
+    let base_price = base.price.0 as u128;
+    let quote_price = quote.price.0 as u128;
+
+    let exponent = base.expo as i64 + PRICE_DECIMALS as i64 - quote.expo as i64;
+    let scale = 10u128
+        .checked_pow(
+            exponent
+                .unsigned_abs()
+                .try_into()
+                .map_err(|_| "Oracle price out of range")?,
+        )
+        .ok_or("Oracle price out of range")?;
+
+    if exponent >= 0 {
+        base_price
+            .checked_mul(scale)
+            .map(|n| n / quote_price)
+            .ok_or("Oracle price out of range")
+    } else {
+        quote_price
+            .checked_mul(scale)
+            .map(|d| base_price / d)
+            .ok_or("Oracle price out of range")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(price: i64, expo: i32) -> OraclePrice {
+        OraclePrice {
+            price: I64(price),
+            conf: U64(0),
+            expo,
+            publish_time: 0,
+        }
+    }
+
+    #[test]
+    fn test_quote_price_in_usd() {
+        // SOL at $150.12345678 with Pyth's usual expo of -8
+        let sol = price(15_012_345_678, -8);
+        assert_eq!(quote_price(&sol, &OraclePrice::usd()), Ok(15_012_345_678));
+    }
+
+    #[test]
+    fn test_quote_price_cross_rate() {
+        // ETH $3000 (expo -8) in SOL $150 (expo -5) = 20 SOL
+        let eth = price(300_000_000_000, -8);
+        let sol = price(15_000_000, -5);
+        assert_eq!(quote_price(&eth, &sol), Ok(20 * 100_000_000));
+    }
+
+    #[test]
+    fn test_quote_price_rejects_non_positive() {
+        assert_eq!(
+            quote_price(&price(0, -8), &OraclePrice::usd()),
+            Err("Oracle price must be positive")
+        );
+        assert_eq!(
+            quote_price(&OraclePrice::usd(), &price(-1, -8)),
+            Err("Oracle price must be positive")
+        );
+    }
+}
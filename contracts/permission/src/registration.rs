@@ -0,0 +1,223 @@
+use near_sdk::{AccountId, PublicKey};
+
+use crate::signature::verify_ed25519_signature;
+
+/// Registration format version accepted by `parse_registration`
+pub const REGISTRATION_VERSION: u16 = 1;
+/// How long a relayer attestation stays valid after its registration was issued
+/// (7 days, nanoseconds)
+pub const ATTESTATION_TTL_NS: u64 = 7 * 24 * 60 * 60 * 1_000_000_000;
+/// Oldest registration accepted (1 hour, nanoseconds)
+pub const MAX_REGISTRATION_AGE_NS: u64 = 60 * 60 * 1_000_000_000;
+/// Domain prefix of the message a root key signs to certify a signing key
+pub const SIGNING_KEY_DOMAIN: &[u8] = b"shade-registration-key:";
+
+/// Bytes covered by the signing key's signature
+const REGISTRATION_BODY_LEN: usize = 2 + 8 + 48 + 32 + 64 + 32;
+/// Body, registration signature, root key and root certification
+pub const REGISTRATION_LEN: usize = REGISTRATION_BODY_LEN + 64 + 32 + 64;
+
+/// Relayer registration issued by a certified signing key
+///
+/// This is not a hardware quote format; the contract does not verify TDX or
+/// SGX quotes. An off-chain verifier the admin trusts (a root key) checks the
+/// enclave's attestation itself and certifies an Ed25519 signing key, which
+/// then signs registrations naming the code it observed. The contract only
+/// checks that signature chain, so it trusts the verifier's judgement.
+///
+/// Layout (integers little-endian):
+/// - version: u16
+/// - issued_at: u64, nanoseconds
+/// - measurement: 48 bytes, code measurement reported by the verifier
+/// - compose_hash: 32 bytes, hash of the app compose file
+/// - report_data: 64 bytes, `relayer_report_data` of the relayer key
+/// - signing_key: 32 bytes, Ed25519 key that issued the registration
+/// - signature: 64 bytes, signing key over all fields above
+/// - root_key: 32 bytes, Ed25519 root that certified the signing key
+/// - root_signature: 64 bytes, root key over SIGNING_KEY_DOMAIN || signing_key
+#[derive(Clone, Debug, PartialEq)]
+pub struct Registration<'a> {
+    pub issued_at: u64,
+    pub measurement: &'a [u8],
+    pub compose_hash: &'a [u8],
+    pub report_data: &'a [u8],
+    pub signing_key: &'a [u8],
+    pub root_key: &'a [u8],
+    body: &'a [u8],
+    signature: &'a [u8],
+    root_signature: &'a [u8],
+}
+
+/// Split a registration into its fields; does not check signatures
+pub fn parse_registration(registration: &[u8]) -> Result<Registration<'_>, &'static str> {
+    if registration.len() != REGISTRATION_LEN {
+        return Err("Malformed relayer registration");
+    }
+    let (body, rest) = registration.split_at(REGISTRATION_BODY_LEN);
+    let (signature, rest) = rest.split_at(64);
+    let (root_key, root_signature) = rest.split_at(32);
+
+    let (version, fields) = body.split_at(2);
+    if u16::from_le_bytes([version[0], version[1]]) != REGISTRATION_VERSION {
+        return Err("Unsupported relayer registration version");
+    }
+    let (issued_at, fields) = fields.split_at(8);
+    let (measurement, fields) = fields.split_at(48);
+    let (compose_hash, fields) = fields.split_at(32);
+    let (report_data, signing_key) = fields.split_at(64);
+
+    Ok(Registration {
+        issued_at: u64::from_le_bytes(issued_at.try_into().unwrap()),
+        measurement,
+        compose_hash,
+        report_data,
+        signing_key,
+        root_key,
+        body,
+        signature,
+        root_signature,
+    })
+}
+
+/// Check the registration's signature chain: `is_trusted_root` must accept
+/// the root key, the root must certify the signing key, and the signing key
+/// must sign the registration body
+pub fn verify_registration(
+    registration: &Registration,
+    is_trusted_root: impl Fn(&[u8]) -> bool,
+) -> Result<(), &'static str> {
+    if !is_trusted_root(registration.root_key) {
+        return Err("Attestation root not trusted");
+    }
+    let mut certified = SIGNING_KEY_DOMAIN.to_vec();
+    certified.extend_from_slice(registration.signing_key);
+    if !verify_ed25519_signature(
+        registration.root_key,
+        &certified,
+        registration.root_signature,
+    ) {
+        return Err("Invalid signing key certification");
+    }
+    if !verify_ed25519_signature(
+        registration.signing_key,
+        registration.body,
+        registration.signature,
+    ) {
+        return Err("Invalid relayer registration signature");
+    }
+    Ok(())
+}
+
+/// Report data a relayer's registration must carry:
+/// sha256(account_id || 0x00 || public_key), zero-padded to 64 bytes.
+/// `public_key` is the NEAR access key as bytes (curve type, then key)
+pub fn relayer_report_data(account_id: &str, public_key: &[u8]) -> [u8; 64] {
+    let mut preimage = account_id.as_bytes().to_vec();
+    preimage.push(0);
+    preimage.extend_from_slice(public_key);
+    let mut report_data = [0u8; 64];
+    report_data[..32].copy_from_slice(&near_sdk::env::sha256_array(&preimage));
+    report_data
+}
+
+/// Lowercase hex of a measurement pair, checking the byte lengths
+pub fn normalize_measurement(
+    measurement: &str,
+    compose_hash: &str,
+) -> Result<(String, String), &'static str> {
+    match (hex::decode(measurement), hex::decode(compose_hash)) {
+        (Ok(m), Ok(c)) if m.len() == 48 && c.len() == 32 => Ok((hex::encode(m), hex::encode(c))),
+        _ => Err("Measurement must be 48 bytes and compose hash 32 bytes of hex"),
+    }
+}
+
+/// Registry key of an approved measurement pair
+pub fn measurement_key(measurement: &str, compose_hash: &str) -> String {
+    format!("{}:{}", measurement, compose_hash)
+}
+
+/// Revocation key of a relayer account's access key
+pub fn relayer_key_id(account_id: &AccountId, public_key: &PublicKey) -> String {
+    format!("{}:{}", account_id, String::from(public_key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn registration(root: &SigningKey, report_data: &[u8; 64]) -> Vec<u8> {
+        let signing_key = SigningKey::from_bytes(&[5u8; 32]);
+        let mut body = REGISTRATION_VERSION.to_le_bytes().to_vec();
+        body.extend_from_slice(&10u64.to_le_bytes());
+        body.extend_from_slice(&[1u8; 48]);
+        body.extend_from_slice(&[2u8; 32]);
+        body.extend_from_slice(report_data);
+        body.extend_from_slice(signing_key.verifying_key().as_bytes());
+
+        let mut certified = SIGNING_KEY_DOMAIN.to_vec();
+        certified.extend_from_slice(signing_key.verifying_key().as_bytes());
+
+        let mut registration = body.clone();
+        registration.extend_from_slice(&signing_key.sign(&body).to_bytes());
+        registration.extend_from_slice(root.verifying_key().as_bytes());
+        registration.extend_from_slice(&root.sign(&certified).to_bytes());
+        registration
+    }
+
+    #[test]
+    fn test_registration_roundtrip() {
+        let root = SigningKey::from_bytes(&[4u8; 32]);
+        let bytes = registration(&root, &[7u8; 64]);
+        let parsed = parse_registration(&bytes).unwrap();
+
+        assert_eq!(parsed.issued_at, 10);
+        assert_eq!(parsed.measurement, &[1u8; 48]);
+        assert_eq!(parsed.compose_hash, &[2u8; 32]);
+        assert_eq!(parsed.report_data, &[7u8; 64]);
+        assert_eq!(
+            verify_registration(&parsed, |key| key == root.verifying_key().as_bytes()),
+            Ok(())
+        );
+        assert_eq!(
+            verify_registration(&parsed, |_| false),
+            Err("Attestation root not trusted")
+        );
+    }
+
+    #[test]
+    fn test_tampered_registration_rejected() {
+        let root = SigningKey::from_bytes(&[4u8; 32]);
+        let mut bytes = registration(&root, &[7u8; 64]);
+        // Claim a different measurement
+        bytes[10] ^= 1;
+        let parsed = parse_registration(&bytes).unwrap();
+        assert_eq!(
+            verify_registration(&parsed, |_| true),
+            Err("Invalid relayer registration signature")
+        );
+
+        // Swap in a signing key the root never certified
+        let mut bytes = registration(&root, &[7u8; 64]);
+        bytes[REGISTRATION_BODY_LEN - 1] ^= 1;
+        let parsed = parse_registration(&bytes).unwrap();
+        assert_eq!(
+            verify_registration(&parsed, |_| true),
+            Err("Invalid signing key certification")
+        );
+    }
+
+    #[test]
+    fn test_malformed_registration_rejected() {
+        assert_eq!(
+            parse_registration(&[0u8; 10]),
+            Err("Malformed relayer registration")
+        );
+        let mut bytes = vec![0u8; REGISTRATION_LEN];
+        bytes[0] = 2;
+        assert_eq!(
+            parse_registration(&bytes),
+            Err("Unsupported relayer registration version")
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
 use near_sdk::env;
 
 /// Verify NEAR/Solana Ed25519 signature using NEAR's built-in verifier
@@ -22,61 +23,151 @@ pub fn verify_ed25519_signature(public_key: &[u8], message: &[u8], signature: &[
     env::ed25519_verify(&sig, message, &pk)
 }
 
+/// NEP-413 tag prefix: 2^31 + 413
+/// Keeps signed messages from ever being valid transactions
+pub const NEP413_TAG: u32 = 2_147_484_061;
+
+/// NEP-413 `signMessage` payload
+/// See: https://github.com/near/NEPs/blob/master/neps/nep-0413.md
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq)]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct Nep413Payload {
+    pub message: String,
+    pub nonce: [u8; 32],
+    pub recipient: String,
+    pub callback_url: Option<String>,
+}
+
 /// Verify NEAR Ed25519 signature
-/// NEAR uses SHA-256 hash of NEP-413 formatted message
-pub fn verify_near_signature(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
-    // NEAR signs SHA-256 hash of the message (NEP-413 format)
-    let hash = env::sha256(message);
+/// NEAR wallets sign SHA-256 of borsh(NEP413_TAG) || borsh(payload)
+pub fn verify_near_signature(public_key: &[u8], payload: &Nep413Payload, signature: &[u8]) -> bool {
+    let mut data = NEP413_TAG.to_le_bytes().to_vec();
+    data.extend(near_sdk::borsh::to_vec(payload).unwrap_or_default());
+    let hash = env::sha256(&data);
     verify_ed25519_signature(public_key, &hash, signature)
 }
 
 /// Verify Solana Ed25519 signature
-/// Solana signs raw message bytes directly
+/// Solana signs raw message bytes directly, either the message itself or
+/// an off-chain message envelope (see `solana_message_content`)
 pub fn verify_solana_signature(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
     // Solana signs raw message
     verify_ed25519_signature(public_key, message, signature)
 }
 
+/// Signing domain prefixing Solana off-chain messages
+pub const SOLANA_OFFCHAIN_DOMAIN: &[u8; 16] = b"\xffsolana offchain";
+/// Domain, version, format and length bytes before the body
+const SOLANA_OFFCHAIN_HEADER_LEN: usize = 20;
+/// Body limit for formats Ledger can display (packet size minus header)
+const SOLANA_OFFCHAIN_MAX_LEN_LEDGER: usize = 1232 - SOLANA_OFFCHAIN_HEADER_LEN;
+/// Body limit for extended UTF-8
+const SOLANA_OFFCHAIN_MAX_LEN: usize = u16::MAX as usize - SOLANA_OFFCHAIN_HEADER_LEN;
+
+/// Message content a Solana wallet approved
+/// Messages starting with the off-chain signing domain must be a valid
+/// version 0 envelope (domain, version, format, u16 LE length, body) and
+/// are unwrapped to their body; anything else was signed raw
+pub fn solana_message_content(message: &[u8]) -> Result<&[u8], &'static str> {
+    let Some(envelope) = message.strip_prefix(SOLANA_OFFCHAIN_DOMAIN) else {
+        return Ok(message);
+    };
+
+    let [version, format, len_lo, len_hi, body @ ..] = envelope else {
+        return Err("Malformed Solana off-chain message");
+    };
+    if *version != 0 {
+        return Err("Unsupported Solana off-chain message version");
+    }
+    if body.is_empty() || body.len() != u16::from_le_bytes([*len_lo, *len_hi]) as usize {
+        return Err("Malformed Solana off-chain message");
+    }
+
+    let valid = match format {
+        // Restricted ASCII
+        0 => {
+            body.len() <= SOLANA_OFFCHAIN_MAX_LEN_LEDGER
+                && body.iter().all(|b| (0x20..=0x7e).contains(b))
+        }
+        // Limited UTF-8
+        1 => body.len() <= SOLANA_OFFCHAIN_MAX_LEN_LEDGER && core::str::from_utf8(body).is_ok(),
+        // Extended UTF-8
+        2 => body.len() <= SOLANA_OFFCHAIN_MAX_LEN && core::str::from_utf8(body).is_ok(),
+        _ => return Err("Unsupported Solana off-chain message format"),
+    };
+    if !valid {
+        return Err("Solana off-chain message does not match its format");
+    }
+
+    Ok(body)
+}
+
+/// secp256k1 curve order / 2; signatures with a larger `s` are malleable
+const SECP256K1_HALF_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
 /// Verify EVM secp256k1 signature (personal_sign format)
 /// Uses NEAR's ecrecover to recover the address
 pub fn verify_evm_signature(address: &str, message: &[u8], signature: &[u8]) -> bool {
-    // EVM signatures are 65 bytes: r (32) + s (32) + v (1)
-    if signature.len() != 65 {
-        return false;
-    }
+    // Create Ethereum signed message hash
+    let prefixed_message = create_eth_signed_message(message);
+    let message_hash = env::keccak256(&prefixed_message);
+
+    verify_evm_digest(address, &message_hash, signature)
+}
 
+/// Verify EVM secp256k1 signature over a precomputed 32-byte digest
+/// (e.g. an EIP-712 typed data hash)
+pub fn verify_evm_digest(address: &str, digest: &[u8], signature: &[u8]) -> bool {
     // Parse expected address (remove 0x prefix if present)
     let expected_address = match parse_evm_address(address) {
         Some(addr) => addr,
         None => return false,
     };
 
-    // Create Ethereum signed message hash
-    let prefixed_message = create_eth_signed_message(message);
-    let message_hash = env::keccak256(&prefixed_message);
+    match recover_evm_address(digest, signature) {
+        Some(recovered) => recovered == expected_address,
+        None => false,
+    }
+}
 
-    // Parse signature components
-    let v = signature[64];
-    // Recovery ID: v is either 27/28, convert to 0/1
-    let recovery_id = if v >= 27 { v - 27 } else { v };
+/// Recover the signer address from a 65-byte r || s || v signature
+fn recover_evm_address(digest: &[u8], signature: &[u8]) -> Option<[u8; 20]> {
+    // EVM signatures are 65 bytes: r (32) + s (32) + v (1)
+    if signature.len() != 65 {
+        return None;
+    }
+
+    let recovery_id = normalize_recovery_id(signature[64])?;
+
+    // Reject high-s signatures; (r, n - s) would verify for the same message
+    if signature[32..64] > SECP256K1_HALF_ORDER[..] {
+        return None;
+    }
 
     // Use NEAR's ecrecover to get public key
-    let recovered_pubkey = match env::ecrecover(&message_hash, signature, recovery_id, true)
-    {
-        Some(pubkey) => pubkey,
-        None => return false,
-    };
+    let recovered_pubkey = env::ecrecover(digest, &signature[..64], recovery_id, true)?;
 
     // Derive address from recovered public key (last 20 bytes of keccak256 hash)
     let pubkey_hash = env::keccak256(&recovered_pubkey);
-    let recovered_address: [u8; 20] = pubkey_hash[12..32].try_into().unwrap_or([0u8; 20]);
+    pubkey_hash[12..32].try_into().ok()
+}
 
-    // Compare addresses
-    recovered_address == expected_address
+/// Map a signature `v` byte to a 0/1 recovery ID
+/// Accepts raw (0/1), Ethereum (27/28) and EIP-155 (chain_id * 2 + 35/36) forms
+fn normalize_recovery_id(v: u8) -> Option<u8> {
+    match v {
+        0 | 1 => Some(v),
+        27 | 28 => Some(v - 27),
+        35.. => Some((v - 35) % 2),
+        _ => None,
+    }
 }
 
 /// Parse EVM address from hex string
-fn parse_evm_address(address: &str) -> Option<[u8; 20]> {
+pub(crate) fn parse_evm_address(address: &str) -> Option<[u8; 20]> {
     let addr_str = address.strip_prefix("0x").unwrap_or(address);
     if addr_str.len() != 40 {
         return None;
@@ -122,6 +213,154 @@ mod tests {
         assert_eq!(result, result2);
     }
 
+    #[test]
+    fn test_verify_near_signature_nep413() {
+        use ed25519_dalek::{Signer, SigningKey};
+
+        let key = SigningKey::from_bytes(&[3u8; 32]);
+        let payload = Nep413Payload {
+            message: "Register wallet".to_string(),
+            nonce: [9u8; 32],
+            recipient: "permission.near".to_string(),
+            callback_url: None,
+        };
+
+        // Hand-rolled borsh layout, as wallets produce it
+        let mut signed = NEP413_TAG.to_le_bytes().to_vec();
+        signed.extend((payload.message.len() as u32).to_le_bytes());
+        signed.extend(payload.message.as_bytes());
+        signed.extend(payload.nonce);
+        signed.extend((payload.recipient.len() as u32).to_le_bytes());
+        signed.extend(payload.recipient.as_bytes());
+        signed.push(0);
+        let signature = key.sign(&env::sha256(&signed)).to_bytes();
+
+        let public_key = key.verifying_key().to_bytes();
+        assert!(verify_near_signature(&public_key, &payload, &signature));
+
+        // Any change to the payload invalidates the signature
+        let other = Nep413Payload {
+            recipient: "other.near".to_string(),
+            ..payload.clone()
+        };
+        assert!(!verify_near_signature(&public_key, &other, &signature));
+        let with_callback = Nep413Payload {
+            callback_url: Some("https://app.example".to_string()),
+            ..payload
+        };
+        assert!(!verify_near_signature(
+            &public_key,
+            &with_callback,
+            &signature
+        ));
+    }
+
+    fn offchain_message(version: u8, format: u8, body: &[u8]) -> Vec<u8> {
+        let mut out = SOLANA_OFFCHAIN_DOMAIN.to_vec();
+        out.push(version);
+        out.push(format);
+        out.extend((body.len() as u16).to_le_bytes());
+        out.extend_from_slice(body);
+        out
+    }
+
+    #[test]
+    fn test_solana_message_content() {
+        let body = b"Register wallet for derivation path: solana-1,test with nonce: 0";
+
+        // Raw messages pass through; envelopes are unwrapped
+        assert_eq!(solana_message_content(body), Ok(&body[..]));
+        for format in 0..=2 {
+            let envelope = offchain_message(0, format, body);
+            assert_eq!(solana_message_content(&envelope), Ok(&body[..]));
+        }
+
+        let utf8 = "Registrera plånbok".as_bytes();
+        assert_eq!(
+            solana_message_content(&offchain_message(0, 0, utf8)),
+            Err("Solana off-chain message does not match its format")
+        );
+        assert_eq!(
+            solana_message_content(&offchain_message(0, 1, utf8)),
+            Ok(utf8)
+        );
+    }
+
+    #[test]
+    fn test_solana_offchain_header_validated() {
+        let body = b"hello";
+
+        assert_eq!(
+            solana_message_content(&offchain_message(1, 0, body)),
+            Err("Unsupported Solana off-chain message version")
+        );
+        assert_eq!(
+            solana_message_content(&offchain_message(0, 3, body)),
+            Err("Unsupported Solana off-chain message format")
+        );
+
+        let mut trailing = offchain_message(0, 0, body);
+        trailing.push(b'!');
+        assert_eq!(
+            solana_message_content(&trailing),
+            Err("Malformed Solana off-chain message")
+        );
+        assert_eq!(
+            solana_message_content(&offchain_message(0, 0, b"")),
+            Err("Malformed Solana off-chain message")
+        );
+        assert_eq!(
+            solana_message_content(SOLANA_OFFCHAIN_DOMAIN),
+            Err("Malformed Solana off-chain message")
+        );
+    }
+
+    #[test]
+    fn test_normalize_recovery_id() {
+        assert_eq!(normalize_recovery_id(0), Some(0));
+        assert_eq!(normalize_recovery_id(1), Some(1));
+        assert_eq!(normalize_recovery_id(27), Some(0));
+        assert_eq!(normalize_recovery_id(28), Some(1));
+        // EIP-155 on chain 1: 37 / 38
+        assert_eq!(normalize_recovery_id(37), Some(0));
+        assert_eq!(normalize_recovery_id(38), Some(1));
+        assert_eq!(normalize_recovery_id(2), None);
+        assert_eq!(normalize_recovery_id(29), None);
+    }
+
+    #[test]
+    fn test_verify_evm_signature() {
+        use k256::ecdsa::SigningKey;
+
+        let key = SigningKey::from_bytes(&[5u8; 32].into()).unwrap();
+        let encoded = key.verifying_key().to_encoded_point(false);
+        let address = format!(
+            "0x{}",
+            hex::encode(&env::keccak256(&encoded.as_bytes()[1..])[12..])
+        );
+
+        let message = b"Register wallet";
+        let digest = env::keccak256(&create_eth_signed_message(message));
+        let (sig, recid) = key.sign_prehash_recoverable(&digest).unwrap();
+        let mut signature = sig.to_bytes().to_vec();
+        signature.push(recid.to_byte() + 27);
+
+        assert!(verify_evm_signature(&address, message, &signature));
+
+        // Same signature with an EIP-155 style v
+        let mut eip155 = signature.clone();
+        eip155[64] = recid.to_byte() + 37;
+        assert!(verify_evm_signature(&address, message, &eip155));
+
+        // Malleable twin (r, n - s, !v) must be rejected
+        let high_s = k256::ecdsa::Signature::from_scalars(sig.r(), -*sig.s()).unwrap();
+        let mut malleable = high_s.to_bytes().to_vec();
+        malleable.push((recid.to_byte() ^ 1) + 27);
+        assert!(!verify_evm_signature(&address, message, &malleable));
+
+        assert!(!verify_evm_signature(&address, b"other", &signature));
+    }
+
     #[test]
     fn test_create_eth_signed_message() {
         let message = b"Hello";
@@ -129,4 +368,30 @@ mod tests {
         let expected = "\x19Ethereum Signed Message:\n5Hello";
         assert_eq!(result, expected.as_bytes());
     }
+
+    #[test]
+    fn test_nep413_payload_matches_client_fixture() {
+        // Built by createNep413Payload in src/permission/types.ts
+        let fixtures: near_sdk::serde_json::Value =
+            near_sdk::serde_json::from_str(include_str!("../tests/fixtures/messages.json"))
+                .unwrap();
+        let fixture = &fixtures["nep413"];
+        let bytes = hex::decode(fixture["payload"].as_str().unwrap()).unwrap();
+
+        let payload = Nep413Payload::try_from_slice(&bytes).unwrap();
+        assert_eq!(payload.message, fixture["message"].as_str().unwrap());
+        assert_eq!(
+            hex::encode(payload.nonce),
+            fixture["nonce"].as_str().unwrap()
+        );
+        assert_eq!(payload.recipient, fixture["recipient"].as_str().unwrap());
+        assert_eq!(payload.callback_url, None);
+
+        let mut data = NEP413_TAG.to_le_bytes().to_vec();
+        data.extend_from_slice(&bytes);
+        assert_eq!(
+            format!("0x{}", hex::encode(env::sha256(&data))),
+            fixture["signing_hash"].as_str().unwrap()
+        );
+    }
 }
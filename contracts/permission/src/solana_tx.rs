@@ -0,0 +1,550 @@
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use near_sdk::env;
+
+use crate::types::AllowedOperation;
+
+pub type Pubkey = [u8; 32];
+
+/// System program (11111111111111111111111111111111)
+const SYSTEM_PROGRAM: Pubkey = [0u8; 32];
+const TOKEN_PROGRAM: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+const TOKEN_2022_PROGRAM: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
+const ASSOCIATED_TOKEN_PROGRAM: &str = "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL";
+const COMPUTE_BUDGET_PROGRAM: &str = "ComputeBudget111111111111111111111111111111";
+const MEMO_PROGRAM: &str = "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr";
+/// Wrapped SOL mint, accepted as an alias for native SOL transfers
+const WRAPPED_SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+
+/// System program instruction: Transfer { lamports }
+const SYSTEM_IX_TRANSFER: u32 = 2;
+/// Token program instruction: TransferChecked { amount, decimals }
+const TOKEN_IX_TRANSFER_CHECKED: u8 = 12;
+/// Token program instruction: SyncNative
+const TOKEN_IX_SYNC_NATIVE: u8 = 17;
+
+/// Compiled instruction with account indices into the message account list
+pub struct CompiledInstruction {
+    pub program_id_index: u8,
+    pub accounts: Vec<u8>,
+    pub data: Vec<u8>,
+}
+
+/// Decoded legacy or v0 Solana message
+pub struct SolanaMessage {
+    /// Static account keys; accounts loaded from lookup tables are not included
+    pub account_keys: Vec<Pubkey>,
+    pub instructions: Vec<CompiledInstruction>,
+}
+
+impl SolanaMessage {
+    /// Resolve an account index; None for accounts loaded from lookup tables,
+    /// whose addresses are not visible on-chain
+    fn account(&self, index: u8) -> Option<&Pubkey> {
+        self.account_keys.get(index as usize)
+    }
+}
+
+/// Value transfer found in a message
+#[derive(Debug, PartialEq)]
+pub struct SolanaTransfer {
+    /// SPL mint, None for native SOL
+    pub mint: Option<Pubkey>,
+    /// Token program owning the mint, None for native SOL
+    pub token_program: Option<Pubkey>,
+    /// Receiving account (wallet for SOL, token account for SPL)
+    pub destination: Pubkey,
+    pub amount: u64,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], &'static str> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .ok_or("Malformed Solana message")?;
+        let slice = self
+            .data
+            .get(self.pos..end)
+            .ok_or("Malformed Solana message")?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, &'static str> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn pubkey(&mut self) -> Result<Pubkey, &'static str> {
+        let mut key = [0u8; 32];
+        key.copy_from_slice(self.take(32)?);
+        Ok(key)
+    }
+
+    /// Solana compact-u16 ("shortvec") length prefix
+    fn compact_u16(&mut self) -> Result<usize, &'static str> {
+        let mut value = 0usize;
+        for i in 0..3 {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as usize) << (7 * i);
+            if byte & 0x80 == 0 {
+                return if value > u16::MAX as usize {
+                    Err("Malformed Solana message")
+                } else {
+                    Ok(value)
+                };
+            }
+        }
+        Err("Malformed Solana message")
+    }
+
+    fn short_vec(&mut self) -> Result<&'a [u8], &'static str> {
+        let len = self.compact_u16()?;
+        self.take(len)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos == self.data.len()
+    }
+}
+
+/// Parse a serialized legacy or v0 Solana message (the bytes that get signed)
+pub fn parse_message(bytes: &[u8]) -> Result<SolanaMessage, &'static str> {
+    let mut reader = Reader::new(bytes);
+
+    // Versioned messages set the top bit of the first byte
+    let first = *bytes.first().ok_or("Malformed Solana message")?;
+    let versioned = first & 0x80 != 0;
+    if versioned {
+        if first & 0x7f != 0 {
+            return Err("Unsupported Solana message version");
+        }
+        reader.u8()?;
+    }
+
+    // Header: required signatures, readonly signed, readonly unsigned
+    reader.take(3)?;
+
+    let key_count = reader.compact_u16()?;
+    let mut account_keys = Vec::with_capacity(key_count);
+    for _ in 0..key_count {
+        account_keys.push(reader.pubkey()?);
+    }
+
+    // Recent blockhash
+    reader.take(32)?;
+
+    let ix_count = reader.compact_u16()?;
+    let mut instructions = Vec::with_capacity(ix_count);
+    for _ in 0..ix_count {
+        let program_id_index = reader.u8()?;
+        let accounts = reader.short_vec()?.to_vec();
+        let data = reader.short_vec()?.to_vec();
+        instructions.push(CompiledInstruction {
+            program_id_index,
+            accounts,
+            data,
+        });
+    }
+
+    let mut loaded_accounts = 0usize;
+    if versioned {
+        let lookup_count = reader.compact_u16()?;
+        for _ in 0..lookup_count {
+            reader.pubkey()?;
+            loaded_accounts += reader.short_vec()?.len();
+            loaded_accounts += reader.short_vec()?.len();
+        }
+    }
+
+    if !reader.is_empty() {
+        return Err("Trailing bytes after Solana message");
+    }
+
+    let total_accounts = account_keys.len() + loaded_accounts;
+    for ix in &instructions {
+        // Program IDs can never come from lookup tables
+        if ix.program_id_index as usize >= account_keys.len() {
+            return Err("Invalid program index in Solana message");
+        }
+        if ix.accounts.iter().any(|&i| i as usize >= total_accounts) {
+            return Err("Invalid account index in Solana message");
+        }
+    }
+
+    Ok(SolanaMessage {
+        account_keys,
+        instructions,
+    })
+}
+
+/// Walk all instructions, returning value transfers.
+/// Programs outside the built-in set must pass `is_allowed_program`,
+/// and their instructions are not inspected further.
+pub fn extract_transfers(
+    message: &SolanaMessage,
+    is_allowed_program: impl Fn(&str) -> bool,
+) -> Result<Vec<SolanaTransfer>, &'static str> {
+    let mut transfers = Vec::new();
+
+    for ix in &message.instructions {
+        let program_id = message.account_keys[ix.program_id_index as usize];
+        let program = bs58::encode(program_id).into_string();
+        let account = |pos: usize| -> Result<Pubkey, &'static str> {
+            let index = *ix.accounts.get(pos).ok_or("Missing instruction account")?;
+            message
+                .account(index)
+                .copied()
+                .ok_or("Transfer account loaded from lookup table")
+        };
+
+        if program_id == SYSTEM_PROGRAM {
+            if ix.data.len() != 12 || read_u32(&ix.data[0..4]) != SYSTEM_IX_TRANSFER {
+                return Err("Unsupported system instruction");
+            }
+            transfers.push(SolanaTransfer {
+                mint: None,
+                token_program: None,
+                destination: account(1)?,
+                amount: read_u64(&ix.data[4..12]),
+            });
+        } else if program == TOKEN_PROGRAM || program == TOKEN_2022_PROGRAM {
+            match ix.data.first() {
+                Some(&TOKEN_IX_TRANSFER_CHECKED) if ix.data.len() == 10 => {
+                    transfers.push(SolanaTransfer {
+                        mint: Some(account(1)?),
+                        token_program: Some(program_id),
+                        destination: account(2)?,
+                        amount: read_u64(&ix.data[1..9]),
+                    });
+                }
+                Some(&TOKEN_IX_SYNC_NATIVE) => {}
+                _ => return Err("Unsupported token instruction"),
+            }
+        } else if program == ASSOCIATED_TOKEN_PROGRAM {
+            // Create / CreateIdempotent only move rent from the payer
+            if !matches!(ix.data.as_slice(), [] | [0] | [1]) {
+                return Err("Unsupported associated token instruction");
+            }
+        } else if program == COMPUTE_BUDGET_PROGRAM || program == MEMO_PROGRAM {
+            // No value movement
+        } else if !is_allowed_program(&program) {
+            return Err("Solana program not allowed");
+        }
+    }
+
+    Ok(transfers)
+}
+
+/// Check a Solana message payload against an allowed operation:
+/// every transfer must move the source or target asset to the destination
+/// (directly or via its associated token account), and the source asset
+/// total must not exceed `max_amount`, the amount being executed
+pub fn validate_solana_payload(
+    operation: &AllowedOperation,
+    payload: &[u8],
+    max_amount: u128,
+    is_allowed_program: impl Fn(&str) -> bool,
+) -> Result<(), &'static str> {
+    let message = parse_message(payload)?;
+    let transfers = extract_transfers(&message, is_allowed_program)?;
+
+    let destination = decode_pubkey(&operation.destination_address);
+    let source_asset = operation.operation_type.source_asset();
+    let target_asset = operation.operation_type.target_asset();
+
+    let mut source_total: u128 = 0;
+    for transfer in &transfers {
+        let is_source = asset_matches(source_asset, transfer.mint.as_ref());
+        if !is_source && !asset_matches(target_asset, transfer.mint.as_ref()) {
+            return Err("Transfer asset not allowed by operation");
+        }
+
+        let recipient_ok = match (&destination, &transfer.mint, &transfer.token_program) {
+            (Some(dest), None, _) => transfer.destination == *dest,
+            (Some(dest), Some(mint), Some(token_program)) => {
+                transfer.destination == *dest
+                    || associated_token_address(dest, token_program, mint)
+                        .is_some_and(|ata| transfer.destination == ata)
+            }
+            _ => false,
+        };
+        if !recipient_ok {
+            return Err("Transfer recipient does not match destination");
+        }
+
+        if is_source {
+            source_total += transfer.amount as u128;
+        }
+    }
+
+    if source_total > max_amount {
+        return Err("Transfer amount exceeds max_amount");
+    }
+
+    Ok(())
+}
+
+/// Asset strings hold base58 mint addresses; "SOL" or the wrapped SOL mint match native transfers
+fn asset_matches(asset: &str, mint: Option<&Pubkey>) -> bool {
+    match mint {
+        None => asset == "SOL" || asset == WRAPPED_SOL_MINT,
+        Some(mint) => bs58::encode(mint).into_string() == asset,
+    }
+}
+
+pub fn decode_pubkey(address: &str) -> Option<Pubkey> {
+    let bytes = bs58::decode(address).into_vec().ok()?;
+    bytes.try_into().ok()
+}
+
+/// Associated token account for (wallet, token program, mint)
+pub fn associated_token_address(
+    wallet: &Pubkey,
+    token_program: &Pubkey,
+    mint: &Pubkey,
+) -> Option<Pubkey> {
+    let ata_program = decode_pubkey(ASSOCIATED_TOKEN_PROGRAM)?;
+    find_program_address(&[wallet, token_program, mint], &ata_program)
+}
+
+/// Solana `find_program_address`: highest bump whose hash is off the ed25519 curve
+fn find_program_address(seeds: &[&[u8]], program_id: &Pubkey) -> Option<Pubkey> {
+    (0..=u8::MAX).rev().find_map(|bump| {
+        let mut data = Vec::new();
+        for seed in seeds {
+            data.extend_from_slice(seed);
+        }
+        data.push(bump);
+        data.extend_from_slice(program_id);
+        data.extend_from_slice(b"ProgramDerivedAddress");
+
+        let hash = env::sha256_array(&data);
+        let on_curve = CompressedEdwardsY(hash).decompress().is_some();
+        (!on_curve).then_some(hash)
+    })
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes.try_into().unwrap_or_default())
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes.try_into().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::AllowedOperationType;
+    use near_sdk::json_types::U128;
+
+    const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    const OWNER: &str = "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM";
+
+    fn key(address: &str) -> Pubkey {
+        decode_pubkey(address).unwrap()
+    }
+
+    /// Build a legacy message from account keys and (program index, accounts, data)
+    fn legacy_message(keys: &[Pubkey], instructions: &[(u8, Vec<u8>, Vec<u8>)]) -> Vec<u8> {
+        let mut out = vec![1, 0, 0, keys.len() as u8];
+        for k in keys {
+            out.extend_from_slice(k);
+        }
+        out.extend_from_slice(&[9u8; 32]);
+        out.push(instructions.len() as u8);
+        for (program, accounts, data) in instructions {
+            out.push(*program);
+            out.push(accounts.len() as u8);
+            out.extend_from_slice(accounts);
+            out.push(data.len() as u8);
+            out.extend_from_slice(data);
+        }
+        out
+    }
+
+    fn system_transfer(lamports: u64) -> Vec<u8> {
+        let mut data = SYSTEM_IX_TRANSFER.to_le_bytes().to_vec();
+        data.extend_from_slice(&lamports.to_le_bytes());
+        data
+    }
+
+    fn transfer_checked(amount: u64) -> Vec<u8> {
+        let mut data = vec![TOKEN_IX_TRANSFER_CHECKED];
+        data.extend_from_slice(&amount.to_le_bytes());
+        data.push(6);
+        data
+    }
+
+    fn operation(source: &str, target: &str, max_amount: u128) -> AllowedOperation {
+        AllowedOperation {
+            operation_id: "op".to_string(),
+            derivation_path: "solana-1,test".to_string(),
+            operation_type: AllowedOperationType::Swap {
+                source_asset: source.to_string(),
+                target_asset: target.to_string(),
+                max_amount: U128(max_amount),
+            },
+            source_chain: "solana".to_string(),
+            destination_address: OWNER.to_string(),
+            destination_chain: "solana".to_string(),
+            slippage_bps: 50,
+            expires_at: None,
+            executed: false,
+            nonce: 1,
+            created_at: 0,
+            recurring: None,
+            execution_count: 0,
+            last_executed_at: None,
+            consumed_amount: U128(0),
+        }
+    }
+
+    #[test]
+    fn test_compact_u16() {
+        assert_eq!(Reader::new(&[0x05]).compact_u16(), Ok(5));
+        assert_eq!(Reader::new(&[0x80, 0x01]).compact_u16(), Ok(128));
+        assert_eq!(Reader::new(&[0xff, 0xff, 0x03]).compact_u16(), Ok(65535));
+        assert!(Reader::new(&[0xff, 0xff, 0x04]).compact_u16().is_err());
+    }
+
+    #[test]
+    fn test_associated_token_address() {
+        // Known USDC ATA for the owner wallet
+        let ata = associated_token_address(&key(OWNER), &key(TOKEN_PROGRAM), &key(USDC_MINT));
+        assert_eq!(
+            bs58::encode(ata.unwrap()).into_string(),
+            "FGETo8T8wMcN2wCjav8VK6eh3dLk63evNDPxzLSJra8B"
+        );
+    }
+
+    #[test]
+    fn test_sol_transfer_within_limit() {
+        let payer = [1u8; 32];
+        let msg = legacy_message(
+            &[payer, key(OWNER), SYSTEM_PROGRAM],
+            &[(2, vec![0, 1], system_transfer(1_000))],
+        );
+
+        assert!(
+            validate_solana_payload(&operation("SOL", USDC_MINT, 1_000), &msg, 1_000, |_| false)
+                .is_ok()
+        );
+        assert_eq!(
+            validate_solana_payload(&operation("SOL", USDC_MINT, 999), &msg, 999, |_| false),
+            Err("Transfer amount exceeds max_amount")
+        );
+    }
+
+    #[test]
+    fn test_sol_transfer_to_other_recipient_rejected() {
+        let msg = legacy_message(
+            &[[1u8; 32], [2u8; 32], SYSTEM_PROGRAM],
+            &[(2, vec![0, 1], system_transfer(1))],
+        );
+
+        assert_eq!(
+            validate_solana_payload(&operation("SOL", USDC_MINT, 1_000), &msg, 1_000, |_| false),
+            Err("Transfer recipient does not match destination")
+        );
+    }
+
+    #[test]
+    fn test_spl_transfer_checked_to_ata() {
+        let source = [1u8; 32];
+        let authority = [3u8; 32];
+        let ata =
+            associated_token_address(&key(OWNER), &key(TOKEN_PROGRAM), &key(USDC_MINT)).unwrap();
+        let keys = [authority, source, ata, key(USDC_MINT), key(TOKEN_PROGRAM)];
+        let msg = legacy_message(&keys, &[(4, vec![1, 3, 2, 0], transfer_checked(500))]);
+
+        assert!(
+            validate_solana_payload(&operation(USDC_MINT, "SOL", 500), &msg, 500, |_| false)
+                .is_ok()
+        );
+
+        // Wrong mint for the operation
+        assert_eq!(
+            validate_solana_payload(&operation("SOL", "SOL", 500), &msg, 500, |_| false),
+            Err("Transfer asset not allowed by operation")
+        );
+    }
+
+    #[test]
+    fn test_unknown_program_rejected_unless_allowed() {
+        let jupiter = key("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4");
+        let msg = legacy_message(&[[1u8; 32], jupiter], &[(1, vec![0], vec![42])]);
+        let op = operation(USDC_MINT, "SOL", 500);
+
+        assert_eq!(
+            validate_solana_payload(&op, &msg, op.operation_type.max_amount(), |_| false),
+            Err("Solana program not allowed")
+        );
+        assert!(
+            validate_solana_payload(&op, &msg, op.operation_type.max_amount(), |p| p
+                == "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4")
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_v0_message_with_lookup_tables() {
+        let payer = [1u8; 32];
+        let mut msg = vec![0x80];
+        msg.extend(legacy_message(
+            &[payer, key(OWNER), SYSTEM_PROGRAM],
+            &[(2, vec![0, 1], system_transfer(10))],
+        ));
+        // One lookup table with one writable and one readonly account
+        msg.push(1);
+        msg.extend_from_slice(&[5u8; 32]);
+        msg.extend_from_slice(&[1, 0, 1, 1]);
+
+        assert_eq!(parse_message(&msg).unwrap().account_keys.len(), 3);
+        assert!(
+            validate_solana_payload(&operation("SOL", USDC_MINT, 10), &msg, 10, |_| false).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_transfer_to_lookup_account_rejected() {
+        let mut msg = vec![0x80];
+        msg.extend(legacy_message(
+            &[[1u8; 32], SYSTEM_PROGRAM],
+            &[(1, vec![0, 2], system_transfer(10))],
+        ));
+        msg.push(1);
+        msg.extend_from_slice(&[5u8; 32]);
+        msg.extend_from_slice(&[1, 0, 0]);
+
+        assert_eq!(
+            validate_solana_payload(&operation("SOL", USDC_MINT, 10), &msg, 10, |_| false),
+            Err("Transfer account loaded from lookup table")
+        );
+    }
+
+    #[test]
+    fn test_malformed_messages_rejected() {
+        assert!(parse_message(&[]).is_err());
+        assert_eq!(
+            parse_message(&[0x81, 1, 0, 0]).err(),
+            Some("Unsupported Solana message version")
+        );
+
+        let mut msg = legacy_message(&[[1u8; 32]], &[]);
+        msg.push(0);
+        assert_eq!(
+            parse_message(&msg).err(),
+            Some("Trailing bytes after Solana message")
+        );
+    }
+}
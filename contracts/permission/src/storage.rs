@@ -0,0 +1,201 @@
+use near_sdk::json_types::U128;
+use near_sdk::{assert_one_yocto, env, near, AccountId, NearToken, Promise, StorageUsage};
+
+use crate::types::{
+    DerivationPath, PermissionEvent, StorageAccount, StorageBalance, StorageBalanceBounds,
+};
+use crate::{PermissionContract, PermissionContractExt};
+
+/// Bytes charged for a sponsor's own storage record
+pub const STORAGE_ACCOUNT_BYTES: StorageUsage = 128;
+
+impl StorageAccount {
+    fn used_cost(&self) -> u128 {
+        env::storage_byte_cost().as_yoctonear() * self.used_bytes as u128
+    }
+
+    fn balance(&self) -> StorageBalance {
+        StorageBalance {
+            total: U128(self.deposit),
+            available: U128(self.deposit.saturating_sub(self.used_cost())),
+        }
+    }
+}
+
+/// NEP-145 storage management
+/// Balances belong to sponsoring accounts; each derivation path is charged to
+/// the account that submitted its first mutation (normally the TEE relayer)
+#[near]
+impl PermissionContract {
+    /// Deposit NEAR to cover storage for paths `account_id` sponsors
+    #[payable]
+    pub fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance {
+        let amount = env::attached_deposit().as_yoctonear();
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let min = self.storage_balance_bounds().min.0;
+
+        let (account, refund) = match self.storage_accounts.get(&account_id) {
+            Some(account) if registration_only == Some(true) => (account, amount),
+            Some(mut account) => {
+                account.deposit += amount;
+                (account, 0)
+            }
+            None => {
+                assert!(
+                    amount >= min,
+                    "Deposit must cover the storage balance minimum"
+                );
+                let refund = if registration_only == Some(true) {
+                    amount - min
+                } else {
+                    0
+                };
+                let account = StorageAccount {
+                    deposit: amount - refund,
+                    used_bytes: STORAGE_ACCOUNT_BYTES,
+                };
+                (account, refund)
+            }
+        };
+        self.storage_accounts.insert(&account_id, &account);
+
+        if amount > refund {
+            PermissionEvent::StorageDeposited {
+                account_id,
+                amount: U128(amount - refund),
+            }
+            .emit();
+        }
+        if refund > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(NearToken::from_yoctonear(refund));
+        }
+        account.balance()
+    }
+
+    /// Withdraw available storage balance; all of it when `amount` is omitted
+    #[payable]
+    pub fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let mut account = self
+            .storage_accounts
+            .get(&account_id)
+            .unwrap_or_else(|| env::panic_str("Account is not registered"));
+
+        let available = account.balance().available.0;
+        let amount = amount.map(|a| a.0).unwrap_or(available);
+        assert!(
+            amount <= available,
+            "Withdraw amount exceeds available balance"
+        );
+
+        account.deposit -= amount;
+        self.storage_accounts.insert(&account_id, &account);
+        if amount > 0 {
+            PermissionEvent::StorageWithdrawn {
+                account_id: account_id.clone(),
+                amount: U128(amount),
+            }
+            .emit();
+            Promise::new(account_id).transfer(NearToken::from_yoctonear(amount));
+        }
+        account.balance()
+    }
+
+    /// Close a storage account that no longer sponsors any storage
+    /// Returns false if the caller is not registered
+    #[payable]
+    pub fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        assert_one_yocto();
+        assert!(force != Some(true), "Force unregister is not supported");
+        let account_id = env::predecessor_account_id();
+        let Some(account) = self.storage_accounts.get(&account_id) else {
+            return false;
+        };
+        assert!(
+            account.used_bytes <= STORAGE_ACCOUNT_BYTES,
+            "Account still pays for derivation path storage"
+        );
+
+        self.storage_accounts.remove(&account_id);
+        PermissionEvent::StorageWithdrawn {
+            account_id: account_id.clone(),
+            amount: U128(account.deposit),
+        }
+        .emit();
+        Promise::new(account_id).transfer(NearToken::from_yoctonear(account.deposit));
+        true
+    }
+
+    pub fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        StorageBalanceBounds {
+            min: U128(env::storage_byte_cost().as_yoctonear() * STORAGE_ACCOUNT_BYTES as u128),
+            max: None,
+        }
+    }
+
+    pub fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.storage_accounts.get(&account_id).map(|a| a.balance())
+    }
+
+    /// Get the account paying for a derivation path's storage
+    pub fn get_storage_sponsor(&self, derivation_path: DerivationPath) -> Option<AccountId> {
+        self.path_sponsors.get(&derivation_path)
+    }
+}
+
+impl PermissionContract {
+    /// Make the caller the storage sponsor of a path that has none
+    pub(crate) fn ensure_storage_sponsor(&mut self, derivation_path: &DerivationPath) {
+        if !self.path_sponsors.contains_key(derivation_path) {
+            self.path_sponsors
+                .insert(derivation_path, &env::predecessor_account_id());
+        }
+    }
+
+    /// Charge or refund a path's sponsor for storage used since `initial_usage`
+    /// Paths without a sponsor (created before storage management) are
+    /// covered by the contract
+    pub(crate) fn charge_storage(
+        &mut self,
+        derivation_path: &DerivationPath,
+        initial_usage: StorageUsage,
+    ) {
+        if let Some(sponsor) = self.path_sponsors.get(derivation_path) {
+            self.charge_sponsor(&sponsor, initial_usage);
+        }
+    }
+
+    /// Charge or refund `sponsor` for storage used since `initial_usage`
+    pub(crate) fn charge_sponsor(&mut self, sponsor: &AccountId, initial_usage: StorageUsage) {
+        let usage = env::storage_usage();
+        if usage == initial_usage {
+            return;
+        }
+        let Some(mut account) = self.storage_accounts.get(sponsor) else {
+            assert!(
+                usage < initial_usage,
+                "Storage sponsor has no storage deposit"
+            );
+            return;
+        };
+
+        if usage > initial_usage {
+            account.used_bytes += usage - initial_usage;
+            assert!(
+                account.used_cost() <= account.deposit,
+                "Insufficient storage deposit"
+            );
+        } else {
+            account.used_bytes = account
+                .used_bytes
+                .saturating_sub(initial_usage - usage)
+                .max(STORAGE_ACCOUNT_BYTES);
+        }
+        self.storage_accounts.insert(sponsor, &account);
+    }
+}
@@ -2,13 +2,16 @@ use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::collections::UnorderedMap;
 use near_sdk::json_types::U128;
 use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, AccountId, PublicKey};
 use schemars::JsonSchema;
 
 /// Derivation path for MPC key (e.g., "solana-1,user-xyz")
 pub type DerivationPath = String;
 
 /// Supported wallet types for signature verification
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema,
+)]
 #[serde(crate = "near_sdk::serde")]
 pub enum WalletType {
     /// NEAR Ed25519 with NEP-413 format
@@ -17,10 +20,51 @@ pub enum WalletType {
     Solana,
     /// EVM secp256k1 ECDSA (personal_sign)
     Evm,
+    /// EVM secp256k1 ECDSA over EIP-712 typed data (eth_signTypedData_v4)
+    Eip712,
+}
+
+impl WalletType {
+    /// Variant name, as serialized to JSON
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Near => "Near",
+            Self::Solana => "Solana",
+            Self::Evm => "Evm",
+            Self::Eip712 => "Eip712",
+        }
+    }
+}
+
+/// Roles that can be granted for routine administration
+/// The owner holds every role implicitly
+#[derive(
+    BorshDeserialize,
+    BorshSerialize,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    JsonSchema,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Role {
+    /// Grant and revoke roles
+    Admin,
+    /// Register and remove TEE relayers
+    RelayerManager,
+    /// Pause the contract
+    Pauser,
+    /// Change MPC, allowlist and oracle configuration
+    ConfigManager,
 }
 
 /// Price condition for triggering operations
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema,
+)]
 #[serde(crate = "near_sdk::serde")]
 pub enum PriceCondition {
     Above,
@@ -28,7 +72,9 @@ pub enum PriceCondition {
 }
 
 /// Operation types user can pre-approve
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema,
+)]
 #[serde(crate = "near_sdk::serde")]
 #[serde(tag = "type")]
 pub enum AllowedOperationType {
@@ -40,6 +86,8 @@ pub enum AllowedOperationType {
         max_amount: U128,
     },
     /// Limit order: execute when price crosses threshold
+    /// Trigger prices are quote_asset per price_asset, scaled by 10^8,
+    /// and checked against the configured price oracle
     LimitOrder {
         price_asset: String,
         quote_asset: String,
@@ -75,8 +123,112 @@ pub enum AllowedOperationType {
     },
 }
 
+impl AllowedOperationType {
+    /// Serialized `type` tags of all operation types
+    pub const NAMES: [&'static str; 4] = ["Swap", "LimitOrder", "StopLoss", "TakeProfit"];
+
+    /// Serialized `type` tag of the operation
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Swap { .. } => "Swap",
+            Self::LimitOrder { .. } => "LimitOrder",
+            Self::StopLoss { .. } => "StopLoss",
+            Self::TakeProfit { .. } => "TakeProfit",
+        }
+    }
+
+    /// Asset the operation spends
+    pub fn source_asset(&self) -> &str {
+        match self {
+            Self::Swap { source_asset, .. }
+            | Self::LimitOrder { source_asset, .. }
+            | Self::StopLoss { source_asset, .. }
+            | Self::TakeProfit { source_asset, .. } => source_asset,
+        }
+    }
+
+    /// Asset the operation receives
+    pub fn target_asset(&self) -> &str {
+        match self {
+            Self::Swap { target_asset, .. }
+            | Self::LimitOrder { target_asset, .. }
+            | Self::StopLoss { target_asset, .. }
+            | Self::TakeProfit { target_asset, .. } => target_asset,
+        }
+    }
+
+    /// Maximum amount of the source asset the operation may spend
+    pub fn max_amount(&self) -> u128 {
+        match self {
+            Self::Swap { max_amount, .. }
+            | Self::LimitOrder { max_amount, .. }
+            | Self::StopLoss { max_amount, .. }
+            | Self::TakeProfit { max_amount, .. } => max_amount.0,
+        }
+    }
+
+    /// (price_asset, quote_asset) of conditional operations; None for swaps
+    pub fn price_assets(&self) -> Option<(&str, &str)> {
+        match self {
+            Self::Swap { .. } => None,
+            Self::LimitOrder {
+                price_asset,
+                quote_asset,
+                ..
+            }
+            | Self::StopLoss {
+                price_asset,
+                quote_asset,
+                ..
+            }
+            | Self::TakeProfit {
+                price_asset,
+                quote_asset,
+                ..
+            } => Some((price_asset, quote_asset)),
+        }
+    }
+}
+
+/// Repeat schedule for a recurring (DCA) operation
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RecurringSchedule {
+    /// Minimum time between executions (nanoseconds)
+    pub interval: u64,
+    /// Total executions allowed
+    pub max_executions: u32,
+    /// Source asset each execution may spend, at most max_amount
+    #[schemars(with = "String")]
+    pub amount_per_execution: U128,
+    /// First execution allowed at (nanoseconds)
+    pub start_at: u64,
+    /// No executions at or after (nanoseconds)
+    pub end_at: Option<u64>,
+}
+
+impl RecurringSchedule {
+    /// Check the schedule allows at least one execution within `max_amount`
+    pub fn validate(&self, max_amount: u128) -> Result<(), &'static str> {
+        if self.interval == 0 || self.max_executions == 0 {
+            return Err("Recurring interval and max executions must be positive");
+        }
+        if self.amount_per_execution.0 == 0 || self.amount_per_execution.0 > max_amount {
+            return Err("Amount per execution must be positive and at most max_amount");
+        }
+        if self.end_at.is_some_and(|end| end <= self.start_at) {
+            return Err("Recurring schedule ends before it starts");
+        }
+        Ok(())
+    }
+}
+
 /// A pre-approved operation
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema,
+)]
 #[serde(crate = "near_sdk::serde")]
 pub struct AllowedOperation {
     /// Unique operation ID
@@ -85,6 +237,8 @@ pub struct AllowedOperation {
     pub derivation_path: DerivationPath,
     /// Type of operation allowed
     pub operation_type: AllowedOperationType,
+    /// Chain whose transactions the operation signs
+    pub source_chain: String,
     /// Where to send output
     pub destination_address: String,
     /// Which chain for destination
@@ -93,27 +247,121 @@ pub struct AllowedOperation {
     pub slippage_bps: u16,
     /// Optional expiry timestamp (nanoseconds)
     pub expires_at: Option<u64>,
-    /// Whether operation has been executed
+    /// Whether operation is complete: budget exhausted or, for recurring
+    /// operations, all executions used
     pub executed: bool,
     /// Nonce for replay protection
     pub nonce: u64,
     /// When operation was created
     pub created_at: u64,
+    /// Repeat schedule; None for one-shot operations
+    pub recurring: Option<RecurringSchedule>,
+    /// Executions so far
+    pub execution_count: u32,
+    /// Timestamp of the latest execution (nanoseconds)
+    pub last_executed_at: Option<u64>,
+    /// Source asset spent so far out of max_amount
+    #[schemars(with = "String")]
+    pub consumed_amount: U128,
+}
+
+impl AllowedOperation {
+    /// Most of the source asset a single execution may spend: the remaining
+    /// budget, capped by the recurring per-execution amount
+    pub fn execution_max_amount(&self) -> u128 {
+        let remaining = self
+            .operation_type
+            .max_amount()
+            .saturating_sub(self.consumed_amount.0);
+        match &self.recurring {
+            Some(schedule) => schedule.amount_per_execution.0.min(remaining),
+            None => remaining,
+        }
+    }
+
+    /// Check the operation may be signed at `now`
+    pub fn check_executable(&self, now: u64) -> Result<(), &'static str> {
+        if self.executed {
+            return Err("Operation already executed");
+        }
+        if self.expires_at.is_some_and(|expires| now >= expires) {
+            return Err("Operation has expired");
+        }
+        if let Some(schedule) = &self.recurring {
+            if now < schedule.start_at {
+                return Err("Recurring operation has not started");
+            }
+            if schedule.end_at.is_some_and(|end| now >= end) {
+                return Err("Recurring operation has ended");
+            }
+            if self
+                .last_executed_at
+                .is_some_and(|last| now < last.saturating_add(schedule.interval))
+            {
+                return Err("Recurring interval has not elapsed");
+            }
+        }
+        Ok(())
+    }
+
+    /// Record an execution spending `amount` at `now`; the operation becomes
+    /// executed once its budget is exhausted or, if recurring, it used its
+    /// last execution
+    pub fn record_execution(&mut self, now: u64, amount: u128) {
+        self.execution_count += 1;
+        self.last_executed_at = Some(now);
+        self.consumed_amount = U128(self.consumed_amount.0.saturating_add(amount));
+        self.executed = self.is_complete();
+    }
+
+    /// Undo an execution recorded at `executed_at` whose signature failed
+    /// Other executions may have been recorded since: the interval only
+    /// rewinds to `previous_executed_at` if this was the latest, and the
+    /// operation stays executed if those alone complete it
+    pub fn revert_execution(
+        &mut self,
+        executed_at: u64,
+        previous_executed_at: Option<u64>,
+        amount: u128,
+    ) {
+        self.execution_count = self.execution_count.saturating_sub(1);
+        self.consumed_amount = U128(self.consumed_amount.0.saturating_sub(amount));
+        if self.last_executed_at == Some(executed_at) {
+            self.last_executed_at = previous_executed_at;
+        }
+        self.executed = self.is_complete();
+    }
+
+    /// Budget exhausted or, if recurring, all executions used
+    fn is_complete(&self) -> bool {
+        self.consumed_amount.0 >= self.operation_type.max_amount()
+            || self
+                .recurring
+                .as_ref()
+                .is_some_and(|schedule| self.execution_count >= schedule.max_executions)
+    }
 }
 
 /// Input for creating an allowed operation (without auto-generated fields)
-#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema,
+)]
 #[serde(crate = "near_sdk::serde")]
 pub struct AllowedOperationInput {
     pub operation_type: AllowedOperationType,
+    pub source_chain: String,
     pub destination_address: String,
     pub destination_chain: String,
     pub slippage_bps: u16,
     pub expires_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recurring: Option<RecurringSchedule>,
 }
 
 /// User's registered wallet for signing allowlist changes
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema,
+)]
 #[serde(crate = "near_sdk::serde")]
 pub struct RegisteredWallet {
     pub wallet_type: WalletType,
@@ -121,15 +369,511 @@ pub struct RegisteredWallet {
     pub chain_address: String,
 }
 
+/// Path change that needs the path's approval threshold
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema,
+)]
+#[serde(crate = "near_sdk::serde", tag = "type")]
+pub enum PathChange {
+    AddOperation {
+        operation: Box<AllowedOperationInput>,
+    },
+    RemoveOperation {
+        operation_id: String,
+    },
+    SetThreshold {
+        threshold: u8,
+    },
+    SetSpendingLimit {
+        asset: String,
+        #[schemars(with = "String")]
+        limit: U128,
+        window: u64,
+    },
+    RemoveSpendingLimit {
+        asset: String,
+    },
+    AddDestination {
+        chain: String,
+        address: String,
+    },
+    RemoveDestination {
+        chain: String,
+        address: String,
+    },
+    /// Allow signing for a frozen path again
+    Unfreeze,
+    /// Add an owner wallet; it signs the proposer's link message
+    LinkWallet {
+        new_wallet: RegisteredWallet,
+        new_wallet_signature: Vec<u8>,
+        new_wallet_message: Vec<u8>,
+    },
+    /// Remove an owner wallet other than the last one
+    RemoveWallet {
+        wallet_address: String,
+    },
+    /// Replace an owner wallet; the new wallet signs the same rotate message
+    RotateWallet {
+        old_address: String,
+        new_wallet: RegisteredWallet,
+        new_wallet_signature: Vec<u8>,
+        new_wallet_message: Vec<u8>,
+    },
+}
+
+/// Change waiting for owner wallet approvals
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Proposal {
+    /// Hex sha256 of the path, change, proposer and proposer nonce
+    pub proposal_id: String,
+    pub change: PathChange,
+    /// Owner wallets that approved, proposer first
+    pub approvals: Vec<String>,
+    /// Expiry timestamp (nanoseconds)
+    pub expires_at: u64,
+}
+
+/// Source asset spent by one execution
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq)]
+pub struct Spend {
+    /// Execution timestamp (nanoseconds)
+    pub at: u64,
+    pub amount: u128,
+}
+
+/// Longest spending-limit window (31 days, nanoseconds)
+/// Bounds how many spends a limit keeps and stays exact as a JS number
+pub const MAX_SPENDING_WINDOW_NS: u64 = 31 * 24 * 60 * 60 * 1_000_000_000;
+
+/// Cap on how much of an asset may leave a path within a rolling window
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq)]
+pub struct SpendingLimit {
+    pub asset: String,
+    pub limit: u128,
+    /// Window length (nanoseconds)
+    pub window: u64,
+    /// Spends that may still fall within the window, oldest first
+    pub spends: Vec<Spend>,
+}
+
+impl SpendingLimit {
+    /// Check a rolling window length is usable
+    pub fn check_window(window: u64) -> Result<(), &'static str> {
+        if window == 0 {
+            Err("Spending window must be positive")
+        } else if window > MAX_SPENDING_WINDOW_NS {
+            Err("Spending window too long")
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Amount spent in the window ending at `now`
+    pub fn used(&self, now: u64) -> u128 {
+        self.spends
+            .iter()
+            .filter(|spend| spend.at.saturating_add(self.window) > now)
+            .fold(0u128, |total, spend| total.saturating_add(spend.amount))
+    }
+
+    /// Amount that may still be spent at `now`
+    pub fn remaining(&self, now: u64) -> u128 {
+        self.limit.saturating_sub(self.used(now))
+    }
+}
+
+/// Address-book entry operations may send funds to
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ApprovedDestination {
+    /// Destination chain, matched case-insensitively
+    pub chain: String,
+    pub address: String,
+    /// Signing to this destination is allowed from this timestamp (nanoseconds)
+    pub active_at: u64,
+}
+
+impl ApprovedDestination {
+    pub fn matches(&self, chain: &str, address: &str) -> bool {
+        self.chain.eq_ignore_ascii_case(chain) && self.address == address
+    }
+}
+
 /// User permission set for a derivation path
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct UserPermissions {
     /// Wallets authorized to manage this permission set
     pub owner_wallets: Vec<RegisteredWallet>,
     /// Allowed operations for this derivation path
-    pub allowed_operations: UnorderedMap<String, AllowedOperation>,
+    pub allowed_operations: UnorderedMap<String, VersionedAllowedOperation>,
     /// Next nonce for operation IDs
     pub next_nonce: u64,
+    /// Set by an owner wallet to block all signing for this path
+    pub frozen: bool,
+    /// Owner wallet approvals needed to change the allowlist
+    pub threshold: u8,
+    /// Changes still collecting approvals
+    pub proposals: Vec<Proposal>,
+    /// Rolling-window caps per source asset, across all operations
+    pub spending_limits: Vec<SpendingLimit>,
+    /// Approved destinations; operations may only pay these, so an empty
+    /// book blocks all signing
+    pub destinations: Vec<ApprovedDestination>,
+    /// Operations of a path stored by the first release, still untagged;
+    /// moved into allowed_operations when the permissions are saved
+    #[borsh(skip)]
+    pub legacy_operations: Option<UnorderedMap<String, LegacyAllowedOperation>>,
+}
+
+impl UserPermissions {
+    /// Permissions stored by the first release
+    /// Their operations stay in the untagged map until `upgrade_legacy_operations`;
+    /// new operations go to a separate map, as the two layouts cannot share one
+    /// The first release had no address book, so the destinations of its
+    /// operations are approved as they were
+    pub fn from_legacy(perms: LegacyUserPermissions, derivation_path: &str) -> Self {
+        let mut destinations: Vec<ApprovedDestination> = Vec::new();
+        for operation in perms.allowed_operations.values() {
+            let chain = operation.destination_chain;
+            let address = operation.destination_address;
+            if !destinations.iter().any(|d| d.matches(&chain, &address)) {
+                destinations.push(ApprovedDestination {
+                    chain,
+                    address,
+                    active_at: 0,
+                });
+            }
+        }
+        Self {
+            owner_wallets: perms.owner_wallets,
+            allowed_operations: UnorderedMap::new(format!("opv:{}", derivation_path).as_bytes()),
+            next_nonce: perms.next_nonce,
+            frozen: false,
+            threshold: 1,
+            proposals: Vec::new(),
+            spending_limits: Vec::new(),
+            destinations,
+            legacy_operations: Some(perms.allowed_operations),
+        }
+    }
+
+    /// Operation by ID, upgraded to the current layout
+    pub fn operation(&self, operation_id: &String) -> Option<AllowedOperation> {
+        self.allowed_operations
+            .get(operation_id)
+            .map(Into::into)
+            .or_else(|| {
+                self.legacy_operations
+                    .as_ref()
+                    .and_then(|legacy| legacy.get(operation_id))
+                    .map(Into::into)
+            })
+    }
+
+    /// Store an operation under its ID in the current layout
+    pub fn insert_operation(&mut self, operation: &AllowedOperation) {
+        if let Some(legacy) = &mut self.legacy_operations {
+            legacy.remove(&operation.operation_id);
+        }
+        self.allowed_operations
+            .insert(&operation.operation_id, &operation.clone().into());
+    }
+
+    /// Delete an operation by ID
+    pub fn remove_operation(&mut self, operation_id: &String) {
+        if let Some(legacy) = &mut self.legacy_operations {
+            legacy.remove(operation_id);
+        }
+        self.allowed_operations.remove(operation_id);
+    }
+
+    /// Delete all operations
+    pub fn clear_operations(&mut self) {
+        if let Some(legacy) = &mut self.legacy_operations {
+            legacy.clear();
+        }
+        self.allowed_operations.clear();
+    }
+
+    /// All operations, upgraded to the current layout
+    pub fn operations(&self) -> Vec<AllowedOperation> {
+        let legacy = self
+            .legacy_operations
+            .iter()
+            .flat_map(|legacy| legacy.values().map(Into::into));
+        self.allowed_operations
+            .values()
+            .map(Into::into)
+            .chain(legacy)
+            .collect()
+    }
+
+    /// Move operations stored by the first release into allowed_operations
+    pub fn upgrade_legacy_operations(&mut self) {
+        if let Some(mut legacy) = self.legacy_operations.take() {
+            for (operation_id, operation) in legacy.iter() {
+                self.allowed_operations
+                    .insert(&operation_id, &AllowedOperation::from(operation).into());
+            }
+            legacy.clear();
+        }
+    }
+
+    /// Direct allowlist and wallet changes are only allowed without a threshold
+    pub fn assert_single_approval(&self) {
+        assert!(
+            self.threshold <= 1,
+            "Path requires threshold approval; submit a proposal"
+        );
+    }
+
+    pub fn assert_valid_threshold(&self, threshold: u8) {
+        if let Err(e) = Self::check_threshold(threshold, self.owner_wallets.len()) {
+            env::panic_str(e);
+        }
+    }
+
+    /// Check `threshold` can be met by `wallets` owner wallets
+    fn check_threshold(threshold: u8, wallets: usize) -> Result<(), &'static str> {
+        if threshold >= 1 && threshold as usize <= wallets {
+            Ok(())
+        } else {
+            Err("Threshold must be between 1 and the number of owner wallets")
+        }
+    }
+
+    /// Check a wallet may be added as an owner
+    pub fn check_new_wallet(&self, chain_address: &str) -> Result<(), &'static str> {
+        if self
+            .owner_wallets
+            .iter()
+            .any(|w| w.chain_address == chain_address)
+        {
+            Err("Wallet already registered for this derivation path")
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Check an owner wallet may be removed without closing the path
+    pub fn check_remove_wallet(&self, chain_address: &str) -> Result<(), &'static str> {
+        if !self
+            .owner_wallets
+            .iter()
+            .any(|w| w.chain_address == chain_address)
+        {
+            return Err("Wallet not registered for this derivation path");
+        }
+        if self.owner_wallets.len() == 1 {
+            return Err("Cannot remove the last wallet unless closing the path");
+        }
+        Self::check_threshold(self.threshold, self.owner_wallets.len() - 1)
+            .map_err(|_| "Removing the wallet would leave fewer wallets than the threshold")
+    }
+
+    /// Check an operation may be created with this schedule and destination
+    pub fn check_new_operation(
+        &self,
+        operation: &AllowedOperationInput,
+    ) -> Result<(), &'static str> {
+        if let Some(schedule) = &operation.recurring {
+            schedule.validate(operation.operation_type.max_amount())?;
+        }
+        self.check_new_destination(&operation.destination_chain, &operation.destination_address)
+    }
+
+    /// Check a change is valid for the path as it is now
+    /// Proposals are checked when proposed and again when applied
+    pub fn validate_change(&self, change: &PathChange) -> Result<(), &'static str> {
+        match change {
+            PathChange::AddOperation { operation } => self.check_new_operation(operation),
+            PathChange::SetThreshold { threshold } => {
+                Self::check_threshold(*threshold, self.owner_wallets.len())
+            }
+            PathChange::SetSpendingLimit { window, .. } => SpendingLimit::check_window(*window),
+            PathChange::LinkWallet { new_wallet, .. } => {
+                self.check_new_wallet(&new_wallet.chain_address)
+            }
+            PathChange::RemoveWallet { wallet_address } => self.check_remove_wallet(wallet_address),
+            PathChange::RotateWallet {
+                old_address,
+                new_wallet,
+                ..
+            } => {
+                if !self
+                    .owner_wallets
+                    .iter()
+                    .any(|w| &w.chain_address == old_address)
+                {
+                    return Err("Wallet not registered for this derivation path");
+                }
+                self.check_new_wallet(&new_wallet.chain_address)
+            }
+            PathChange::RemoveOperation { .. }
+            | PathChange::RemoveSpendingLimit { .. }
+            | PathChange::AddDestination { .. }
+            | PathChange::RemoveDestination { .. }
+            | PathChange::Unfreeze => Ok(()),
+        }
+    }
+
+    /// Check spending `amount` of `asset` at `now` stays within its limit
+    pub fn check_spend(&self, asset: &str, amount: u128, now: u64) -> Result<(), &'static str> {
+        match self.spending_limits.iter().find(|l| l.asset == asset) {
+            Some(limit) if amount > limit.remaining(now) => Err("Spending limit exceeded"),
+            _ => Ok(()),
+        }
+    }
+
+    /// Count a spend against the asset's limit, dropping spends that left the window
+    pub fn record_spend(&mut self, asset: &str, amount: u128, now: u64) {
+        if let Some(limit) = self.spending_limits.iter_mut().find(|l| l.asset == asset) {
+            let window = limit.window;
+            limit
+                .spends
+                .retain(|spend| spend.at.saturating_add(window) > now);
+            limit.spends.push(Spend { at: now, amount });
+        }
+    }
+
+    /// Address-book entry for a destination
+    pub fn destination(&self, chain: &str, address: &str) -> Option<&ApprovedDestination> {
+        self.destinations.iter().find(|d| d.matches(chain, address))
+    }
+
+    /// Check an operation may be created for a destination; pending entries count
+    pub fn check_new_destination(&self, chain: &str, address: &str) -> Result<(), &'static str> {
+        if self.destination(chain, address).is_some() {
+            Ok(())
+        } else {
+            Err("Destination not in address book")
+        }
+    }
+
+    /// Check funds may be sent to a destination at `now`
+    pub fn check_destination(
+        &self,
+        chain: &str,
+        address: &str,
+        now: u64,
+    ) -> Result<(), &'static str> {
+        match self.destination(chain, address) {
+            Some(destination) if now >= destination.active_at => Ok(()),
+            Some(_) => Err("Destination not active yet"),
+            None => Err("Destination not in address book"),
+        }
+    }
+
+    /// Undo a spend recorded at `at`, e.g. after a failed signature
+    pub fn refund_spend(&mut self, asset: &str, amount: u128, at: u64) {
+        if let Some(limit) = self.spending_limits.iter_mut().find(|l| l.asset == asset) {
+            let spend = Spend { at, amount };
+            if let Some(index) = limit.spends.iter().position(|s| *s == spend) {
+                limit.spends.remove(index);
+            }
+        }
+    }
+}
+
+/// Stored AllowedOperation record
+/// Add a variant for each layout change and upgrade it in From so
+/// existing records are converted when read
+#[derive(BorshDeserialize, BorshSerialize)]
+pub enum VersionedAllowedOperation {
+    V1(AllowedOperation),
+}
+
+impl From<VersionedAllowedOperation> for AllowedOperation {
+    fn from(versioned: VersionedAllowedOperation) -> Self {
+        match versioned {
+            VersionedAllowedOperation::V1(operation) => operation,
+        }
+    }
+}
+
+impl From<AllowedOperation> for VersionedAllowedOperation {
+    fn from(operation: AllowedOperation) -> Self {
+        Self::V1(operation)
+    }
+}
+
+/// AllowedOperation as stored by the first release, untagged
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct LegacyAllowedOperation {
+    pub operation_id: String,
+    pub derivation_path: DerivationPath,
+    pub operation_type: AllowedOperationType,
+    pub destination_address: String,
+    pub destination_chain: String,
+    pub slippage_bps: u16,
+    pub expires_at: Option<u64>,
+    pub executed: bool,
+    pub nonce: u64,
+    pub created_at: u64,
+}
+
+impl From<LegacyAllowedOperation> for AllowedOperation {
+    fn from(operation: LegacyAllowedOperation) -> Self {
+        Self {
+            // Before partial fills an execution could spend the whole budget,
+            // so executed operations count it as consumed
+            consumed_amount: U128(if operation.executed {
+                operation.operation_type.max_amount()
+            } else {
+                0
+            }),
+            operation_id: operation.operation_id,
+            derivation_path: operation.derivation_path,
+            operation_type: operation.operation_type,
+            // Signing was dispatched on the destination chain
+            source_chain: operation.destination_chain.clone(),
+            destination_address: operation.destination_address,
+            destination_chain: operation.destination_chain,
+            slippage_bps: operation.slippage_bps,
+            expires_at: operation.expires_at,
+            executed: operation.executed,
+            nonce: operation.nonce,
+            created_at: operation.created_at,
+            recurring: None,
+            execution_count: operation.executed as u32,
+            last_executed_at: None,
+        }
+    }
+}
+
+/// UserPermissions as stored by the first release: untagged, with untagged
+/// LegacyAllowedOperation values
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct LegacyUserPermissions {
+    pub owner_wallets: Vec<RegisteredWallet>,
+    pub allowed_operations: UnorderedMap<String, LegacyAllowedOperation>,
+    pub next_nonce: u64,
+}
+
+/// Stored UserPermissions record, upgraded on read like VersionedAllowedOperation
+#[derive(BorshDeserialize, BorshSerialize)]
+pub enum VersionedUserPermissions {
+    V1(UserPermissions),
+}
+
+impl From<VersionedUserPermissions> for UserPermissions {
+    fn from(versioned: VersionedUserPermissions) -> Self {
+        match versioned {
+            VersionedUserPermissions::V1(perms) => perms,
+        }
+    }
+}
+
+impl From<UserPermissions> for VersionedUserPermissions {
+    fn from(perms: UserPermissions) -> Self {
+        Self::V1(perms)
+    }
 }
 
 /// View type for user permissions (for queries)
@@ -139,16 +883,163 @@ pub struct UserPermissionsView {
     pub owner_wallets: Vec<RegisteredWallet>,
     pub operations: Vec<AllowedOperation>,
     pub next_nonce: u64,
+    pub frozen: bool,
+    pub threshold: u8,
 }
 
 impl From<&UserPermissions> for UserPermissionsView {
     fn from(perms: &UserPermissions) -> Self {
         Self {
             owner_wallets: perms.owner_wallets.clone(),
-            operations: perms.allowed_operations.values().collect(),
+            operations: perms.operations(),
             next_nonce: perms.next_nonce,
+            frozen: perms.frozen,
+            threshold: perms.threshold,
+        }
+    }
+}
+
+/// Spending limit with its usage in the current window (for queries)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SpendingLimitView {
+    pub asset: String,
+    #[schemars(with = "String")]
+    pub limit: U128,
+    /// Window length (nanoseconds)
+    pub window: u64,
+    #[schemars(with = "String")]
+    pub used: U128,
+    #[schemars(with = "String")]
+    pub remaining: U128,
+}
+
+impl SpendingLimitView {
+    pub fn new(limit: &SpendingLimit, now: u64) -> Self {
+        Self {
+            asset: limit.asset.clone(),
+            limit: U128(limit.limit),
+            window: limit.window,
+            used: U128(limit.used(now)),
+            remaining: U128(limit.remaining(now)),
+        }
+    }
+}
+
+/// Storage deposit of an account sponsoring derivation paths
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug)]
+pub struct StorageAccount {
+    /// Total deposited yoctoNEAR
+    pub deposit: u128,
+    /// Bytes charged against the deposit
+    pub used_bytes: u64,
+}
+
+/// NEP-145 storage balance
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageBalance {
+    #[schemars(with = "String")]
+    pub total: U128,
+    #[schemars(with = "String")]
+    pub available: U128,
+}
+
+/// NEP-145 storage balance bounds
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageBalanceBounds {
+    #[schemars(with = "String")]
+    pub min: U128,
+    #[schemars(with = "Option<String>")]
+    pub max: Option<U128>,
+}
+
+/// Relayer whose access key a verifier-certified registration bound to an
+/// approved measurement; no TEE quote is verified on chain
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AttestedRelayer {
+    /// Access key the relayer must sign its transactions with
+    #[schemars(with = "String")]
+    pub public_key: PublicKey,
+    /// Hex code measurement and compose hash the verifier reported
+    pub measurement: String,
+    pub compose_hash: String,
+    /// Re-attestation needed from this timestamp (nanoseconds)
+    pub expires_at: u64,
+}
+
+/// What a TEE relayer may do; relayers registered without one are unrestricted
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RelayerScope {
+    /// Relayer methods it may call, e.g. "sign_allowed"
+    pub methods: Vec<String>,
+    /// Operation types it may add or sign, e.g. "LimitOrder"
+    pub operation_types: Vec<String>,
+    /// MPC key types it may sign with: "Eddsa" and/or "Ecdsa"
+    pub key_types: Vec<String>,
+    /// Derivation path prefixes it may act on, matched on whole ","-separated
+    /// segments ("solana-1" covers "solana-1,alice" but not "solana-10");
+    /// None allows every path
+    pub path_prefixes: Option<Vec<String>>,
+}
+
+impl RelayerScope {
+    /// Scope allowing nothing, given to relayers no manager registered
+    pub fn deny_all() -> Self {
+        Self {
+            methods: Vec::new(),
+            operation_types: Vec::new(),
+            key_types: Vec::new(),
+            path_prefixes: Some(Vec::new()),
         }
     }
+
+    /// Check the scope only names known methods, operation types and key types
+    pub fn validate(&self, relayer_methods: &[&str]) -> Result<(), &'static str> {
+        if self
+            .methods
+            .iter()
+            .any(|m| !relayer_methods.contains(&m.as_str()))
+        {
+            return Err("Unknown relayer method in scope");
+        }
+        if self
+            .operation_types
+            .iter()
+            .any(|t| !AllowedOperationType::NAMES.contains(&t.as_str()))
+        {
+            return Err("Unknown operation type in scope");
+        }
+        if self.key_types.iter().any(|k| k != "Eddsa" && k != "Ecdsa") {
+            return Err("Unknown key type in scope");
+        }
+        if self
+            .path_prefixes
+            .as_ref()
+            .is_some_and(|prefixes| prefixes.iter().any(|p| p.is_empty()))
+        {
+            return Err("Path prefix must not be empty");
+        }
+        Ok(())
+    }
+
+    /// Whether the scope covers a derivation path
+    pub fn allows_path(&self, derivation_path: &str) -> bool {
+        self.path_prefixes.as_ref().is_none_or(|prefixes| {
+            prefixes.iter().any(|p| {
+                derivation_path
+                    .strip_prefix(p.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with(','))
+            })
+        })
+    }
 }
 
 /// Contract configuration (for view)
@@ -157,9 +1048,274 @@ impl From<&UserPermissions> for UserPermissionsView {
 pub struct ContractConfig {
     #[schemars(with = "String")]
     pub owner: near_sdk::AccountId,
+    #[schemars(with = "Option<String>")]
+    pub pending_owner: Option<near_sdk::AccountId>,
+    pub paused: bool,
     #[schemars(with = "String")]
     pub mpc_contract: near_sdk::AccountId,
     #[schemars(with = "Vec<String>")]
     pub tee_relayers: Vec<near_sdk::AccountId>,
     pub active_operations_count: u64,
+    pub allowed_solana_programs: Vec<String>,
+    pub allowed_near_methods: Vec<String>,
+    #[schemars(with = "Option<String>")]
+    pub price_oracle: Option<near_sdk::AccountId>,
+    /// (asset, oracle price feed ID) pairs
+    pub price_feeds: Vec<(String, String)>,
+    /// Whether relayers need a valid attestation, not just registration
+    pub require_attestation: bool,
+    /// Hex Ed25519 keys trusted to certify attestation keys
+    pub attestation_roots: Vec<String>,
+    /// Approved "measurement:compose_hash" pairs (hex)
+    pub approved_measurements: Vec<String>,
+    /// MPC Ed25519 root key ("ed25519:<base58>") NEAR accounts derive from
+    pub mpc_ed25519_key: Option<String>,
+}
+
+/// Signature returned by sign_allowed, ready to attach to the transaction
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "scheme")]
+pub enum MpcSignature {
+    /// ECDSA signature: r and s as 32-byte hex, v the recovery ID (0 or 1)
+    Secp256k1 { r: String, s: String, v: u8 },
+    /// 64-byte ed25519 signature as hex
+    Ed25519 { signature: String },
+}
+
+/// NEP-297 standard name of contract events
+pub const EVENT_STANDARD: &str = "shadelink-permission";
+/// NEP-297 version of contract events
+pub const EVENT_VERSION: &str = "1.0.0";
+
+/// NEP-297 event log: `EVENT_JSON:{"standard", "version", "event", "data"}`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EventLog {
+    pub standard: String,
+    pub version: String,
+    #[serde(flatten)]
+    pub event: PermissionEvent,
+}
+
+/// Contract state changes, emitted as NEP-297 events
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum PermissionEvent {
+    TeeRelayerRegistered {
+        relayer_account: AccountId,
+    },
+    TeeRelayerRemoved {
+        relayer_account: AccountId,
+    },
+    RelayerScopeSet {
+        relayer_account: AccountId,
+        scope: RelayerScope,
+    },
+    RelayerAttested {
+        relayer_account: AccountId,
+        public_key: PublicKey,
+        measurement: String,
+        compose_hash: String,
+        expires_at: u64,
+    },
+    MeasurementApproved {
+        measurement: String,
+        compose_hash: String,
+    },
+    MeasurementRevoked {
+        measurement: String,
+        compose_hash: String,
+    },
+    AttestationRootAdded {
+        public_key: String,
+    },
+    AttestationRootRemoved {
+        public_key: String,
+    },
+    AttestationRequirementSet {
+        required: bool,
+    },
+    MpcContractUpdated {
+        mpc_contract: AccountId,
+    },
+    MpcEd25519KeySet {
+        public_key: String,
+    },
+    RoleGranted {
+        account_id: AccountId,
+        role: Role,
+    },
+    RoleRevoked {
+        account_id: AccountId,
+        role: Role,
+    },
+    OwnershipProposed {
+        owner: AccountId,
+        pending_owner: AccountId,
+    },
+    OwnershipTransferred {
+        previous_owner: AccountId,
+        owner: AccountId,
+    },
+    StorageDeposited {
+        account_id: AccountId,
+        amount: U128,
+    },
+    StorageWithdrawn {
+        account_id: AccountId,
+        amount: U128,
+    },
+    Paused {
+        account_id: AccountId,
+    },
+    Unpaused {
+        account_id: AccountId,
+    },
+    /// New contract code deployed; sha256 of the code as hex
+    ContractUpdated {
+        code_hash: String,
+    },
+    SolanaProgramAllowed {
+        program_id: String,
+    },
+    SolanaProgramRemoved {
+        program_id: String,
+    },
+    NearMethodAllowed {
+        receiver_id: AccountId,
+        method_name: String,
+    },
+    NearMethodRemoved {
+        receiver_id: AccountId,
+        method_name: String,
+    },
+    PriceOracleSet {
+        price_oracle: AccountId,
+    },
+    PriceFeedSet {
+        asset: String,
+        price_id: String,
+    },
+    PriceFeedRemoved {
+        asset: String,
+    },
+    WalletRegistered {
+        derivation_path: DerivationPath,
+        wallet_type: WalletType,
+        chain_address: String,
+    },
+    OperationAdded {
+        derivation_path: DerivationPath,
+        operation: Box<AllowedOperation>,
+    },
+    OperationRemoved {
+        derivation_path: DerivationPath,
+        operation_id: String,
+    },
+    WalletLinked {
+        derivation_path: DerivationPath,
+        wallet_type: WalletType,
+        chain_address: String,
+        signer_address: String,
+    },
+    WalletRemoved {
+        derivation_path: DerivationPath,
+        chain_address: String,
+        signer_address: String,
+    },
+    WalletRotated {
+        derivation_path: DerivationPath,
+        old_address: String,
+        new_address: String,
+        signer_address: String,
+    },
+    /// Last wallet removed; the path's operations were deleted
+    PathClosed {
+        derivation_path: DerivationPath,
+    },
+    ProposalCreated {
+        derivation_path: DerivationPath,
+        proposal_id: String,
+        change: PathChange,
+        signer_address: String,
+    },
+    ProposalApproved {
+        derivation_path: DerivationPath,
+        proposal_id: String,
+        signer_address: String,
+    },
+    ProposalExecuted {
+        derivation_path: DerivationPath,
+        proposal_id: String,
+    },
+    ThresholdChanged {
+        derivation_path: DerivationPath,
+        threshold: u8,
+    },
+    SpendingLimitSet {
+        derivation_path: DerivationPath,
+        asset: String,
+        limit: U128,
+        window: u64,
+    },
+    SpendingLimitRemoved {
+        derivation_path: DerivationPath,
+        asset: String,
+    },
+    DestinationAdded {
+        derivation_path: DerivationPath,
+        chain: String,
+        address: String,
+        active_at: u64,
+    },
+    DestinationRemoved {
+        derivation_path: DerivationPath,
+        chain: String,
+        address: String,
+    },
+    PathFrozen {
+        derivation_path: DerivationPath,
+        signer_address: String,
+    },
+    PathUnfrozen {
+        derivation_path: DerivationPath,
+        signer_address: String,
+    },
+    /// Operation marked executed and sent to the MPC signer
+    OperationExecuted {
+        derivation_path: DerivationPath,
+        operation_id: String,
+        execution_count: u32,
+        amount: U128,
+        consumed_amount: U128,
+    },
+    MpcSignatureReceived {
+        derivation_path: DerivationPath,
+        operation_id: String,
+    },
+    /// MPC signing failed; the execution was reverted, returning its amount
+    /// to the budget and spending limit and, unless other executions
+    /// completed the operation, making it active again
+    MpcSignatureFailed {
+        derivation_path: DerivationPath,
+        operation_id: String,
+        error: String,
+    },
+}
+
+impl PermissionEvent {
+    /// Log the event as `EVENT_JSON:...`
+    pub fn emit(self) {
+        let log = EventLog {
+            standard: EVENT_STANDARD.to_string(),
+            version: EVENT_VERSION.to_string(),
+            event: self,
+        };
+        env::log_str(&format!(
+            "EVENT_JSON:{}",
+            near_sdk::serde_json::to_string(&log).unwrap()
+        ));
+    }
 }
@@ -115,5 +115,6 @@ echo "MPC Contract: $MPC_CONTRACT"
 echo ""
 echo "Next steps:"
 echo "  1. Add TEE relayers: near call $CONTRACT_ID add_tee_relayer '{\"relayer\": \"<account>\"}' --accountId $CONTRACT_ID"
-echo "  2. Update .env with PERMISSION_CONTRACT_ID=$CONTRACT_ID"
-echo "  3. Test with: near view $CONTRACT_ID get_config '{}'"
+echo "  2. Fund relayer storage: near call $CONTRACT_ID storage_deposit '{\"account_id\": \"<account>\"}' --deposit 1 --accountId <funder>"
+echo "  3. Update .env with PERMISSION_CONTRACT_ID=$CONTRACT_ID"
+echo "  4. Test with: near view $CONTRACT_ID get_config '{}'"
@@ -0,0 +1,62 @@
+#!/bin/bash
+set -e
+
+# Upgrade a deployed NEAR permission contract in place
+# Calls the owner-only update_contract, which deploys the new WASM and runs migrate
+# Usage: ./scripts/upgrade-contract.sh [testnet|mainnet] <contract-account-id> <owner-account-id>
+#
+# Examples:
+#   ./scripts/upgrade-contract.sh testnet permission.shade.testnet owner.shade.testnet
+
+SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+PROJECT_ROOT="$(dirname "$SCRIPT_DIR")"
+
+NETWORK="${1:-testnet}"
+CONTRACT_ID="${2:-}"
+OWNER_ID="${3:-}"
+
+if [ -z "$CONTRACT_ID" ] || [ -z "$OWNER_ID" ]; then
+    echo "Usage: $0 [testnet|mainnet] <contract-account-id> <owner-account-id>"
+    exit 1
+fi
+
+if [ "$NETWORK" != "testnet" ] && [ "$NETWORK" != "mainnet" ]; then
+    echo "Error: Network must be 'testnet' or 'mainnet'"
+    exit 1
+fi
+
+export NEAR_ENV="$NETWORK"
+
+WASM_PATH="$PROJECT_ROOT/out/permission_contract.wasm"
+if [ ! -f "$WASM_PATH" ]; then
+    echo "WASM file not found at $WASM_PATH"
+    echo "Building contract first..."
+    "$SCRIPT_DIR/build-contract.sh"
+fi
+
+if [ ! -f "$WASM_PATH" ]; then
+    echo "Error: WASM file still not found after build. Check for build errors."
+    exit 1
+fi
+
+if ! command -v near &> /dev/null; then
+    echo "Error: near-cli not found. Install with: npm install -g near-cli"
+    exit 1
+fi
+
+echo "Upgrading permission contract..."
+echo "  Network: $NETWORK"
+echo "  Contract ID: $CONTRACT_ID"
+echo "  WASM: $WASM_PATH"
+echo ""
+
+# The WASM is the raw call input, not JSON arguments
+near call "$CONTRACT_ID" update_contract \
+    --base64 "$(base64 < "$WASM_PATH" | tr -d '\n')" \
+    --accountId "$OWNER_ID" \
+    --networkId "$NETWORK" \
+    --gas 300000000000000
+
+echo ""
+echo "✅ Upgrade complete!"
+echo "  Check with: near view $CONTRACT_ID get_config '{}'"
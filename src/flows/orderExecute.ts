@@ -45,7 +45,6 @@ import type { FlowDefinition, FlowContext, FlowResult } from "./types";
 // async function signOrderTransaction(
 //   order: Order,
 //   payload: Uint8Array,
-//   teePrice: string,
 //   ctx: FlowContext,
 // ): Promise<Uint8Array> {
 //   const { logger } = ctx;
@@ -70,8 +69,6 @@ import type { FlowDefinition, FlowContext, FlowResult } from "./types";
 //       operation_id: order.permissionOperationId,
 //       payload: Array.from(payload),
 //       key_type: order.agentChain === "solana" ? "Eddsa" : "Ecdsa",
-//       tee_price: teePrice,
-//       tee_timestamp: Date.now(),
 //     });
 //
 //     logger.info(`Received signature via permission contract (${signature.length} bytes)`);
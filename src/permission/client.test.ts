@@ -16,33 +16,25 @@ import {
 
 describe("Permission Types", () => {
   describe("createRegisterWalletMessage", () => {
-    it("should create a valid registration message", () => {
+    it("should create the plain-text registration message", () => {
       const publicKey = new Uint8Array(32).fill(1);
       const message = createRegisterWalletMessage(
         "solana-1,order-123",
         "Solana",
         publicKey,
         "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU",
-        1
+        0
       );
 
-      const parsed = JSON.parse(message);
-      expect(parsed.action).toBe("register_wallet");
-      expect(parsed.derivation_path).toBe("solana-1,order-123");
-      expect(parsed.wallet_type).toBe("Solana");
-      expect(parsed.chain_address).toBe("7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU");
-      expect(parsed.nonce).toBe(1);
-      expect(parsed.public_key).toHaveLength(32);
+      expect(message).toBe("Register wallet for derivation path: solana-1,order-123 with nonce: 0");
     });
 
-    it("should support different wallet types", () => {
+    it("should not depend on the wallet type", () => {
       const publicKey = new Uint8Array(32);
 
-      const nearMsg = createRegisterWalletMessage("near-1,user", "Near", publicKey, "user.near", 1);
-      expect(JSON.parse(nearMsg).wallet_type).toBe("Near");
-
-      const evmMsg = createRegisterWalletMessage("evm-1,user", "Evm", publicKey, "0x123...", 1);
-      expect(JSON.parse(evmMsg).wallet_type).toBe("Evm");
+      const nearMsg = createRegisterWalletMessage("near-1,user", "Near", publicKey, "user.near", 0);
+      const evmMsg = createRegisterWalletMessage("near-1,user", "Evm", publicKey, "0x123...", 0);
+      expect(evmMsg).toBe(nearMsg);
     });
   });
 
@@ -59,6 +51,7 @@ describe("Permission Types", () => {
           target_asset: "SOL_MINT",
           max_amount: "100000000",
         },
+        source_chain: "solana",
         destination_address: "user_wallet",
         destination_chain: "solana",
         slippage_bps: 100,
@@ -90,6 +83,7 @@ describe("Permission Types", () => {
           target_asset: "USDC_MINT",
           max_amount: "1000000000",
         },
+        source_chain: "solana",
         destination_address: "user_wallet",
         destination_chain: "solana",
         slippage_bps: 200,
@@ -116,6 +110,7 @@ describe("Permission Types", () => {
           target_asset: "USDC_MINT",
           max_amount: "1000000000",
         },
+        source_chain: "solana",
         destination_address: "user_wallet",
         destination_chain: "solana",
         slippage_bps: 100,
@@ -140,6 +135,7 @@ describe("Permission Types", () => {
           target_asset: "SOL_MINT",
           max_amount: "100000000",
         },
+        source_chain: "solana",
         destination_address: "user_wallet",
         destination_chain: "solana",
         slippage_bps: 50,
@@ -165,6 +161,7 @@ describe("Permission Types", () => {
           target_asset: "SOL",
           max_amount: "100",
         },
+        source_chain: "solana",
         destination_address: "user",
         destination_chain: "solana",
         slippage_bps: 50,
@@ -207,6 +204,7 @@ describe("Operation Type Validation", () => {
         target_asset: "SOL",
         max_amount: "100",
       },
+      source_chain: "solana",
       destination_address: "user",
       destination_chain: "solana",
       slippage_bps: 100,
@@ -229,6 +227,7 @@ describe("Operation Type Validation", () => {
         target_asset: "USDC",
         max_amount: "1",
       },
+      source_chain: "solana",
       destination_address: "user",
       destination_chain: "solana",
       slippage_bps: 200,
@@ -245,6 +244,7 @@ describe("Operation Type Validation", () => {
         target_asset: "SOL",
         max_amount: "100",
       },
+      source_chain: "solana",
       destination_address: "user",
       destination_chain: "solana",
       slippage_bps: 50,
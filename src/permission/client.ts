@@ -23,7 +23,23 @@ import type {
   RegisterWalletArgs,
   AddAllowedOperationArgs,
   RemoveAllowedOperationArgs,
+  SetPathFrozenArgs,
+  LinkWalletArgs,
+  ProposeChangeArgs,
+  ApproveProposalArgs,
+  Proposal,
+  SetSpendingLimitArgs,
+  RemoveSpendingLimitArgs,
+  SpendingLimitView,
+  ApprovedDestination,
+  DestinationArgs,
+  RemoveWalletArgs,
+  RotateWalletArgs,
   SignAllowedArgs,
+  MpcSignature,
+  StorageBalance,
+  AttestedRelayer,
+  RelayerScope,
 } from "./types";
 
 // ─── Configuration ──────────────────────────────────────────────────────────────
@@ -40,6 +56,9 @@ const PERMISSION_CONTRACT_ID = process.env.PERMISSION_CONTRACT_ID ||
 const GAS_FOR_REGISTER = BigInt("50000000000000"); // 50 TGas
 const GAS_FOR_ADD_OPERATION = BigInt("50000000000000"); // 50 TGas
 const GAS_FOR_REMOVE_OPERATION = BigInt("30000000000000"); // 30 TGas
+const GAS_FOR_FREEZE = BigInt("30000000000000"); // 30 TGas
+const GAS_FOR_WALLET_CHANGE = BigInt("50000000000000"); // 50 TGas
+const GAS_FOR_ATTESTATION = BigInt("50000000000000"); // 50 TGas
 const GAS_FOR_SIGN_ALLOWED = BigInt("300000000000000"); // 300 TGas (cross-contract to MPC)
 
 // ─── Provider Setup ─────────────────────────────────────────────────────────────
@@ -197,7 +216,116 @@ export async function isOperationAllowed(
 }
 
 /**
- * Get derivation path for a wallet address
+ * Get unexpired proposals waiting for approvals on a derivation path
+ */
+export async function getProposals(derivationPath: DerivationPath): Promise<Proposal[]> {
+  const result = await nearProvider.query({
+    request_type: "call_function",
+    finality: "final",
+    account_id: PERMISSION_CONTRACT_ID,
+    method_name: "get_proposals",
+    args_base64: Buffer.from(JSON.stringify({ derivation_path: derivationPath })).toString("base64"),
+  });
+
+  const resultBytes = (result as any).result;
+  if (!resultBytes || resultBytes.length === 0) {
+    return [];
+  }
+
+  const resultStr = Buffer.from(resultBytes).toString("utf8");
+  return JSON.parse(resultStr) as Proposal[];
+}
+
+/**
+ * Get spending limits for a derivation path with usage in the current window
+ */
+export async function getSpendingLimits(
+  derivationPath: DerivationPath,
+): Promise<SpendingLimitView[]> {
+  const result = await nearProvider.query({
+    request_type: "call_function",
+    finality: "final",
+    account_id: PERMISSION_CONTRACT_ID,
+    method_name: "get_spending_limits",
+    args_base64: Buffer.from(JSON.stringify({ derivation_path: derivationPath })).toString("base64"),
+  });
+
+  const resultBytes = (result as any).result;
+  if (!resultBytes || resultBytes.length === 0) {
+    return [];
+  }
+
+  const resultStr = Buffer.from(resultBytes).toString("utf8");
+  return JSON.parse(resultStr) as SpendingLimitView[];
+}
+
+/**
+ * Get the address book for a derivation path, including pending destinations
+ */
+export async function getDestinations(
+  derivationPath: DerivationPath,
+): Promise<ApprovedDestination[]> {
+  const result = await nearProvider.query({
+    request_type: "call_function",
+    finality: "final",
+    account_id: PERMISSION_CONTRACT_ID,
+    method_name: "get_destinations",
+    args_base64: Buffer.from(JSON.stringify({ derivation_path: derivationPath })).toString("base64"),
+  });
+
+  const resultBytes = (result as any).result;
+  if (!resultBytes || resultBytes.length === 0) {
+    return [];
+  }
+
+  const resultStr = Buffer.from(resultBytes).toString("utf8");
+  return JSON.parse(resultStr) as ApprovedDestination[];
+}
+
+/**
+ * Get a relayer's attestation, including expired ones
+ */
+export async function getAttestedRelayer(accountId: string): Promise<AttestedRelayer | null> {
+  const result = await nearProvider.query({
+    request_type: "call_function",
+    finality: "final",
+    account_id: PERMISSION_CONTRACT_ID,
+    method_name: "get_attested_relayer",
+    args_base64: Buffer.from(JSON.stringify({ account: accountId })).toString("base64"),
+  });
+
+  const resultBytes = (result as any).result;
+  if (!resultBytes || resultBytes.length === 0) {
+    return null;
+  }
+
+  const resultStr = Buffer.from(resultBytes).toString("utf8");
+  return JSON.parse(resultStr) as AttestedRelayer | null;
+}
+
+/**
+ * Get a relayer's scope; null if it is unrestricted
+ */
+export async function getRelayerScope(accountId: string): Promise<RelayerScope | null> {
+  const result = await nearProvider.query({
+    request_type: "call_function",
+    finality: "final",
+    account_id: PERMISSION_CONTRACT_ID,
+    method_name: "get_relayer_scope",
+    args_base64: Buffer.from(JSON.stringify({ account: accountId })).toString("base64"),
+  });
+
+  const resultBytes = (result as any).result;
+  if (!resultBytes || resultBytes.length === 0) {
+    return null;
+  }
+
+  const resultStr = Buffer.from(resultBytes).toString("utf8");
+  return JSON.parse(resultStr) as RelayerScope | null;
+}
+
+/**
+ * Get the primary (first linked) derivation path for a wallet address
  */
 export async function getDerivationPathForWallet(
   chainAddress: string,
@@ -207,7 +335,7 @@ export async function getDerivationPathForWallet(
       request_type: "call_function",
       finality: "final",
       account_id: PERMISSION_CONTRACT_ID,
-      method_name: "get_derivation_path_for_wallet",
+      method_name: "get_path_for_wallet",
       args_base64: Buffer.from(JSON.stringify({ chain_address: chainAddress })).toString("base64"),
     });
 
@@ -223,11 +351,83 @@ export async function getDerivationPathForWallet(
   }
 }
 
+/**
+ * Get all derivation paths a wallet address owns
+ */
+export async function getPathsForWallet(
+  chainAddress: string,
+  fromIndex = 0,
+  limit = 100,
+): Promise<DerivationPath[]> {
+  const result = await nearProvider.query({
+    request_type: "call_function",
+    finality: "final",
+    account_id: PERMISSION_CONTRACT_ID,
+    method_name: "get_paths_for_wallet",
+    args_base64: Buffer.from(JSON.stringify({
+      chain_address: chainAddress,
+      from_index: fromIndex,
+      limit,
+    })).toString("base64"),
+  });
+
+  const resultBytes = (result as any).result;
+  if (!resultBytes || resultBytes.length === 0) {
+    return [];
+  }
+
+  const resultStr = Buffer.from(resultBytes).toString("utf8");
+  return JSON.parse(resultStr) as DerivationPath[];
+}
+
+/**
+ * Get the lowest nonce the next signed add/remove from a wallet may use
+ */
+export async function getNextNonce(chainAddress: string): Promise<number> {
+  const result = await nearProvider.query({
+    request_type: "call_function",
+    finality: "final",
+    account_id: PERMISSION_CONTRACT_ID,
+    method_name: "get_next_nonce",
+    args_base64: Buffer.from(JSON.stringify({ chain_address: chainAddress })).toString("base64"),
+  });
+
+  const resultBytes = (result as any).result;
+  if (!resultBytes || resultBytes.length === 0) {
+    return 0;
+  }
+
+  const resultStr = Buffer.from(resultBytes).toString("utf8");
+  return JSON.parse(resultStr) as number;
+}
+
+/**
+ * Get the NEP-145 storage balance of a sponsoring account (e.g. the relayer)
+ * Registering wallets and adding operations fail once it is used up
+ */
+export async function getStorageBalance(accountId: string): Promise<StorageBalance | null> {
+  const result = await nearProvider.query({
+    request_type: "call_function",
+    finality: "final",
+    account_id: PERMISSION_CONTRACT_ID,
+    method_name: "storage_balance_of",
+    args_base64: Buffer.from(JSON.stringify({ account_id: accountId })).toString("base64"),
+  });
+
+  const resultBytes = (result as any).result;
+  if (!resultBytes || resultBytes.length === 0) {
+    return null;
+  }
+
+  const resultStr = Buffer.from(resultBytes).toString("utf8");
+  return JSON.parse(resultStr) as StorageBalance | null;
+}
+
 // ─── Change Methods (TEE Relayer) ───────────────────────────────────────────────
 
 /**
- * Register a wallet for a derivation path
- * Called by TEE with user's signature
+ * Register the first wallet for a new derivation path
+ * Called by TEE with user's signature; fails if the path already exists
  */
 export async function registerWallet(
   args: RegisterWalletArgs,
@@ -249,6 +449,78 @@ export async function registerWallet(
   return txHash;
 }
 
+/**
+ * Propose an allowlist, threshold, spending limit or address book change (proposer must sign)
+ * Applied immediately when the path's threshold is 1; returns the proposal ID
+ */
+export async function proposeChange(
+  args: ProposeChangeArgs,
+): Promise<{ txHash: string; proposalId: string }> {
+  const relayer = await getRelayerAccount();
+
+  const result = await relayer.functionCall({
+    contractId: PERMISSION_CONTRACT_ID,
+    methodName: "propose_change",
+    args,
+    gas: GAS_FOR_ADD_OPERATION,
+    attachedDeposit: BigInt(0),
+  });
+
+  const txHash = (result as any).transaction?.hash ||
+    (result as any).transaction_outcome?.id;
+
+  const returnValue = (result as any).status?.SuccessValue;
+  const proposalId = returnValue
+    ? JSON.parse(Buffer.from(returnValue, "base64").toString("utf8"))
+    : "";
+
+  console.log(`[permission] Proposal created: ${proposalId} (${txHash})`);
+  return { txHash, proposalId };
+}
+
+/**
+ * Approve a pending proposal with another owner wallet
+ */
+export async function approveProposal(args: ApproveProposalArgs): Promise<string> {
+  const relayer = await getRelayerAccount();
+
+  const result = await relayer.functionCall({
+    contractId: PERMISSION_CONTRACT_ID,
+    methodName: "approve_proposal",
+    args,
+    gas: GAS_FOR_ADD_OPERATION,
+    attachedDeposit: BigInt(0),
+  });
+
+  const txHash = (result as any).transaction?.hash ||
+    (result as any).transaction_outcome?.id;
+
+  console.log(`[permission] Proposal approved: ${args.proposal_id} (${txHash})`);
+  return txHash;
+}
+
+/**
+ * Add a wallet to an existing derivation path
+ * Requires signatures from both the new wallet and an existing owner wallet
+ */
+export async function linkWallet(args: LinkWalletArgs): Promise<string> {
+  const relayer = await getRelayerAccount();
+
+  const result = await relayer.functionCall({
+    contractId: PERMISSION_CONTRACT_ID,
+    methodName: "link_wallet",
+    args,
+    gas: GAS_FOR_WALLET_CHANGE,
+    attachedDeposit: BigInt(0),
+  });
+
+  const txHash = (result as any).transaction?.hash ||
+    (result as any).transaction_outcome?.id;
+
+  console.log(`[permission] Wallet linked: ${args.new_wallet.chain_address} (${txHash})`);
+  return txHash;
+}
+
 /**
  * Add an allowed operation
  * Called by TEE with user's signature
@@ -309,18 +581,181 @@ export async function removeAllowedOperation(
   return txHash;
 }
 
+/**
+ * Cap how much of an asset may leave a derivation path per rolling window
+ * (user must sign); paths with a threshold use proposeChange instead
+ */
+export async function setSpendingLimit(args: SetSpendingLimitArgs): Promise<string> {
+  const relayer = await getRelayerAccount();
+
+  const result = await relayer.functionCall({
+    contractId: PERMISSION_CONTRACT_ID,
+    methodName: "set_spending_limit",
+    args,
+    gas: GAS_FOR_ADD_OPERATION,
+    attachedDeposit: BigInt(0),
+  });
+
+  const txHash = (result as any).transaction?.hash ||
+    (result as any).transaction_outcome?.id;
+
+  console.log(`[permission] Spending limit set for ${args.asset} (${txHash})`);
+  return txHash;
+}
+
+/**
+ * Remove the spending limit on an asset (user must sign)
+ */
+export async function removeSpendingLimit(args: RemoveSpendingLimitArgs): Promise<string> {
+  const relayer = await getRelayerAccount();
+
+  const result = await relayer.functionCall({
+    contractId: PERMISSION_CONTRACT_ID,
+    methodName: "remove_spending_limit",
+    args,
+    gas: GAS_FOR_REMOVE_OPERATION,
+    attachedDeposit: BigInt(0),
+  });
+
+  const txHash = (result as any).transaction?.hash ||
+    (result as any).transaction_outcome?.id;
+
+  console.log(`[permission] Spending limit removed for ${args.asset} (${txHash})`);
+  return txHash;
+}
+
+/**
+ * Add a destination to, or remove one from, a path's address book (user must sign)
+ * Added destinations can receive funds after a 24 hour delay
+ */
+export async function setDestination(args: DestinationArgs, add: boolean): Promise<string> {
+  const relayer = await getRelayerAccount();
+
+  const result = await relayer.functionCall({
+    contractId: PERMISSION_CONTRACT_ID,
+    methodName: add ? "add_destination" : "remove_destination",
+    args,
+    gas: add ? GAS_FOR_ADD_OPERATION : GAS_FOR_REMOVE_OPERATION,
+    attachedDeposit: BigInt(0),
+  });
+
+  const txHash = (result as any).transaction?.hash ||
+    (result as any).transaction_outcome?.id;
+
+  console.log(`[permission] Destination ${add ? "added" : "removed"}: ${args.address} (${txHash})`);
+  return txHash;
+}
+
+/**
+ * Register the relayer account with a signed registration
+ * The registration is issued by a signing key that a verifier trusted by the
+ * contract admin certified after checking the enclave; the contract checks
+ * that signature chain only, not a TEE quote. Its report data must bind this
+ * account and the key signing the transaction; call again with a fresh
+ * registration before the attestation expires. A relayer no manager
+ * registered can do nothing until a RelayerManager sets its scope
+ */
+export async function registerAttestedRelayer(registration: Uint8Array): Promise<string> {
+  const relayer = await getRelayerAccount();
+
+  const result = await relayer.functionCall({
+    contractId: PERMISSION_CONTRACT_ID,
+    methodName: "register_attested_relayer",
+    args: { registration: Array.from(registration) },
+    gas: GAS_FOR_ATTESTATION,
+    attachedDeposit: BigInt(0),
+  });
+
+  const txHash = (result as any).transaction?.hash ||
+    (result as any).transaction_outcome?.id;
+
+  console.log(`[permission] Relayer attested: ${relayer.accountId} (${txHash})`);
+  return txHash;
+}
+
+/**
+ * Freeze or unfreeze signing for a derivation path (user must sign)
+ */
+export async function setPathFrozen(
+  args: SetPathFrozenArgs,
+  frozen: boolean,
+): Promise<string> {
+  const relayer = await getRelayerAccount();
+
+  const result = await relayer.functionCall({
+    contractId: PERMISSION_CONTRACT_ID,
+    methodName: frozen ? "freeze_path" : "unfreeze_path",
+    args,
+    gas: GAS_FOR_FREEZE,
+    attachedDeposit: BigInt(0),
+  });
+
+  const txHash = (result as any).transaction?.hash ||
+    (result as any).transaction_outcome?.id;
+
+  console.log(`[permission] Path ${frozen ? "frozen" : "unfrozen"}: ${args.derivation_path} (${txHash})`);
+  return txHash;
+}
+
+/**
+ * Remove an owner wallet, or close the path when removing the last one
+ * (signed by the removed wallet or another owner wallet)
+ */
+export async function removeWallet(args: RemoveWalletArgs): Promise<string> {
+  const relayer = await getRelayerAccount();
+
+  const result = await relayer.functionCall({
+    contractId: PERMISSION_CONTRACT_ID,
+    methodName: "remove_wallet",
+    args,
+    gas: GAS_FOR_WALLET_CHANGE,
+    attachedDeposit: BigInt(0),
+  });
+
+  const txHash = (result as any).transaction?.hash ||
+    (result as any).transaction_outcome?.id;
+
+  console.log(`[permission] Wallet removed: ${args.wallet_address} (${txHash})`);
+  return txHash;
+}
+
+/**
+ * Replace an owner wallet with a new key
+ * (signed by the old wallet or another owner wallet, and by the new wallet)
+ */
+export async function rotateWallet(args: RotateWalletArgs): Promise<string> {
+  const relayer = await getRelayerAccount();
+
+  const result = await relayer.functionCall({
+    contractId: PERMISSION_CONTRACT_ID,
+    methodName: "rotate_wallet",
+    args,
+    gas: GAS_FOR_WALLET_CHANGE,
+    attachedDeposit: BigInt(0),
+  });
+
+  const txHash = (result as any).transaction?.hash ||
+    (result as any).transaction_outcome?.id;
+
+  console.log(
+    `[permission] Wallet rotated: ${args.old_address} -> ${args.new_wallet.chain_address} (${txHash})`,
+  );
+  return txHash;
+}
+
 /**
  * Request signature for an allowed operation
  * This is the key method - validates allowlist then calls MPC
  */
 export async function signAllowed(
   args: SignAllowedArgs,
-): Promise<Uint8Array> {
+): Promise<MpcSignature> {
   const relayer = await getRelayerAccount();
 
   console.log(`[permission] Requesting signature for operation ${args.operation_id}`);
   console.log(`[permission] Derivation path: ${args.derivation_path}`);
   console.log(`[permission] Payload length: ${args.payload.length}`);
+  console.log(`[permission] Amount: ${args.amount}`);
 
   const result = await relayer.functionCall({
     contractId: PERMISSION_CONTRACT_ID,
@@ -336,28 +771,15 @@ export async function signAllowed(
     throw new Error("No signature returned from permission contract");
   }
 
-  const signatureData = JSON.parse(Buffer.from(returnValue, "base64").toString("utf8"));
-
-  // Parse signature based on format
-  let signature: Uint8Array;
-  if (typeof signatureData === "string") {
-    // Hex string
-    signature = signatureData.startsWith("0x")
-      ? Buffer.from(signatureData.slice(2), "hex")
-      : Buffer.from(signatureData, "hex");
-  } else if (Array.isArray(signatureData)) {
-    // Byte array
-    signature = new Uint8Array(signatureData);
-  } else if (signatureData.r && signatureData.s) {
-    // r,s format (EdDSA)
-    signature = new Uint8Array(64);
-    signature.set(Buffer.from(signatureData.r, "hex"), 0);
-    signature.set(Buffer.from(signatureData.s, "hex"), 32);
-  } else {
-    throw new Error(`Unknown signature format: ${JSON.stringify(signatureData)}`);
+  // null when MPC signing failed; the contract reverted the execution
+  const signature: MpcSignature | null = JSON.parse(
+    Buffer.from(returnValue, "base64").toString("utf8"),
+  );
+  if (!signature) {
+    throw new Error("MPC signing failed; operation execution was reverted");
   }
 
-  console.log(`[permission] Signature received, length: ${signature.length}`);
+  console.log(`[permission] ${signature.scheme} signature received`);
   return signature;
 }
 
@@ -374,6 +796,7 @@ export function createLimitOrderOperation(params: {
   sourceAsset: string;
   targetAsset: string;
   maxAmount: string;
+  sourceChain: string;
   destinationAddress: string;
   destinationChain: string;
   slippageBps: number;
@@ -390,6 +813,7 @@ export function createLimitOrderOperation(params: {
       target_asset: params.targetAsset,
       max_amount: params.maxAmount,
     },
+    source_chain: params.sourceChain,
     destination_address: params.destinationAddress,
     destination_chain: params.destinationChain,
     slippage_bps: params.slippageBps,
@@ -407,6 +831,7 @@ export function createStopLossOperation(params: {
   sourceAsset: string;
   targetAsset: string;
   maxAmount: string;
+  sourceChain: string;
   destinationAddress: string;
   destinationChain: string;
   slippageBps: number;
@@ -422,6 +847,7 @@ export function createStopLossOperation(params: {
       target_asset: params.targetAsset,
       max_amount: params.maxAmount,
     },
+    source_chain: params.sourceChain,
     destination_address: params.destinationAddress,
     destination_chain: params.destinationChain,
     slippage_bps: params.slippageBps,
@@ -439,6 +865,7 @@ export function createTakeProfitOperation(params: {
   sourceAsset: string;
   targetAsset: string;
   maxAmount: string;
+  sourceChain: string;
   destinationAddress: string;
   destinationChain: string;
   slippageBps: number;
@@ -454,6 +881,7 @@ export function createTakeProfitOperation(params: {
       target_asset: params.targetAsset,
       max_amount: params.maxAmount,
     },
+    source_chain: params.sourceChain,
     destination_address: params.destinationAddress,
     destination_chain: params.destinationChain,
     slippage_bps: params.slippageBps,
@@ -468,6 +896,7 @@ export function createSwapOperation(params: {
   sourceAsset: string;
   targetAsset: string;
   maxAmount: string;
+  sourceChain: string;
   destinationAddress: string;
   destinationChain: string;
   slippageBps: number;
@@ -480,6 +909,7 @@ export function createSwapOperation(params: {
       target_asset: params.targetAsset,
       max_amount: params.maxAmount,
     },
+    source_chain: params.sourceChain,
     destination_address: params.destinationAddress,
     destination_chain: params.destinationChain,
     slippage_bps: params.slippageBps,
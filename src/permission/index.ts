@@ -11,6 +11,7 @@
 export type {
   DerivationPath,
   WalletType,
+  Role,
   PriceCondition,
   AllowedOperationType,
   SwapOperation,
@@ -19,19 +20,59 @@ export type {
   TakeProfitOperation,
   AllowedOperation,
   AllowedOperationInput,
+  RecurringSchedule,
   RegisteredWallet,
   UserPermissionsView,
   RegisterWalletArgs,
   AddAllowedOperationArgs,
   RemoveAllowedOperationArgs,
+  SetPathFrozenArgs,
+  LinkWalletArgs,
+  PathChange,
+  Proposal,
+  ProposeChangeArgs,
+  ApproveProposalArgs,
+  SpendingLimitView,
+  SetSpendingLimitArgs,
+  RemoveSpendingLimitArgs,
+  ApprovedDestination,
+  DestinationArgs,
+  AttestedRelayer,
+  RelayerScope,
+  RemoveWalletArgs,
+  RotateWalletArgs,
   SignAllowedArgs,
+  MpcSignature,
+  StorageBalance,
   GetActiveOperationsResult,
+  Eip712Action,
+  PermissionEvent,
+  PermissionEventLog,
 } from "./types";
 
 export {
   createRegisterWalletMessage,
   createAddOperationMessage,
   createRemoveOperationMessage,
+  createFreezePathMessage,
+  createSetThresholdMessage,
+  createApproveProposalMessage,
+  createSetSpendingLimitMessage,
+  createRemoveSpendingLimitMessage,
+  createDestinationMessage,
+  createLinkWalletMessage,
+  createRemoveWalletMessage,
+  createRotateWalletMessage,
+  createSolanaOffchainMessage,
+  createActionMessage,
+  createNep413Payload,
+  nep413SigningHash,
+  NEP413_TAG,
+  createEip712TypedData,
+  eip712VerifyingContract,
+  EIP712_TYPES,
+  PERMISSION_EVENT_STANDARD,
+  parsePermissionEvent,
 } from "./types";
 
 // Client
@@ -42,11 +83,29 @@ export {
   getActiveOperations,
   isOperationAllowed,
   getDerivationPathForWallet,
+  getPathsForWallet,
+  getProposals,
+  getSpendingLimits,
+  getDestinations,
+  getAttestedRelayer,
+  getRelayerScope,
+  getNextNonce,
+  getStorageBalance,
   // Change methods
   registerWallet,
   addAllowedOperation,
   removeAllowedOperation,
+  setPathFrozen,
+  proposeChange,
+  approveProposal,
+  setSpendingLimit,
+  removeSpendingLimit,
+  setDestination,
+  linkWallet,
+  removeWallet,
+  rotateWallet,
   signAllowed,
+  registerAttestedRelayer,
   // Helpers
   createLimitOrderOperation,
   createStopLossOperation,
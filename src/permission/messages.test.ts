@@ -0,0 +1,68 @@
+/**
+ * Round-trip tests for the signed message builders
+ *
+ * contracts/permission/tests/fixtures/messages.json holds what these builders
+ * produce; the contract's unit tests (message.rs, signature.rs) check the same
+ * file verifies against the actions it describes.
+ */
+
+import { describe, it, expect } from "vitest";
+import { readFileSync } from "fs";
+import { join } from "path";
+import { bytesToHex, hashTypedData, hexToBytes } from "viem";
+import {
+  createActionMessage,
+  createEip712TypedData,
+  createNep413Payload,
+  nep413SigningHash,
+  type Eip712Action,
+} from "./types";
+
+interface MessageFixtures {
+  contract_id: string;
+  actions: { action: Eip712Action; message: string; eip712_digest: string }[];
+  nep413: {
+    message: string;
+    nonce: string;
+    recipient: string;
+    payload: string;
+    signing_hash: string;
+  };
+}
+
+const fixtures: MessageFixtures = JSON.parse(
+  readFileSync(
+    join(__dirname, "../../contracts/permission/tests/fixtures/messages.json"),
+    "utf8",
+  ),
+);
+
+describe("Signed message fixtures", () => {
+  fixtures.actions.forEach(({ action, message, eip712_digest }, i) => {
+    it(`builds the ${action.type} message the contract verifies (#${i})`, () => {
+      expect(createActionMessage(action)).toBe(message);
+    });
+
+    it(`builds the ${action.type} EIP-712 digest the contract rebuilds (#${i})`, () => {
+      const typedData = createEip712TypedData(fixtures.contract_id, action);
+      expect(hashTypedData(typedData as Parameters<typeof hashTypedData>[0])).toBe(eip712_digest);
+    });
+  });
+
+  it("builds the NEP-413 payload the contract decodes", () => {
+    const { nep413 } = fixtures;
+    const payload = createNep413Payload(
+      nep413.message,
+      hexToBytes(`0x${nep413.nonce}`),
+      nep413.recipient,
+    );
+    expect(bytesToHex(payload).slice(2)).toBe(nep413.payload);
+    expect(nep413SigningHash(payload)).toBe(nep413.signing_hash);
+  });
+
+  it("rejects NEP-413 nonces that are not 32 bytes", () => {
+    expect(() => createNep413Payload("msg", new Uint8Array(16), "permission.near")).toThrow(
+      "NEP-413 nonce must be 32 bytes",
+    );
+  });
+});
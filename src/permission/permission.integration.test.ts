@@ -3,10 +3,11 @@
  *
  * These tests verify the full permission flow:
  * 1. Register wallet with Solana signature
- * 2. Add allowed operation with signature
- * 3. Verify operation is allowed
- * 4. Verify unauthorized operations are rejected
- * 5. Remove operation
+ * 2. Approve a destination in the path's address book
+ * 3. Add allowed operation with signature
+ * 4. Verify operation is allowed
+ * 5. Verify unauthorized operations are rejected
+ * 6. Remove operation
  *
  * Run with: RUN_PERMISSION_TESTS=1 npm test -- src/permission/permission.integration.test.ts
  */
@@ -22,6 +23,7 @@ import {
   createRegisterWalletMessage,
   createAddOperationMessage,
   createRemoveOperationMessage,
+  createDestinationMessage,
   type AllowedOperationInput,
 } from "./types";
 
@@ -213,11 +215,11 @@ describe.skipIf(!shouldRun)("Permission Contract Integration", () => {
         "Solana",
         solanaKeypair.publicKey.toBytes(),
         solanaKeypair.publicKey.toBase58(),
-        1,
+        0,
       );
 
       // Message is now a plain string format (not JSON)
-      expect(message).toBe(`Register wallet for derivation path: ${derivationPath} with nonce: 1`);
+      expect(message).toBe(`Register wallet for derivation path: ${derivationPath} with nonce: 0`);
     });
 
     it("should sign registration message with Solana key", () => {
@@ -226,7 +228,7 @@ describe.skipIf(!shouldRun)("Permission Contract Integration", () => {
         "Solana",
         solanaKeypair.publicKey.toBytes(),
         solanaKeypair.publicKey.toBase58(),
-        1,
+        0,
       );
 
       const signature = signWithSolana(message, solanaKeypair);
@@ -248,7 +250,7 @@ describe.skipIf(!shouldRun)("Permission Contract Integration", () => {
         "Solana",
         solanaKeypair.publicKey.toBytes(),
         solanaKeypair.publicKey.toBase58(),
-        1,
+        0,
       );
 
       const signature = signWithSolana(message, solanaKeypair);
@@ -261,6 +263,27 @@ describe.skipIf(!shouldRun)("Permission Contract Integration", () => {
         chain_address: solanaKeypair.publicKey.toBase58(),
         signature: Array.from(signature),
         message: Array.from(messageBytes),
+        nonce: 0,
+      });
+
+      expect(result.success).toBe(true);
+    });
+  });
+
+  describe("Address Book", () => {
+    it("should add the wallet as a destination", async () => {
+      const address = solanaKeypair.publicKey.toBase58();
+      const message = createDestinationMessage(derivationPath, true, "solana", address, 1);
+      const signature = signWithSolana(message, solanaKeypair);
+      const messageBytes = new TextEncoder().encode(message);
+
+      const result = await nearCall("add_destination", {
+        derivation_path: derivationPath,
+        chain: "solana",
+        address,
+        signature: Array.from(signature),
+        message: Array.from(messageBytes),
+        signer_address: address,
         nonce: 1,
       });
 
@@ -283,12 +306,13 @@ describe.skipIf(!shouldRun)("Permission Contract Integration", () => {
           target_asset: "SOL",
           max_amount: "100000000", // 100 USDC
         },
+        source_chain: "solana",
         destination_address: solanaKeypair.publicKey.toBase58(),
         destination_chain: "solana",
         slippage_bps: 100, // 1%
       };
 
-      // Get current nonce (should be 2 after registration)
+      // Nonces start at 0: registration used 0, the destination 1
       const message = createAddOperationMessage(derivationPath, operation, 2);
       const signature = signWithSolana(message, solanaKeypair);
       const messageBytes = new TextEncoder().encode(message);
@@ -299,6 +323,7 @@ describe.skipIf(!shouldRun)("Permission Contract Integration", () => {
         signature: Array.from(signature),
         message: Array.from(messageBytes),
         signer_address: solanaKeypair.publicKey.toBase58(),
+        nonce: 2,
       });
 
       expect(result.success).toBe(true);
@@ -364,6 +389,7 @@ describe.skipIf(!shouldRun)("Permission Contract Integration", () => {
           target_asset: "SOL",
           max_amount: "50000000",
         },
+        source_chain: "solana",
         destination_address: solanaKeypair.publicKey.toBase58(),
         destination_chain: "solana",
         slippage_bps: 50,
@@ -381,6 +407,7 @@ describe.skipIf(!shouldRun)("Permission Contract Integration", () => {
         signature: Array.from(invalidSignature),
         message: Array.from(messageBytes),
         signer_address: solanaKeypair.publicKey.toBase58(),
+        nonce: 3,
       });
 
       // Should fail due to invalid signature
@@ -398,6 +425,7 @@ describe.skipIf(!shouldRun)("Permission Contract Integration", () => {
           target_asset: "SOL",
           max_amount: "50000000",
         },
+        source_chain: "solana",
         destination_address: unregisteredKeypair.publicKey.toBase58(),
         destination_chain: "solana",
         slippage_bps: 50,
@@ -413,6 +441,7 @@ describe.skipIf(!shouldRun)("Permission Contract Integration", () => {
         signature: Array.from(signature),
         message: Array.from(messageBytes),
         signer_address: unregisteredKeypair.publicKey.toBase58(),
+        nonce: 99,
       });
 
       // Should fail because the signer is not authorized for this derivation path
@@ -433,8 +462,8 @@ describe.skipIf(!shouldRun)("Permission Contract Integration", () => {
         removedOperationId = opId;
         console.log("Removing operation:", opId);
 
-        // Create remove message with next nonce
-        const message = createRemoveOperationMessage(derivationPath, opId, 4);
+        // The rejected add above did not consume nonce 3
+        const message = createRemoveOperationMessage(derivationPath, opId, 3);
         const signature = signWithSolana(message, solanaKeypair);
         const messageBytes = new TextEncoder().encode(message);
 
@@ -444,6 +473,7 @@ describe.skipIf(!shouldRun)("Permission Contract Integration", () => {
           signature: Array.from(signature),
           message: Array.from(messageBytes),
           signer_address: solanaKeypair.publicKey.toBase58(),
+          nonce: 3,
         });
 
         console.log("Remove result:", result);
@@ -489,7 +519,7 @@ describe.skipIf(!shouldRun)("MPC Signing Flow", () => {
       "Solana",
       testKeypair.publicKey.toBytes(),
       testKeypair.publicKey.toBase58(),
-      1,
+      0,
     );
 
     const signature = signWithSolana(message, testKeypair);
@@ -502,13 +532,32 @@ describe.skipIf(!shouldRun)("MPC Signing Flow", () => {
       chain_address: testKeypair.publicKey.toBase58(),
       signature: Array.from(signature),
       message: Array.from(messageBytes),
-      nonce: 1,
+      nonce: 0,
     });
 
     console.log("MPC test - wallet registered:", result.success);
     expect(result.success).toBe(true);
   }, 30000); // 30 second timeout for blockchain calls
 
+  it("should add a destination for MPC test", async () => {
+    const address = testKeypair.publicKey.toBase58();
+    const message = createDestinationMessage(testDerivationPath, true, "solana", address, 1);
+    const signature = signWithSolana(message, testKeypair);
+    const messageBytes = new TextEncoder().encode(message);
+
+    const result = await nearCall("add_destination", {
+      derivation_path: testDerivationPath,
+      chain: "solana",
+      address,
+      signature: Array.from(signature),
+      message: Array.from(messageBytes),
+      signer_address: address,
+      nonce: 1,
+    });
+
+    expect(result.success).toBe(true);
+  }, 30000);
+
   it("should add a Swap operation for MPC test", async () => {
     const operation: AllowedOperationInput = {
       operation_type: {
@@ -517,6 +566,7 @@ describe.skipIf(!shouldRun)("MPC Signing Flow", () => {
         target_asset: "SOL",
         max_amount: "1000000", // 1 USDC
       },
+      source_chain: "solana",
       destination_address: testKeypair.publicKey.toBase58(),
       destination_chain: "solana",
       slippage_bps: 100,
@@ -532,6 +582,7 @@ describe.skipIf(!shouldRun)("MPC Signing Flow", () => {
       signature: Array.from(signature),
       message: Array.from(messageBytes),
       signer_address: testKeypair.publicKey.toBase58(),
+      nonce: 2,
     });
 
     console.log("MPC test - operation added:", result.success);
@@ -550,7 +601,9 @@ describe.skipIf(!shouldRun)("MPC Signing Flow", () => {
     expect(testOperationId).toBeDefined();
   }, 30000); // 30 second timeout
 
-  it("should call sign_allowed and get MPC signature", async () => {
+  // A fresh destination activates DESTINATION_ACTIVATION_DELAY_NS (24h) after
+  // it is added, so a path created by this run cannot be signed for yet
+  it("should refuse sign_allowed while the destination is pending", async () => {
     if (!testOperationId) {
       console.log("Skipping - no operation ID from previous test");
       return;
@@ -576,17 +629,16 @@ describe.skipIf(!shouldRun)("MPC Signing Flow", () => {
       operation_id: testOperationId,
       payload: Array.from(testPayload),
       key_type: "Eddsa",
-      tee_price: null,
-      tee_timestamp: null,
+      amount: "1000000", // Whole budget, so the operation completes
     }, BigInt("300000000000000")); // 300 TGas for MPC cross-contract call
 
     console.log("MPC test - sign_allowed result:", result);
 
-    // The call should succeed - MPC contract signs and returns signature
-    expect(result.success).toBe(true);
+    // Rejected before any MPC call is made
+    expect(result.success).toBe(false);
   }, 60000); // 60 second timeout for MPC call
 
-  it("should mark operation as executed after signing", async () => {
+  it("should leave the operation allowed after the refused signing", async () => {
     if (!testOperationId) {
       console.log("Skipping - no operation ID");
       return;
@@ -603,9 +655,8 @@ describe.skipIf(!shouldRun)("MPC Signing Flow", () => {
 
     console.log("MPC test - operation still allowed:", allowed);
 
-    // After sign_allowed, the operation should be marked as executed
-    // so is_operation_allowed should return false
-    expect(allowed).toBe(false);
+    // The refused sign_allowed must not consume the operation
+    expect(allowed).toBe(true);
   }, 30000); // 30 second timeout
 });
 
@@ -2,13 +2,30 @@
  * Permission contract types - matching contracts/permission/src/types.rs
  */
 
+import {
+  bytesToHex,
+  concatBytes,
+  keccak256,
+  sha256,
+  stringToBytes,
+  type Address,
+  type Hex,
+} from "viem";
+
 // ─── Basic Types ────────────────────────────────────────────────────────────────
 
 /** Derivation path for MPC key (e.g., "solana-1,user-xyz") */
 export type DerivationPath = string;
 
-/** Supported wallet types for signature verification */
-export type WalletType = "Near" | "Solana" | "Evm";
+/**
+ * Supported wallet types for signature verification
+ * "Evm" signs the text messages below with personal_sign;
+ * "Eip712" signs the typed data from createEip712TypedData (eth_signTypedData_v4)
+ */
+export type WalletType = "Near" | "Solana" | "Evm" | "Eip712";
+
+/** Contract roles; the owner holds every role implicitly */
+export type Role = "Admin" | "RelayerManager" | "Pauser" | "ConfigManager";
 
 /** Price condition for triggering operations */
 export type PriceCondition = "Above" | "Below";
@@ -26,7 +43,9 @@ export interface LimitOrderOperation {
   type: "LimitOrder";
   price_asset: string;
   quote_asset: string;
-  trigger_price: string; // U128 as string
+  // U128 as string: quote_asset per price_asset scaled by 10^8, checked
+  // against the contract's price oracle when the operation is signed
+  trigger_price: string;
   condition: PriceCondition;
   source_asset: string;
   target_asset: string;
@@ -61,25 +80,41 @@ export type AllowedOperationType =
 
 // ─── Allowed Operation ──────────────────────────────────────────────────────────
 
+/** Repeat schedule for a recurring (DCA) operation */
+export interface RecurringSchedule {
+  interval: number; // Minimum nanoseconds between executions
+  max_executions: number;
+  amount_per_execution: string; // U128 as string; at most max_amount
+  start_at: number; // nanoseconds
+  end_at?: number; // nanoseconds
+}
+
 export interface AllowedOperation {
   operation_id: string;
   derivation_path: DerivationPath;
   operation_type: AllowedOperationType;
+  source_chain: string; // Chain whose transactions the operation signs
   destination_address: string;
   destination_chain: string;
   slippage_bps: number;
   expires_at?: number; // nanoseconds
-  executed: boolean;
+  executed: boolean; // Budget exhausted, or recurring: all executions used
   nonce: number;
   created_at: number; // nanoseconds
+  recurring?: RecurringSchedule;
+  execution_count: number;
+  last_executed_at?: number; // nanoseconds
+  consumed_amount: string; // Source asset spent so far out of max_amount
 }
 
 export interface AllowedOperationInput {
   operation_type: AllowedOperationType;
+  source_chain: string; // Chain whose transactions the operation signs, e.g. "near"
   destination_address: string;
   destination_chain: string;
   slippage_bps: number;
   expires_at?: number; // nanoseconds
+  recurring?: RecurringSchedule; // Omit for one-shot operations
 }
 
 // ─── Registered Wallet ──────────────────────────────────────────────────────────
@@ -96,17 +131,112 @@ export interface UserPermissionsView {
   owner_wallets: RegisteredWallet[];
   operations: AllowedOperation[];
   next_nonce: number;
+  frozen: boolean; // Signing blocked by an owner wallet
+  threshold: number; // Owner wallet approvals needed for allowlist changes
+}
+
+// ─── Proposals ──────────────────────────────────────────────────────────────────
+
+/** Allowlist change that needs the path's approval threshold */
+export type PathChange =
+  | { type: "AddOperation"; operation: AllowedOperationInput }
+  | { type: "RemoveOperation"; operation_id: string }
+  | { type: "SetThreshold"; threshold: number }
+  | { type: "SetSpendingLimit"; asset: string; limit: string; window: number }
+  | { type: "RemoveSpendingLimit"; asset: string }
+  | { type: "AddDestination"; chain: string; address: string }
+  | { type: "RemoveDestination"; chain: string; address: string }
+  | { type: "Unfreeze" }
+  | {
+    type: "LinkWallet";
+    new_wallet: RegisteredWallet;
+    new_wallet_signature: number[];
+    new_wallet_message: SignedMessageBytes;
+  }
+  | { type: "RemoveWallet"; wallet_address: string }
+  | {
+    type: "RotateWallet";
+    old_address: string;
+    new_wallet: RegisteredWallet;
+    new_wallet_signature: number[];
+    new_wallet_message: SignedMessageBytes;
+  };
+
+export interface Proposal {
+  proposal_id: string; // Hex sha256 of path, change, proposer and nonce
+  change: PathChange;
+  approvals: string[]; // Approving owner wallets, proposer first
+  expires_at: number; // Nanoseconds
+}
+
+// ─── Spending Limits ────────────────────────────────────────────────────────────
+
+/** Rolling-window cap on a source asset leaving a path, with current usage */
+export interface SpendingLimitView {
+  asset: string;
+  limit: string; // U128 as string
+  window: number; // Nanoseconds
+  used: string; // Spent within the window ending now
+  remaining: string;
+}
+
+// ─── Address Book ───────────────────────────────────────────────────────────────
+
+/**
+ * Approved destination for a path's operations
+ * add_allowed_operation and sign_allowed reject destinations not in the book,
+ * so a path needs one before it can add operations
+ */
+export interface ApprovedDestination {
+  chain: string; // Matched case-insensitively
+  address: string;
+  active_at: number; // Nanoseconds; new destinations activate after 24 hours
+}
+
+/**
+ * Relayer registered with a signed registration from a trusted verifier
+ * (not an on-chain checked TEE quote)
+ * Valid until expires_at while its measurement stays approved
+ */
+export interface AttestedRelayer {
+  public_key: string; // NEAR access key, e.g. "ed25519:..."
+  measurement: string; // 48-byte code measurement as hex
+  compose_hash: string; // 32-byte app compose hash as hex
+  expires_at: number; // Nanoseconds; re-attest before this
+}
+
+/**
+ * What a TEE relayer may do; relayers registered without one are unrestricted
+ */
+export interface RelayerScope {
+  methods: string[]; // Relayer methods, e.g. "sign_allowed"
+  operation_types: AllowedOperationType["type"][];
+  key_types: ("Eddsa" | "Ecdsa")[];
+  path_prefixes: string[] | null; // Whole ","-separated segments; null allows every derivation path
 }
 
 // ─── Method Arguments ───────────────────────────────────────────────────────────
 
+/**
+ * `message` bytes submitted alongside a user signature:
+ * - Solana / Evm: UTF-8 bytes of the message the wallet signed
+ * - Solana (Ledger / off-chain signing): the full off-chain message envelope
+ *   from createSolanaOffchainMessage, exactly as signed
+ * - Eip712: unused; the contract rebuilds the typed data from the call
+ * - Near: borsh-serialized NEP-413 payload `{ message, nonce: [u8; 32],
+ *   recipient, callbackUrl }` exactly as passed to the wallet's signMessage.
+ *   `recipient` must be the permission contract ID and each 32-byte nonce
+ *   can only be used once per wallet.
+ */
+export type SignedMessageBytes = number[];
+
 export interface RegisterWalletArgs {
   derivation_path: DerivationPath;
   wallet_type: WalletType;
   public_key: number[]; // Vec<u8> as array
   chain_address: string;
   signature: number[]; // Vec<u8> as array
-  message: number[]; // Vec<u8> as array
+  message: SignedMessageBytes;
   nonce: number;
 }
 
@@ -114,27 +244,159 @@ export interface AddAllowedOperationArgs {
   derivation_path: DerivationPath;
   operation: AllowedOperationInput;
   signature: number[];
-  message: number[];
+  message: SignedMessageBytes;
   signer_address: string;
+  nonce: number; // Must match the nonce in the signed message
 }
 
 export interface RemoveAllowedOperationArgs {
   derivation_path: DerivationPath;
   operation_id: string;
   signature: number[];
-  message: number[];
+  message: SignedMessageBytes;
+  signer_address: string;
+  nonce: number; // Must match the nonce in the signed message
+}
+
+/** Arguments for freeze_path / unfreeze_path; any caller may submit them */
+export interface SetPathFrozenArgs {
+  derivation_path: DerivationPath;
+  signature: number[];
+  message: SignedMessageBytes;
+  signer_address: string;
+  nonce: number; // Must match the nonce in the signed message
+}
+
+/**
+ * Arguments for link_wallet
+ * The new wallet and an existing owner wallet each sign the same link message
+ */
+export interface LinkWalletArgs {
+  derivation_path: DerivationPath;
+  new_wallet: RegisteredWallet;
+  new_wallet_signature: number[];
+  new_wallet_message: SignedMessageBytes;
+  signature: number[];
+  message: SignedMessageBytes;
+  signer_address: string; // Existing owner wallet giving consent
+  nonce: number; // Owner wallet nonce; must match the signed message
+}
+
+/**
+ * Arguments for propose_change
+ * The proposer signs the same message as for the direct change
+ * (createAddOperationMessage, createRemoveOperationMessage, createSetThresholdMessage,
+ * createSetSpendingLimitMessage, createRemoveSpendingLimitMessage, createDestinationMessage,
+ * createFreezePathMessage with frozen false, createLinkWalletMessage,
+ * createRemoveWalletMessage with closePath false or createRotateWalletMessage).
+ * LinkWallet and RotateWallet also carry the new wallet's signature over that message.
+ */
+export interface ProposeChangeArgs {
+  derivation_path: DerivationPath;
+  change: PathChange;
+  signature: number[];
+  message: SignedMessageBytes;
+  signer_address: string;
+  nonce: number; // Must match the nonce in the signed message
+}
+
+/** Arguments for approve_proposal; signed with createApproveProposalMessage */
+export interface ApproveProposalArgs {
+  derivation_path: DerivationPath;
+  proposal_id: string;
+  signature: number[];
+  message: SignedMessageBytes;
+  signer_address: string;
+  nonce: number; // Must match the nonce in the signed message
+}
+
+/** Arguments for set_spending_limit; signed with createSetSpendingLimitMessage */
+export interface SetSpendingLimitArgs {
+  derivation_path: DerivationPath;
+  asset: string; // Source asset as used in operations
+  limit: string; // U128 as string
+  window: number; // Rolling window in nanoseconds
+  signature: number[];
+  message: SignedMessageBytes;
+  signer_address: string;
+  nonce: number; // Must match the nonce in the signed message
+}
+
+/** Arguments for remove_spending_limit; signed with createRemoveSpendingLimitMessage */
+export interface RemoveSpendingLimitArgs {
+  derivation_path: DerivationPath;
+  asset: string;
+  signature: number[];
+  message: SignedMessageBytes;
   signer_address: string;
+  nonce: number; // Must match the nonce in the signed message
+}
+
+/**
+ * Arguments for add_destination / remove_destination; signed with
+ * createDestinationMessage. The last destination cannot be removed.
+ */
+export interface DestinationArgs {
+  derivation_path: DerivationPath;
+  chain: string;
+  address: string;
+  signature: number[];
+  message: SignedMessageBytes;
+  signer_address: string;
+  nonce: number; // Must match the nonce in the signed message
+}
+
+/** Arguments for remove_wallet; close_path is required to remove the last wallet */
+export interface RemoveWalletArgs {
+  derivation_path: DerivationPath;
+  wallet_address: string;
+  close_path: boolean;
+  signature: number[];
+  message: SignedMessageBytes;
+  signer_address: string;
+  nonce: number; // Must match the nonce in the signed message
+}
+
+/**
+ * Arguments for rotate_wallet; the signer may be the old wallet or another owner.
+ * The new wallet signs the same createRotateWalletMessage to prove it holds the key.
+ */
+export interface RotateWalletArgs {
+  derivation_path: DerivationPath;
+  old_address: string;
+  new_wallet: RegisteredWallet;
+  new_wallet_signature: number[];
+  new_wallet_message: SignedMessageBytes;
+  signature: number[];
+  message: SignedMessageBytes;
+  signer_address: string;
+  nonce: number; // Must match the nonce in the signed message
 }
 
 export interface SignAllowedArgs {
   derivation_path: DerivationPath;
   operation_id: string;
-  payload: number[]; // Transaction bytes
+  // Eddsa: serialized Solana message, or the borsh-serialized NEAR transaction
+  // when the operation's source_chain is "near"; Ecdsa: unsigned RLP
+  // transaction (legacy EIP-155, EIP-2930 or EIP-1559). The contract checks it
+  // against the operation and hashes NEAR/EVM transactions itself.
+  payload: number[];
   key_type: "Eddsa" | "Ecdsa";
-  tee_price?: string; // U128 as string
-  tee_timestamp?: number;
+  // Source asset this payload spends; must fit the operation's remaining
+  // budget (and recurring amount_per_execution). The operation completes
+  // once max_amount has been consumed.
+  amount: string;
 }
 
+/**
+ * Signature returned by sign_allowed
+ * Secp256k1: r and s as 32-byte hex, v the recovery ID (0 or 1);
+ * Ed25519: 64-byte signature as hex
+ */
+export type MpcSignature =
+  | { scheme: "Secp256k1"; r: string; s: string; v: number }
+  | { scheme: "Ed25519"; signature: string };
+
 // ─── Query Results ──────────────────────────────────────────────────────────────
 
 export interface GetActiveOperationsResult {
@@ -142,6 +404,149 @@ export interface GetActiveOperationsResult {
   operation: AllowedOperation;
 }
 
+/** NEP-145 storage balance of an account sponsoring derivation paths */
+export interface StorageBalance {
+  total: string; // U128 as string
+  available: string; // U128 as string
+}
+
+// ─── Events ─────────────────────────────────────────────────────────────────────
+
+/** NEP-297 standard name of permission contract events */
+export const PERMISSION_EVENT_STANDARD = "shadelink-permission";
+
+/** State change logged by the contract as `EVENT_JSON:{...}` */
+export type PermissionEvent =
+  | { event: "tee_relayer_registered"; data: { relayer_account: string } }
+  | { event: "tee_relayer_removed"; data: { relayer_account: string } }
+  | { event: "relayer_scope_set"; data: { relayer_account: string; scope: RelayerScope } }
+  | {
+      event: "relayer_attested";
+      data: {
+        relayer_account: string;
+        public_key: string;
+        measurement: string;
+        compose_hash: string;
+        expires_at: number;
+      };
+    }
+  | { event: "measurement_approved"; data: { measurement: string; compose_hash: string } }
+  | { event: "measurement_revoked"; data: { measurement: string; compose_hash: string } }
+  | { event: "attestation_root_added"; data: { public_key: string } }
+  | { event: "attestation_root_removed"; data: { public_key: string } }
+  | { event: "attestation_requirement_set"; data: { required: boolean } }
+  | { event: "mpc_contract_updated"; data: { mpc_contract: string } }
+  | { event: "contract_updated"; data: { code_hash: string } }
+  | { event: "role_granted"; data: { account_id: string; role: Role } }
+  | { event: "role_revoked"; data: { account_id: string; role: Role } }
+  | { event: "ownership_proposed"; data: { owner: string; pending_owner: string } }
+  | { event: "ownership_transferred"; data: { previous_owner: string; owner: string } }
+  | { event: "storage_deposited"; data: { account_id: string; amount: string } }
+  | { event: "storage_withdrawn"; data: { account_id: string; amount: string } }
+  | { event: "paused"; data: { account_id: string } }
+  | { event: "unpaused"; data: { account_id: string } }
+  | { event: "solana_program_allowed"; data: { program_id: string } }
+  | { event: "solana_program_removed"; data: { program_id: string } }
+  | { event: "near_method_allowed"; data: { receiver_id: string; method_name: string } }
+  | { event: "near_method_removed"; data: { receiver_id: string; method_name: string } }
+  | { event: "price_oracle_set"; data: { price_oracle: string } }
+  | { event: "price_feed_set"; data: { asset: string; price_id: string } }
+  | { event: "price_feed_removed"; data: { asset: string } }
+  | {
+      event: "wallet_registered";
+      data: { derivation_path: DerivationPath; wallet_type: WalletType; chain_address: string };
+    }
+  | { event: "operation_added"; data: { derivation_path: DerivationPath; operation: AllowedOperation } }
+  | { event: "operation_removed"; data: { derivation_path: DerivationPath; operation_id: string } }
+  | {
+      event: "wallet_linked";
+      data: {
+        derivation_path: DerivationPath;
+        wallet_type: WalletType;
+        chain_address: string;
+        signer_address: string;
+      };
+    }
+  | {
+      event: "wallet_removed";
+      data: { derivation_path: DerivationPath; chain_address: string; signer_address: string };
+    }
+  | {
+      event: "wallet_rotated";
+      data: {
+        derivation_path: DerivationPath;
+        old_address: string;
+        new_address: string;
+        signer_address: string;
+      };
+    }
+  | { event: "path_closed"; data: { derivation_path: DerivationPath } }
+  | {
+      event: "proposal_created";
+      data: {
+        derivation_path: DerivationPath;
+        proposal_id: string;
+        change: PathChange;
+        signer_address: string;
+      };
+    }
+  | {
+      event: "proposal_approved";
+      data: { derivation_path: DerivationPath; proposal_id: string; signer_address: string };
+    }
+  | { event: "proposal_executed"; data: { derivation_path: DerivationPath; proposal_id: string } }
+  | { event: "threshold_changed"; data: { derivation_path: DerivationPath; threshold: number } }
+  | {
+      event: "spending_limit_set";
+      data: { derivation_path: DerivationPath; asset: string; limit: string; window: number };
+    }
+  | { event: "spending_limit_removed"; data: { derivation_path: DerivationPath; asset: string } }
+  | {
+      event: "destination_added";
+      data: { derivation_path: DerivationPath; chain: string; address: string; active_at: number };
+    }
+  | {
+      event: "destination_removed";
+      data: { derivation_path: DerivationPath; chain: string; address: string };
+    }
+  | { event: "path_frozen"; data: { derivation_path: DerivationPath; signer_address: string } }
+  | { event: "path_unfrozen"; data: { derivation_path: DerivationPath; signer_address: string } }
+  | {
+      event: "operation_executed";
+      data: {
+        derivation_path: DerivationPath;
+        operation_id: string;
+        execution_count: number;
+        amount: string;
+        consumed_amount: string;
+      };
+    }
+  | { event: "mpc_signature_received"; data: { derivation_path: DerivationPath; operation_id: string } }
+  | {
+      event: "mpc_signature_failed";
+      data: { derivation_path: DerivationPath; operation_id: string; error: string };
+    };
+
+export type PermissionEventLog = PermissionEvent & {
+  standard: typeof PERMISSION_EVENT_STANDARD;
+  version: string;
+};
+
+/**
+ * Parse a contract log line; returns null for logs that are not permission events
+ */
+export function parsePermissionEvent(log: string): PermissionEventLog | null {
+  if (!log.startsWith("EVENT_JSON:")) {
+    return null;
+  }
+  try {
+    const parsed = JSON.parse(log.slice("EVENT_JSON:".length));
+    return parsed.standard === PERMISSION_EVENT_STANDARD ? parsed : null;
+  } catch {
+    return null;
+  }
+}
+
 // ─── Message Construction ───────────────────────────────────────────────────────
 
 /**
@@ -160,6 +565,8 @@ export function createRegisterWalletMessage(
 
 /**
  * Create message for adding an allowed operation
+ * The contract parses this JSON and rejects it unless action, derivation path,
+ * operation and nonce all match the submitted call arguments
  */
 export function createAddOperationMessage(
   derivationPath: string,
@@ -176,6 +583,8 @@ export function createAddOperationMessage(
 
 /**
  * Create message for removing an allowed operation
+ * The contract parses this JSON and rejects it unless action, derivation path,
+ * operation ID and nonce all match the submitted call arguments
  */
 export function createRemoveOperationMessage(
   derivationPath: string,
@@ -189,3 +598,662 @@ export function createRemoveOperationMessage(
     nonce,
   });
 }
+
+/**
+ * Create message for freezing or unfreezing a derivation path
+ * The contract parses this JSON and rejects it unless action, derivation path
+ * and nonce all match the submitted call arguments
+ */
+export function createFreezePathMessage(
+  derivationPath: string,
+  frozen: boolean,
+  nonce: number,
+): string {
+  return JSON.stringify({
+    action: frozen ? "freeze_path" : "unfreeze_path",
+    derivation_path: derivationPath,
+    nonce,
+  });
+}
+
+/**
+ * Create message for changing a path's approval threshold
+ * Submit it with propose_change; it applies once the current threshold approves
+ */
+export function createSetThresholdMessage(
+  derivationPath: string,
+  threshold: number,
+  nonce: number,
+): string {
+  return JSON.stringify({
+    action: "set_threshold",
+    derivation_path: derivationPath,
+    threshold,
+    nonce,
+  });
+}
+
+/**
+ * Create message for approving a pending proposal
+ * The contract rejects it unless derivation path, proposal ID and nonce match
+ */
+export function createApproveProposalMessage(
+  derivationPath: string,
+  proposalId: string,
+  nonce: number,
+): string {
+  return JSON.stringify({
+    action: "approve_proposal",
+    derivation_path: derivationPath,
+    proposal_id: proposalId,
+    nonce,
+  });
+}
+
+/**
+ * Create message for capping how much of `asset` may leave a path per
+ * rolling `window` (nanoseconds, at most 31 days), across all operations
+ */
+export function createSetSpendingLimitMessage(
+  derivationPath: string,
+  asset: string,
+  limit: string,
+  window: number,
+  nonce: number,
+): string {
+  return JSON.stringify({
+    action: "set_spending_limit",
+    derivation_path: derivationPath,
+    asset,
+    limit,
+    window,
+    nonce,
+  });
+}
+
+/**
+ * Create message for removing the spending limit on `asset`
+ */
+export function createRemoveSpendingLimitMessage(
+  derivationPath: string,
+  asset: string,
+  nonce: number,
+): string {
+  return JSON.stringify({
+    action: "remove_spending_limit",
+    derivation_path: derivationPath,
+    asset,
+    nonce,
+  });
+}
+
+/**
+ * Create message for adding a destination to, or removing one from, a path's address book
+ */
+export function createDestinationMessage(
+  derivationPath: string,
+  add: boolean,
+  chain: string,
+  address: string,
+  nonce: number,
+): string {
+  return JSON.stringify({
+    action: add ? "add_destination" : "remove_destination",
+    derivation_path: derivationPath,
+    chain,
+    address,
+    nonce,
+  });
+}
+
+/**
+ * Create message for adding a wallet to an existing derivation path
+ * Both the new wallet and the consenting owner wallet (`signerAddress`)
+ * sign this message; the nonce is the owner wallet's
+ */
+export function createLinkWalletMessage(
+  derivationPath: string,
+  newWallet: RegisteredWallet,
+  signerAddress: string,
+  nonce: number,
+): string {
+  return JSON.stringify({
+    action: "link_wallet",
+    derivation_path: derivationPath,
+    new_wallet: {
+      wallet_type: newWallet.wallet_type,
+      public_key: bytesToHex(Uint8Array.from(newWallet.public_key)).slice(2),
+      chain_address: newWallet.chain_address,
+    },
+    signer_address: signerAddress,
+    nonce,
+  });
+}
+
+/**
+ * Create message for removing an owner wallet
+ * The contract parses this JSON and rejects it unless action, derivation path,
+ * wallet, close flag and nonce all match the submitted call arguments
+ */
+export function createRemoveWalletMessage(
+  derivationPath: string,
+  walletAddress: string,
+  closePath: boolean,
+  nonce: number,
+): string {
+  return JSON.stringify({
+    action: "remove_wallet",
+    derivation_path: derivationPath,
+    wallet_address: walletAddress,
+    close_path: closePath,
+    nonce,
+  });
+}
+
+/**
+ * Create message for replacing an owner wallet with a new key
+ * The public key is hex encoded; the contract rejects the message unless
+ * the old address and every field of the new wallet match the call arguments
+ */
+export function createRotateWalletMessage(
+  derivationPath: string,
+  oldAddress: string,
+  newWallet: RegisteredWallet,
+  nonce: number,
+): string {
+  return JSON.stringify({
+    action: "rotate_wallet",
+    derivation_path: derivationPath,
+    old_address: oldAddress,
+    new_wallet: {
+      wallet_type: newWallet.wallet_type,
+      public_key: bytesToHex(Uint8Array.from(newWallet.public_key)).slice(2),
+      chain_address: newWallet.chain_address,
+    },
+    nonce,
+  });
+}
+
+/**
+ * Create the JSON (or, for RegisterWallet, text) message a Solana, NEAR or
+ * EVM wallet signs for `action`; Eip712 wallets sign createEip712TypedData
+ * for the same action instead
+ */
+export function createActionMessage(action: Eip712Action): string {
+  const path = action.derivationPath;
+  switch (action.type) {
+    case "RegisterWallet":
+      return `Register wallet for derivation path: ${path} with nonce: ${action.nonce}`;
+    case "AddOperation":
+      return createAddOperationMessage(path, action.operation, action.nonce);
+    case "RemoveOperation":
+      return createRemoveOperationMessage(path, action.operationId, action.nonce);
+    case "FreezePath":
+    case "UnfreezePath":
+      return createFreezePathMessage(path, action.type === "FreezePath", action.nonce);
+    case "SetThreshold":
+      return createSetThresholdMessage(path, action.threshold, action.nonce);
+    case "ApproveProposal":
+      return createApproveProposalMessage(path, action.proposalId, action.nonce);
+    case "SetSpendingLimit":
+      return createSetSpendingLimitMessage(
+        path,
+        action.asset,
+        action.limit,
+        action.window,
+        action.nonce,
+      );
+    case "RemoveSpendingLimit":
+      return createRemoveSpendingLimitMessage(path, action.asset, action.nonce);
+    case "AddDestination":
+    case "RemoveDestination":
+      return createDestinationMessage(
+        path,
+        action.type === "AddDestination",
+        action.chain,
+        action.address,
+        action.nonce,
+      );
+    case "LinkWallet":
+      return createLinkWalletMessage(path, action.newWallet, action.signerAddress, action.nonce);
+    case "RemoveWallet":
+      return createRemoveWalletMessage(path, action.walletAddress, action.closePath, action.nonce);
+    case "RotateWallet":
+      return createRotateWalletMessage(path, action.oldAddress, action.newWallet, action.nonce);
+  }
+}
+
+/**
+ * Wrap a message in the Solana off-chain message format (version 0):
+ * "\xffsolana offchain" || version || format || u16 LE length || body
+ * Format is restricted ASCII when possible, otherwise limited UTF-8
+ * (extended UTF-8 above 1212 bytes); must match contracts/permission/src/signature.rs
+ */
+export function createSolanaOffchainMessage(message: string): Uint8Array {
+  const body = new TextEncoder().encode(message);
+  const domain = new Uint8Array([0xff, ...new TextEncoder().encode("solana offchain")]);
+  const isAscii = body.every((b) => b >= 0x20 && b <= 0x7e);
+  const format = body.length > 1212 ? 2 : isAscii ? 0 : 1;
+
+  const out = new Uint8Array(domain.length + 4 + body.length);
+  out.set(domain, 0);
+  out[16] = 0; // version
+  out[17] = format;
+  out[18] = body.length & 0xff;
+  out[19] = body.length >> 8;
+  out.set(body, 20);
+  return out;
+}
+
+// ─── NEP-413 ────────────────────────────────────────────────────────────────────
+// Must match Nep413Payload in contracts/permission/src/signature.rs
+
+/** NEP-413 tag, 2^31 + 413, prefixed to the payload before hashing */
+export const NEP413_TAG = 2_147_484_061;
+
+/**
+ * Borsh-serialize a NEP-413 signMessage payload; "Near" wallets submit these
+ * bytes as `message`. `recipient` must be the permission contract and
+ * `nonce` 32 random bytes, each usable once per wallet
+ */
+export function createNep413Payload(
+  message: string,
+  nonce: Uint8Array,
+  recipient: string,
+  callbackUrl?: string,
+): Uint8Array {
+  if (nonce.length !== 32) {
+    throw new Error("NEP-413 nonce must be 32 bytes");
+  }
+  const callback =
+    callbackUrl === undefined
+      ? new Uint8Array([0])
+      : concatBytes([new Uint8Array([1]), borshString(callbackUrl)]);
+  return concatBytes([borshString(message), nonce, borshString(recipient), callback]);
+}
+
+/** SHA-256 of borsh(NEP413_TAG) || payload, which the NEAR wallet signs */
+export function nep413SigningHash(payload: Uint8Array): Hex {
+  const tag = new Uint8Array(4);
+  new DataView(tag.buffer).setUint32(0, NEP413_TAG, true);
+  return sha256(concatBytes([tag, payload]));
+}
+
+function borshString(value: string): Uint8Array {
+  const bytes = new TextEncoder().encode(value);
+  const out = new Uint8Array(4 + bytes.length);
+  new DataView(out.buffer).setUint32(0, bytes.length, true);
+  out.set(bytes, 4);
+  return out;
+}
+
+// ─── EIP-712 Typed Data ─────────────────────────────────────────────────────────
+// Must match contracts/permission/src/eip712.rs
+
+export const EIP712_DOMAIN_NAME = "ShadeLink Permissions";
+export const EIP712_DOMAIN_VERSION = "1";
+
+/**
+ * Stand-in verifyingContract for the NEAR permission contract:
+ * last 20 bytes of keccak256(contractId)
+ */
+export function eip712VerifyingContract(contractId: string): Address {
+  return `0x${keccak256(stringToBytes(contractId)).slice(-40)}`;
+}
+
+export const EIP712_TYPES = {
+  RegisterWallet: [
+    { name: "derivationPath", type: "string" },
+    { name: "nonce", type: "uint64" },
+  ],
+  AddOperation: [
+    { name: "derivationPath", type: "string" },
+    { name: "operation", type: "Operation" },
+    { name: "nonce", type: "uint64" },
+  ],
+  RemoveOperation: [
+    { name: "derivationPath", type: "string" },
+    { name: "operationId", type: "string" },
+    { name: "nonce", type: "uint64" },
+  ],
+  FreezePath: [
+    { name: "derivationPath", type: "string" },
+    { name: "nonce", type: "uint64" },
+  ],
+  UnfreezePath: [
+    { name: "derivationPath", type: "string" },
+    { name: "nonce", type: "uint64" },
+  ],
+  SetThreshold: [
+    { name: "derivationPath", type: "string" },
+    { name: "threshold", type: "uint8" },
+    { name: "nonce", type: "uint64" },
+  ],
+  ApproveProposal: [
+    { name: "derivationPath", type: "string" },
+    { name: "proposalId", type: "string" },
+    { name: "nonce", type: "uint64" },
+  ],
+  SetSpendingLimit: [
+    { name: "derivationPath", type: "string" },
+    { name: "asset", type: "string" },
+    { name: "limit", type: "uint256" },
+    { name: "window", type: "uint64" },
+    { name: "nonce", type: "uint64" },
+  ],
+  RemoveSpendingLimit: [
+    { name: "derivationPath", type: "string" },
+    { name: "asset", type: "string" },
+    { name: "nonce", type: "uint64" },
+  ],
+  AddDestination: [
+    { name: "derivationPath", type: "string" },
+    { name: "chain", type: "string" },
+    { name: "address", type: "string" },
+    { name: "nonce", type: "uint64" },
+  ],
+  RemoveDestination: [
+    { name: "derivationPath", type: "string" },
+    { name: "chain", type: "string" },
+    { name: "address", type: "string" },
+    { name: "nonce", type: "uint64" },
+  ],
+  LinkWallet: [
+    { name: "derivationPath", type: "string" },
+    { name: "walletType", type: "string" },
+    { name: "publicKey", type: "bytes" },
+    { name: "chainAddress", type: "string" },
+    { name: "signerAddress", type: "string" },
+    { name: "nonce", type: "uint64" },
+  ],
+  RemoveWallet: [
+    { name: "derivationPath", type: "string" },
+    { name: "walletAddress", type: "string" },
+    { name: "closePath", type: "bool" },
+    { name: "nonce", type: "uint64" },
+  ],
+  RotateWallet: [
+    { name: "derivationPath", type: "string" },
+    { name: "oldAddress", type: "string" },
+    { name: "newWalletType", type: "string" },
+    { name: "newPublicKey", type: "bytes" },
+    { name: "newChainAddress", type: "string" },
+    { name: "nonce", type: "uint64" },
+  ],
+  Operation: [
+    { name: "operationType", type: "string" },
+    { name: "priceAsset", type: "string" },
+    { name: "quoteAsset", type: "string" },
+    { name: "triggerPrice", type: "uint256" },
+    { name: "condition", type: "string" },
+    { name: "sourceAsset", type: "string" },
+    { name: "targetAsset", type: "string" },
+    { name: "maxAmount", type: "uint256" },
+    { name: "sourceChain", type: "string" },
+    { name: "destinationAddress", type: "string" },
+    { name: "destinationChain", type: "string" },
+    { name: "slippageBps", type: "uint16" },
+    { name: "expiresAt", type: "uint64" },
+    { name: "interval", type: "uint64" },
+    { name: "maxExecutions", type: "uint32" },
+    { name: "amountPerExecution", type: "uint256" },
+    { name: "startAt", type: "uint64" },
+    { name: "endAt", type: "uint64" },
+  ],
+} as const;
+
+export type Eip712Action =
+  | { type: "RegisterWallet"; derivationPath: string; nonce: number }
+  | {
+      type: "AddOperation";
+      derivationPath: string;
+      operation: AllowedOperationInput;
+      nonce: number;
+    }
+  | {
+      type: "RemoveOperation";
+      derivationPath: string;
+      operationId: string;
+      nonce: number;
+    }
+  | { type: "FreezePath"; derivationPath: string; nonce: number }
+  | { type: "UnfreezePath"; derivationPath: string; nonce: number }
+  | { type: "SetThreshold"; derivationPath: string; threshold: number; nonce: number }
+  | {
+      type: "ApproveProposal";
+      derivationPath: string;
+      proposalId: string;
+      nonce: number;
+    }
+  | {
+      type: "SetSpendingLimit";
+      derivationPath: string;
+      asset: string;
+      limit: string;
+      window: number;
+      nonce: number;
+    }
+  | { type: "RemoveSpendingLimit"; derivationPath: string; asset: string; nonce: number }
+  | {
+      type: "AddDestination" | "RemoveDestination";
+      derivationPath: string;
+      chain: string;
+      address: string;
+      nonce: number;
+    }
+  | {
+      type: "LinkWallet";
+      derivationPath: string;
+      newWallet: RegisteredWallet;
+      signerAddress: string;
+      nonce: number;
+    }
+  | {
+      type: "RemoveWallet";
+      derivationPath: string;
+      walletAddress: string;
+      closePath: boolean;
+      nonce: number;
+    }
+  | {
+      type: "RotateWallet";
+      derivationPath: string;
+      oldAddress: string;
+      newWallet: RegisteredWallet;
+      nonce: number;
+    };
+
+/** Flatten an operation into the EIP-712 Operation struct; missing fields are "" / 0 */
+function toEip712Operation(operation: AllowedOperationInput) {
+  const op = operation.operation_type;
+  const priced = op.type === "Swap" ? undefined : op;
+  return {
+    operationType: op.type,
+    priceAsset: priced?.price_asset ?? "",
+    quoteAsset: priced?.quote_asset ?? "",
+    triggerPrice: BigInt(priced?.trigger_price ?? 0),
+    condition: op.type === "LimitOrder" ? op.condition : "",
+    sourceAsset: op.source_asset,
+    targetAsset: op.target_asset,
+    maxAmount: BigInt(op.max_amount),
+    sourceChain: operation.source_chain,
+    destinationAddress: operation.destination_address,
+    destinationChain: operation.destination_chain,
+    slippageBps: operation.slippage_bps,
+    expiresAt: BigInt(operation.expires_at ?? 0),
+    interval: BigInt(operation.recurring?.interval ?? 0),
+    maxExecutions: operation.recurring?.max_executions ?? 0,
+    amountPerExecution: BigInt(operation.recurring?.amount_per_execution ?? 0),
+    startAt: BigInt(operation.recurring?.start_at ?? 0),
+    endAt: BigInt(operation.recurring?.end_at ?? 0),
+  };
+}
+
+/**
+ * Create EIP-712 typed data for an "Eip712" wallet to sign
+ * Pass the result to eth_signTypedData_v4 / viem signTypedData; the contract
+ * rebuilds it from the call arguments, so `message` may be left empty
+ */
+export function createEip712TypedData(contractId: string, action: Eip712Action) {
+  const domain = {
+    name: EIP712_DOMAIN_NAME,
+    version: EIP712_DOMAIN_VERSION,
+    verifyingContract: eip712VerifyingContract(contractId),
+  };
+  const nonce = BigInt(action.nonce);
+
+  switch (action.type) {
+    case "RegisterWallet":
+      return {
+        domain,
+        types: { RegisterWallet: EIP712_TYPES.RegisterWallet },
+        primaryType: "RegisterWallet" as const,
+        message: { derivationPath: action.derivationPath, nonce },
+      };
+    case "AddOperation":
+      return {
+        domain,
+        types: {
+          AddOperation: EIP712_TYPES.AddOperation,
+          Operation: EIP712_TYPES.Operation,
+        },
+        primaryType: "AddOperation" as const,
+        message: {
+          derivationPath: action.derivationPath,
+          operation: toEip712Operation(action.operation),
+          nonce,
+        },
+      };
+    case "RemoveOperation":
+      return {
+        domain,
+        types: { RemoveOperation: EIP712_TYPES.RemoveOperation },
+        primaryType: "RemoveOperation" as const,
+        message: {
+          derivationPath: action.derivationPath,
+          operationId: action.operationId,
+          nonce,
+        },
+      };
+    case "FreezePath":
+      return {
+        domain,
+        types: { FreezePath: EIP712_TYPES.FreezePath },
+        primaryType: "FreezePath" as const,
+        message: { derivationPath: action.derivationPath, nonce },
+      };
+    case "UnfreezePath":
+      return {
+        domain,
+        types: { UnfreezePath: EIP712_TYPES.UnfreezePath },
+        primaryType: "UnfreezePath" as const,
+        message: { derivationPath: action.derivationPath, nonce },
+      };
+    case "SetThreshold":
+      return {
+        domain,
+        types: { SetThreshold: EIP712_TYPES.SetThreshold },
+        primaryType: "SetThreshold" as const,
+        message: {
+          derivationPath: action.derivationPath,
+          threshold: action.threshold,
+          nonce,
+        },
+      };
+    case "ApproveProposal":
+      return {
+        domain,
+        types: { ApproveProposal: EIP712_TYPES.ApproveProposal },
+        primaryType: "ApproveProposal" as const,
+        message: {
+          derivationPath: action.derivationPath,
+          proposalId: action.proposalId,
+          nonce,
+        },
+      };
+    case "SetSpendingLimit":
+      return {
+        domain,
+        types: { SetSpendingLimit: EIP712_TYPES.SetSpendingLimit },
+        primaryType: "SetSpendingLimit" as const,
+        message: {
+          derivationPath: action.derivationPath,
+          asset: action.asset,
+          limit: BigInt(action.limit),
+          window: BigInt(action.window),
+          nonce,
+        },
+      };
+    case "RemoveSpendingLimit":
+      return {
+        domain,
+        types: { RemoveSpendingLimit: EIP712_TYPES.RemoveSpendingLimit },
+        primaryType: "RemoveSpendingLimit" as const,
+        message: { derivationPath: action.derivationPath, asset: action.asset, nonce },
+      };
+    case "AddDestination":
+      return {
+        domain,
+        types: { AddDestination: EIP712_TYPES.AddDestination },
+        primaryType: "AddDestination" as const,
+        message: {
+          derivationPath: action.derivationPath,
+          chain: action.chain,
+          address: action.address,
+          nonce,
+        },
+      };
+    case "RemoveDestination":
+      return {
+        domain,
+        types: { RemoveDestination: EIP712_TYPES.RemoveDestination },
+        primaryType: "RemoveDestination" as const,
+        message: {
+          derivationPath: action.derivationPath,
+          chain: action.chain,
+          address: action.address,
+          nonce,
+        },
+      };
+    case "LinkWallet":
+      return {
+        domain,
+        types: { LinkWallet: EIP712_TYPES.LinkWallet },
+        primaryType: "LinkWallet" as const,
+        message: {
+          derivationPath: action.derivationPath,
+          walletType: action.newWallet.wallet_type,
+          publicKey: bytesToHex(Uint8Array.from(action.newWallet.public_key)),
+          chainAddress: action.newWallet.chain_address,
+          signerAddress: action.signerAddress,
+          nonce,
+        },
+      };
+    case "RemoveWallet":
+      return {
+        domain,
+        types: { RemoveWallet: EIP712_TYPES.RemoveWallet },
+        primaryType: "RemoveWallet" as const,
+        message: {
+          derivationPath: action.derivationPath,
+          walletAddress: action.walletAddress,
+          closePath: action.closePath,
+          nonce,
+        },
+      };
+    case "RotateWallet":
+      return {
+        domain,
+        types: { RotateWallet: EIP712_TYPES.RotateWallet },
+        primaryType: "RotateWallet" as const,
+        message: {
+          derivationPath: action.derivationPath,
+          oldAddress: action.oldAddress,
+          newWalletType: action.newWallet.wallet_type,
+          newPublicKey: bytesToHex(Uint8Array.from(action.newWallet.public_key)),
+          newChainAddress: action.newWallet.chain_address,
+          nonce,
+        },
+      };
+  }
+}